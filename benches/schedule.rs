@@ -0,0 +1,50 @@
+// Schedule throughput, run with `cargo bench --features harness`
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use gen::fuzz;
+use gen::harness::GameHarness;
+use gen::logging::Verbosity;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// Same seed every run so each sample plays the same game
+const SEED: u64 = 42;
+
+// The first tick builds the schedule, which is left out of the measurement
+fn new_game() -> (GameHarness, StdRng) {
+    let mut harness = GameHarness::new(&[6, 6, 1, 1]);
+    harness.set_verbosity(Verbosity::Quiet).tick();
+    (harness, StdRng::seed_from_u64(SEED))
+}
+
+// Random players against each other for a number of full turns
+fn simulated_turns(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulated_turns");
+    for turns in [1, 4, 16] {
+        // Ticks per game, so the report reads as ticks per second
+        let (mut harness, mut rng) = new_game();
+        let start = harness.ticks();
+        fuzz::play_turns(&mut harness, turns, &mut rng);
+        group.throughput(Throughput::Elements((harness.ticks() - start) as u64));
+
+        group.bench_with_input(BenchmarkId::from_parameter(turns), &turns, |b, turns| {
+            // Games are set up and dropped outside the measurement
+            b.iter_batched_ref(
+                new_game,
+                |(harness, rng)| fuzz::play_turns(harness, *turns, rng),
+                BatchSize::SmallInput
+            )
+        });
+    }
+    group.finish();
+}
+
+// A tick while everyone is waiting on a player, which should do next to nothing
+fn idle_tick(c: &mut Criterion) {
+    let (mut harness, _) = new_game();
+    c.bench_function("idle_tick", |b| b.iter(|| {
+        harness.tick();
+    }));
+}
+
+criterion_group!(benches, simulated_turns, idle_tick);
+criterion_main!(benches);
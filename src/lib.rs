@@ -0,0 +1,10652 @@
+// Notes:
+// For hero/ card abilities, we should probably write all the systems into one file and then have
+// the cards reference those systems.
+//  E.g.: Dash IE's ability will be a system that runs at the start of the game. If a hero is
+//  DashIE, they can choose an item. If not, the system continues. That could lead to cumbersome
+//  checks though.
+//  
+//  Another example is Crank: How do we evaluate this? A card could have a keyword section, and
+//  upon the event of a card entering the field, we could run the crank system
+
+#![allow(dead_code)]
+
+use std::{collections::{HashMap, HashSet, VecDeque}, ops::Sub, io};
+use serde::{Deserialize, Serialize};
+
+use bevy_ecs::prelude::*;
+use logging::{GameLog, LogEvent};
+use clock::GameClock;
+use dice::Dice;
+
+// Per-subsystem logging verbosity, settable while the game runs
+pub mod logging {
+    use bevy_ecs::prelude::*;
+    use serde::{Deserialize, Serialize};
+    use crate::clock::GameClock;
+
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+    pub enum Subsystem {
+        Priority,
+        Actions,
+        Triggers,
+        Combat,
+        Zones,
+        Phases
+    }
+
+    pub const SUBSYSTEMS: [Subsystem; 6] = [
+        Subsystem::Priority,
+        Subsystem::Actions,
+        Subsystem::Triggers,
+        Subsystem::Combat,
+        Subsystem::Zones,
+        Subsystem::Phases
+    ];
+
+    #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+    pub enum Verbosity {
+        Quiet,
+        Normal,
+        Debug
+    }
+
+    impl Subsystem {
+        pub fn parse(name: &str) -> Option<Self> {
+            SUBSYSTEMS.into_iter().find(|s| format!("{:?}", s).eq_ignore_ascii_case(name))
+        }
+    }
+
+    impl Verbosity {
+        pub fn parse(name: &str) -> Option<Self> {
+            match name.to_lowercase().as_str() {
+                "quiet" => Some(Verbosity::Quiet),
+                "normal" => Some(Verbosity::Normal),
+                "debug" => Some(Verbosity::Debug),
+                _ => None
+            }
+        }
+    }
+
+    // What happened, with names captured when it happened
+    #[derive(Clone, Debug, PartialEq, Serialize)]
+    pub enum LogEvent {
+        CardPlayed {
+            #[serde(with = "crate::entity_ids")]
+            card: Entity,
+            name: String,
+            targets: Vec<String>
+        },
+        DamageDealt {
+            #[serde(with = "crate::entity_ids")]
+            hero: Entity,
+            name: String,
+            amount: u16,
+            life: u16
+        },
+        // Damage absorbed by prevention shields before it was dealt
+        DamagePrevented {
+            #[serde(with = "crate::entity_ids")]
+            hero: Entity,
+            name: String,
+            amount: u16,
+            incoming: u16
+        },
+        PhaseChanged { phase: super::GamePhases },
+        PriorityPassed { name: String },
+        // Link numbers count from 1 within the current combat chain
+        AttackDeclared {
+            link: usize,
+            #[serde(with = "crate::entity_ids")]
+            attack: Entity,
+            #[serde(with = "crate::entity_ids::list")]
+            targets: Vec<Entity>
+        },
+        LinkClosed { link: usize, hit: bool },
+        StepChanged { step: super::CombatSteps },
+        // Resources pitched toward a play that were left floating once it was paid for
+        OverPitched {
+            #[serde(with = "crate::entity_ids")]
+            card: Entity,
+            name: String,
+            amount: u16
+        },
+        // Card paid for at something other than its printed cost
+        CostModified {
+            #[serde(with = "crate::entity_ids")]
+            card: Entity,
+            name: String,
+            printed: u16,
+            cost: u16
+        },
+        // Floating resources lost without being spent
+        ResourcesWasted {
+            #[serde(with = "crate::entity_ids")]
+            hero: Entity,
+            name: String,
+            amount: u16
+        },
+    }
+
+    impl LogEvent {
+        fn subsystem(&self) -> Subsystem {
+            match self {
+                LogEvent::CardPlayed { .. } => Subsystem::Actions,
+                LogEvent::DamageDealt { .. } => Subsystem::Combat,
+                LogEvent::DamagePrevented { .. } => Subsystem::Combat,
+                LogEvent::PhaseChanged { .. } => Subsystem::Phases,
+                LogEvent::PriorityPassed { .. } => Subsystem::Priority,
+                LogEvent::AttackDeclared { .. } => Subsystem::Combat,
+                LogEvent::LinkClosed { .. } => Subsystem::Combat,
+                LogEvent::StepChanged { .. } => Subsystem::Combat,
+                LogEvent::OverPitched { .. } => Subsystem::Actions,
+                LogEvent::CostModified { .. } => Subsystem::Actions,
+                LogEvent::ResourcesWasted { .. } => Subsystem::Actions,
+            }
+        }
+
+        // Step changes are only printed when debugging combat
+        fn level(&self) -> Verbosity {
+            match self {
+                LogEvent::StepChanged { .. } => Verbosity::Debug,
+                _ => Verbosity::Normal
+            }
+        }
+
+        // Moments a frontend animates, sent to players as they happen
+        pub fn beat(&self) -> Option<&'static str> {
+            match self {
+                LogEvent::AttackDeclared { .. } => Some("attack_declared"),
+                LogEvent::DamageDealt { .. } => Some("damage_dealt"),
+                LogEvent::DamagePrevented { .. } => Some("damage_prevented"),
+                LogEvent::LinkClosed { .. } => Some("link_closed"),
+                _ => None
+            }
+        }
+
+        pub fn render(&self) -> String {
+            match self {
+                LogEvent::CardPlayed { name, targets, .. } if targets.is_empty() =>
+                    format!("Card \"{}\" played", name),
+                LogEvent::CardPlayed { name, targets, .. } =>
+                    format!("Card \"{}\" played, targeting \"{}\"", name, targets.join("\", \"")),
+                LogEvent::DamageDealt { name, amount, life, .. } =>
+                    format!("{} taking {} damage, going to {}", name, amount, life),
+                LogEvent::DamagePrevented { name, amount, incoming, .. } =>
+                    format!("{} of {} damage to {} prevented", amount, incoming, name),
+                LogEvent::PhaseChanged { phase } => match phase {
+                    super::GamePhases::StartPhase => String::from("Starting start phase"),
+                    super::GamePhases::ActionPhase => String::from("Starting action phase"),
+                    super::GamePhases::EndPhase => String::from("Starting end phase"),
+                },
+                LogEvent::PriorityPassed { name } => format!("\"{}\" passed priority", name),
+                LogEvent::AttackDeclared { .. } => String::from("Attack added to the chain"),
+                LogEvent::LinkClosed { link, hit: true } => format!("Chain link {} closed, the attack hit", link),
+                LogEvent::LinkClosed { link, hit: false } => format!("Chain link {} closed", link),
+                LogEvent::StepChanged { step } => format!("Moving to {}", step.name()),
+                LogEvent::OverPitched { name, amount, .. } =>
+                    format!("Over-pitched by {} for \"{}\"", amount, name),
+                LogEvent::CostModified { name, printed, cost, .. } =>
+                    format!("\"{}\" costs {} instead of {}", name, cost, printed),
+                LogEvent::ResourcesWasted { name, amount, .. } =>
+                    format!("\"{}\" wasted {} resource(s)", name, amount),
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct LogEntry {
+        // Position in the log, so entries keep their order and id however they are sent
+        pub id: usize,
+        // Milliseconds since the Unix epoch
+        pub timestamp: u128,
+        // Hero responsible, if any
+        #[serde(with = "crate::entity_ids::option")]
+        pub actor: Option<Entity>,
+        pub event: LogEvent
+    }
+
+    // Every notable thing that happened this game, in order
+    // Each game keeps its own verbosity, so games sharing a process do not affect each other
+    #[derive(Resource)]
+    pub struct GameLog {
+        pub entries: Vec<LogEntry>,
+        // Rendered lines the verbosity let through, left for the frontend to show
+        pub output: Vec<String>,
+        levels: [Verbosity; 6]
+    }
+
+    impl Default for GameLog {
+        fn default() -> Self {
+            GameLog { entries: Vec::new(), output: Vec::new(), levels: [Verbosity::Normal; 6] }
+        }
+    }
+
+    impl GameLog {
+        // Records the entry and renders it for the frontend
+        pub fn record(&mut self, clock: &GameClock, actor: Option<Entity>, event: LogEvent) {
+            if self.enabled(event.subsystem(), event.level()) {
+                self.output.push(event.render());
+            }
+            self.entries.push(LogEntry { id: self.entries.len(), timestamp: clock.now(), actor, event });
+        }
+
+        // Adds a line of output, already checked against the verbosity
+        pub fn write(&mut self, line: String) {
+            self.output.push(line);
+        }
+
+        // Lines of output written from the given position on
+        pub fn output_since(&self, position: usize) -> &[String] {
+            self.output.get(position..).unwrap_or_default()
+        }
+
+        pub fn set(&mut self, subsystem: Subsystem, level: Verbosity) {
+            self.levels[subsystem as usize] = level;
+        }
+
+        pub fn set_all(&mut self, level: Verbosity) {
+            self.levels = [level; 6];
+        }
+
+        pub fn level(&self, subsystem: Subsystem) -> Verbosity {
+            self.levels[subsystem as usize]
+        }
+
+        // Messages print when the subsystem is at least as verbose as the message
+        pub fn enabled(&self, subsystem: Subsystem, level: Verbosity) -> bool {
+            level != Verbosity::Quiet && level <= self.level(subsystem)
+        }
+
+        // Beat markers recorded from the given log position on
+        pub fn beats_since(&self, position: usize) -> Vec<serde_json::Value> {
+            self.entries
+                .iter()
+                .skip(position)
+                .filter_map(|entry| entry.event.beat().map(|kind| serde_json::json!({
+                    "id": entry.id,
+                    "kind": kind,
+                    "entry": entry
+                })))
+                .collect()
+        }
+    }
+}
+
+// Writes a line to the game's log output, gated on the subsystem's verbosity
+// The line is formatted before the log is borrowed again to write it
+macro_rules! game_log {
+    ($log:expr, $subsystem:ident, $level:ident, $($arg:tt)*) => {
+        if $log.enabled($crate::logging::Subsystem::$subsystem, $crate::logging::Verbosity::$level) {
+            let line = format!($($arg)*);
+            $log.write(line);
+        }
+    };
+}
+
+pub mod clock {
+    use bevy_ecs::prelude::*;
+    use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+
+    // Where the game reads the current time from
+    pub trait Clock: Send + Sync {
+        // Milliseconds since the Unix epoch
+        fn now(&self) -> u128;
+    }
+
+    // System time is unavailable on wasm32-unknown-unknown, where the frontend supplies a clock
+    #[cfg(feature = "cli")]
+    pub struct SystemClock;
+
+    #[cfg(feature = "cli")]
+    impl Clock for SystemClock {
+        fn now(&self) -> u128 {
+            use std::time::{SystemTime, UNIX_EPOCH};
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|v| v.as_millis())
+                .unwrap_or(0)
+        }
+    }
+
+    // Time only moves when told to
+    // Clones share the same time, so a copy kept outside the world can advance it
+    #[derive(Clone, Default)]
+    pub struct FrozenClock(Arc<AtomicU64>);
+
+    impl FrozenClock {
+        pub fn at(millis: u64) -> Self {
+            FrozenClock(Arc::new(AtomicU64::new(millis)))
+        }
+
+        pub fn advance(&self, millis: u64) {
+            self.0.fetch_add(millis, Ordering::Relaxed);
+        }
+    }
+
+    impl Clock for FrozenClock {
+        fn now(&self) -> u128 {
+            self.0.load(Ordering::Relaxed) as u128
+        }
+    }
+
+    #[derive(Resource)]
+    pub struct GameClock(pub Box<dyn Clock>);
+
+    #[cfg(feature = "cli")]
+    impl Default for GameClock {
+        fn default() -> Self {
+            GameClock(Box::new(SystemClock))
+        }
+    }
+
+    // Without the system clock every entry is stamped 0 until the frontend inserts its own
+    #[cfg(not(feature = "cli"))]
+    impl Default for GameClock {
+        fn default() -> Self {
+            GameClock(Box::new(FrozenClock::default()))
+        }
+    }
+
+    impl GameClock {
+        pub fn now(&self) -> u128 {
+            self.0.now()
+        }
+    }
+}
+
+pub mod dice {
+    use bevy_ecs::prelude::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+    use std::collections::VecDeque;
+
+    // Where every random outcome in the game comes from
+    pub trait RandomSource: Send + Sync {
+        // Rolls a die numbered from 1 to sides
+        fn roll(&mut self, sides: u32) -> u32;
+        fn shuffle(&mut self, cards: &mut VecDeque<Entity>);
+    }
+
+    // OS randomness, which wasm32-unknown-unknown does not have
+    #[cfg(feature = "cli")]
+    pub struct ThreadRandom;
+
+    #[cfg(feature = "cli")]
+    impl RandomSource for ThreadRandom {
+        fn roll(&mut self, sides: u32) -> u32 {
+            rand::thread_rng().gen_range(1..=sides)
+        }
+
+        fn shuffle(&mut self, cards: &mut VecDeque<Entity>) {
+            cards.make_contiguous().shuffle(&mut rand::thread_rng());
+        }
+    }
+
+    // Random from a seed the frontend chooses, e.g. from the browser's crypto API
+    pub struct SeededRandom(StdRng);
+
+    impl SeededRandom {
+        pub fn new(seed: u64) -> Self {
+            SeededRandom(StdRng::seed_from_u64(seed))
+        }
+    }
+
+    impl RandomSource for SeededRandom {
+        fn roll(&mut self, sides: u32) -> u32 {
+            self.0.gen_range(1..=sides)
+        }
+
+        fn shuffle(&mut self, cards: &mut VecDeque<Entity>) {
+            cards.make_contiguous().shuffle(&mut self.0);
+        }
+    }
+
+    // Predetermined rolls, in order, so a game plays out the same way every time
+    // Shuffles leave cards where they are
+    pub struct ScriptedRolls(VecDeque<u32>);
+
+    impl ScriptedRolls {
+        pub fn new(rolls: impl IntoIterator<Item = u32>) -> Self {
+            ScriptedRolls(rolls.into_iter().collect())
+        }
+
+        // Parses a comma separated list, e.g. "6,6,1,2"
+        pub fn parse(rolls: &str) -> Result<Self, String> {
+            rolls
+                .split(',')
+                .map(|v| v.trim().parse::<u32>().map_err(|_| format!("Invalid roll \"{}\"", v)))
+                .collect::<Result<Vec<u32>, String>>()
+                .map(Self::new)
+        }
+    }
+
+    impl RandomSource for ScriptedRolls {
+        fn roll(&mut self, sides: u32) -> u32 {
+            let roll = self.0.pop_front().expect("Ran out of scripted rolls");
+            assert!((1..=sides).contains(&roll), "Scripted roll {} does not fit a d{}", roll, sides);
+            roll
+        }
+
+        fn shuffle(&mut self, _cards: &mut VecDeque<Entity>) {}
+    }
+
+    #[derive(Resource)]
+    pub struct Dice(pub Box<dyn RandomSource>);
+
+    #[cfg(feature = "cli")]
+    impl Default for Dice {
+        fn default() -> Self {
+            Dice(Box::new(ThreadRandom))
+        }
+    }
+
+    // Every game plays out the same until the frontend inserts seeded dice
+    #[cfg(not(feature = "cli"))]
+    impl Default for Dice {
+        fn default() -> Self {
+            Dice(Box::new(SeededRandom::new(0)))
+        }
+    }
+
+    impl Dice {
+        pub fn roll(&mut self, sides: u32) -> u32 {
+            self.0.roll(sides)
+        }
+
+        pub fn shuffle(&mut self, cards: &mut VecDeque<Entity>) {
+            self.0.shuffle(cards);
+        }
+    }
+}
+
+// When an effect entity stops applying
+#[derive(Component, Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Expires {
+    EndOfTurn,
+    EndOfCombatChain,
+    // Lasts until the next attack resolves
+    NextAttack
+}
+
+// Cost to play card
+#[derive(Component, Serialize, Deserialize)]
+pub struct Cost(u16);
+
+// Life paid on top of the resource cost to play card
+#[derive(Component, Serialize, Deserialize)]
+pub struct LifeCost(u16);
+
+// Card color
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Color {
+    Red,
+    Yellow,
+    Blue
+}
+
+impl Color {
+    fn pitch(&self) -> u16 {
+        match &self {
+            Color::Red => 1,
+            Color::Yellow => 2,
+            Color::Blue => 3
+        }
+    }
+}
+
+// Modifies the resources gained when a card is pitched
+#[derive(Component, Serialize, Deserialize)]
+pub enum PitchModifier {
+    // Changes the pitch value of a single card
+    Card { card: Entity, amount: i16 },
+    // Every card pitched by the hero gains the amount
+    Hero { hero: Entity, amount: i16 },
+}
+
+impl PitchModifier {
+    fn applies_to(&self, hero: Entity, card: Entity) -> bool {
+        match &self {
+            PitchModifier::Card { card: target, .. } => *target == card,
+            PitchModifier::Hero { hero: target, .. } => *target == hero,
+        }
+    }
+
+    fn amount(&self) -> i16 {
+        match &self {
+            PitchModifier::Card { amount, .. } => *amount,
+            PitchModifier::Hero { amount, .. } => *amount,
+        }
+    }
+}
+
+// Pitch value of a card after applying all active modifiers
+// Pitch can never go below zero
+pub fn modified_pitch<'a>(
+    color: &Color,
+    hero: Entity,
+    card: Entity,
+    modifiers: impl Iterator<Item = &'a PitchModifier>
+) -> u16 {
+    let bonus: i16 = modifiers
+        .filter(|modifier| modifier.applies_to(hero, card))
+        .map(|modifier| modifier.amount())
+        .sum();
+    (color.pitch() as i16 + bonus).max(0) as u16
+}
+
+// Changes what a card costs to play, applied before the cost is paid
+#[derive(Component, Serialize, Deserialize)]
+pub enum CostModifier {
+    // Cards played by the permanent's controller, for as long as the permanent is in play
+    Static { permanent: Entity, amount: i16 },
+    // The hero's next attack, used up once that attack is paid for
+    NextAttack { hero: Entity, amount: i16 },
+}
+
+impl CostModifier {
+    // in_play gives a permanent's controller while it is in play
+    fn applies_to(&self, hero: Entity, attack: bool, in_play: &impl Fn(Entity) -> Option<Entity>) -> bool {
+        match &self {
+            CostModifier::Static { permanent, .. } => in_play(*permanent) == Some(hero),
+            CostModifier::NextAttack { hero: target, .. } => attack && *target == hero,
+        }
+    }
+
+    fn amount(&self) -> i16 {
+        match &self {
+            CostModifier::Static { amount, .. } => *amount,
+            CostModifier::NextAttack { amount, .. } => *amount,
+        }
+    }
+}
+
+// Cost of a card after applying all active modifiers
+// Cost can never go below zero
+pub fn modified_cost<'a>(
+    base: u16,
+    hero: Entity,
+    attack: bool,
+    modifiers: impl Iterator<Item = &'a CostModifier>,
+    in_play: impl Fn(Entity) -> Option<Entity>
+) -> u16 {
+    let change: i16 = modifiers
+        .filter(|modifier| modifier.applies_to(hero, attack, &in_play))
+        .map(|modifier| modifier.amount())
+        .sum();
+    (base as i16 + change).max(0) as u16
+}
+
+// When floating resources are lost
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum ResourcePolicy {
+    // Resources float until the end of the turn
+    #[default]
+    EndOfTurn,
+    // Resources are also lost whenever a chain link closes
+    EndOfChainLink,
+}
+
+impl ResourcePolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "turn" => Some(ResourcePolicy::EndOfTurn),
+            "link" => Some(ResourcePolicy::EndOfChainLink),
+            _ => None
+        }
+    }
+}
+
+// Format the game is played in
+// It decides how old the heroes are and which deck lists are legal
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    #[default]
+    Constructed,
+    // Young heroes and smaller decks
+    Blitz,
+    // Adult heroes and any size of deck
+    Casual,
+    // Young heroes and Blitz sized decks of commons only
+    Commoner,
+}
+
+impl GameMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "constructed" => Some(GameMode::Constructed),
+            "blitz" => Some(GameMode::Blitz),
+            "casual" => Some(GameMode::Casual),
+            "commoner" => Some(GameMode::Commoner),
+            _ => None
+        }
+    }
+
+    pub fn hero_age(&self) -> HeroAge {
+        match self {
+            GameMode::Blitz | GameMode::Commoner => HeroAge::Young,
+            GameMode::Constructed | GameMode::Casual => HeroAge::Adult
+        }
+    }
+}
+
+// Numbers the game starts from and the format, set before the heroes are spawned
+// Loaded from a file and then changed by flags, e.g. --config blitz.json --life 30
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub format: GameMode,
+    // Every hero starts with this much life, instead of the life on their card for their age
+    pub life: Option<u16>,
+    pub intellect: u16,
+    // Action points the turn player starts their action phase with
+    pub action_points: u16,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            format: GameMode::default(),
+            life: None,
+            intellect: 4,
+            action_points: 1,
+        }
+    }
+}
+
+impl GameConfig {
+    // JSON with any of the fields, e.g. {"format": "commoner", "intellect": 3}
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read \"{}\": {}", path, err))?;
+        serde_json::from_str(&contents).map_err(|err| format!("Invalid game config \"{}\": {}", path, err))
+    }
+
+    // Changes one setting from a command line flag, returning false for flags it does not know
+    pub fn set(&mut self, flag: &str, value: &str) -> Result<bool, String> {
+        let number = || value
+            .parse::<u16>()
+            .map_err(|_| format!("{} must be a number, got \"{}\"", flag, value));
+        match flag {
+            "--format" => {
+                self.format = GameMode::parse(value).ok_or(format!("Unknown format \"{}\"", value))?;
+            },
+            "--life" => self.life = Some(number()?),
+            "--intellect" => self.intellect = number()?,
+            "--action-points" => self.action_points = number()?,
+            _ => return Ok(false)
+        }
+        Ok(true)
+    }
+
+    // Gives a newly spawned hero the configured life and intellect
+    fn apply(&self, world: &mut World, hero: Entity) {
+        let mut hero = world.entity_mut(hero);
+        if let Some(life) = self.life {
+            hero.insert(Health(life));
+        }
+        hero.insert(Intellect(self.intellect));
+    }
+}
+
+// Whether teammates pool their life or keep their own
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum LifeSharing {
+    #[default]
+    Separate,
+    // Teammates start with their life added together and lose or gain it as one
+    Shared,
+}
+
+// How the heroes are split up, set before the heroes are spawned
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum TeamPlay {
+    // One hero against another
+    #[default]
+    Solo,
+    // Two teams of two, taking turns in alternation
+    TwoVsTwo(LifeSharing),
+}
+
+impl TeamPlay {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "solo" => Some(TeamPlay::Solo),
+            "2v2" => Some(TeamPlay::TwoVsTwo(LifeSharing::Separate)),
+            "2v2-shared" => Some(TeamPlay::TwoVsTwo(LifeSharing::Shared)),
+            _ => None
+        }
+    }
+}
+
+// Heroes on the same team block for each other and cannot attack each other
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Team(pub u8);
+
+// Heroes without a team only side with themselves
+pub fn same_team(first: Entity, second: Entity, teams: &Query<&Team>) -> bool {
+    first == second
+        || matches!((teams.get(first), teams.get(second)), (Ok(a), Ok(b)) if a == b)
+}
+
+// Life totals of the teams sharing their life, as of the last time they were evened out
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct SharedLife(HashMap<u8, u16>);
+
+// Keeps some of a hero's floating resources when they would be lost, used up as it keeps them
+#[derive(Component, Serialize, Deserialize)]
+pub struct BankedResources {
+    hero: Entity,
+    amount: u16
+}
+
+// Empties a hero's floating resources, keeping what their banks hold back
+// Used up banks are despawned
+pub fn clear_resources(
+    hero: Entity,
+    resources: &mut Resources,
+    bank_query: &mut Query<(Entity, &mut BankedResources)>,
+    commands: &mut Commands,
+    lost: &mut EventWriter<ResourcesLost>,
+    log: &mut GameLog
+) {
+    if resources.0 == 0 {
+        return;
+    }
+
+    let mut kept = 0;
+    for (entity, mut bank) in bank_query.iter_mut() {
+        if bank.hero != hero || kept == resources.0 {
+            continue;
+        }
+        let amount = bank.amount.min(resources.0 - kept);
+        kept += amount;
+        bank.amount -= amount;
+        if bank.amount == 0 {
+            commands.entity(entity).despawn();
+        }
+    }
+    if kept > 0 {
+        game_log!(log, Actions, Normal, "{} resource(s) banked", kept);
+    }
+
+    let amount = resources.0 - kept;
+    resources.0 = kept;
+    if amount > 0 {
+        lost.send(ResourcesLost { hero, amount });
+    }
+}
+
+// Changes damage dealt to a hero before it is applied
+#[derive(Component, Serialize, Deserialize)]
+pub enum DamageModifier {
+    // Prevents the next damage dealt to the hero, used up as it prevents
+    // Shields absorb damage in the order they were registered, see PreventionCommands
+    Prevent {
+        hero: Entity,
+        amount: u16,
+        #[serde(default)]
+        order: u32
+    },
+    // Every instance of damage dealt to the hero is increased
+    Increase { hero: Entity, amount: u16 },
+    // The next instance of damage dealt to the hero is dealt to another hero instead
+    Redirect { from: Entity, to: Entity },
+}
+
+// Deals damage to a hero after redirection, increases, then prevention
+// Used up modifiers are despawned
+// Returns the hero that was dealt damage and how much
+pub fn apply_damage(
+    target: Entity,
+    amount: u16,
+    modifier_query: &mut Query<(Entity, &mut DamageModifier)>,
+    health_query: &mut Query<(&CardName, &mut Health)>,
+    commands: &mut Commands,
+    log: &mut GameLog,
+    clock: &GameClock
+) -> (Entity, u16) {
+    // Redirects only apply once so damage cannot bounce between heroes
+    let mut target = target;
+    if let Some((entity, to)) = modifier_query
+        .iter()
+        .find_map(|(entity, modifier)| match *modifier {
+            DamageModifier::Redirect { from, to } if from == target => Some((entity, to)),
+            _ => None
+        })
+    {
+        commands.entity(entity).despawn();
+        target = to;
+    }
+
+    // Stacked increases stop at the most damage there can be rather than overflowing
+    let mut amount = amount.saturating_add(modifier_query
+        .iter()
+        .filter_map(|(_, modifier)| match *modifier {
+            DamageModifier::Increase { hero, amount } if hero == target => Some(amount),
+            _ => None
+        })
+        .fold(0, u16::saturating_add));
+
+    // Shields take what they can in turn, a partly used shield keeps the rest
+    let mut shields = modifier_query
+        .iter()
+        .filter_map(|(entity, modifier)| match *modifier {
+            DamageModifier::Prevent { hero, order, .. } if hero == target => Some((order, entity)),
+            _ => None
+        })
+        .collect::<Vec<(u32, Entity)>>();
+    shields.sort();
+    let incoming = amount;
+    for (_, entity) in shields {
+        if amount == 0 {
+            break;
+        }
+        let Ok((_, mut modifier)) = modifier_query.get_mut(entity) else {
+            continue;
+        };
+        if let DamageModifier::Prevent { amount: ref mut shield, .. } = *modifier {
+            let prevented = amount.min(*shield);
+            amount -= prevented;
+            *shield -= prevented;
+            game_log!(log, Combat, Debug, "Shield prevents {}, {} left on it", prevented, *shield);
+            if *shield == 0 {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+    if amount < incoming {
+        if let Ok((name, _)) = health_query.get(target) {
+            log.record(clock, None, LogEvent::DamagePrevented {
+                hero: target,
+                name: name.0.clone(),
+                amount: incoming - amount,
+                incoming
+            });
+        }
+    }
+
+    // Overkill damage is still dealt in full, the target just stops at zero
+    if let Ok((name, mut health)) = health_query.get_mut(target) {
+        health.lose(amount);
+        log.record(clock, None, LogEvent::DamageDealt {
+            hero: target,
+            name: name.0.clone(),
+            amount,
+            life: health.0
+        });
+    }
+    (target, amount)
+}
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct GoAgain;
+
+// Attack power
+#[derive(Component, Serialize, Deserialize)]
+pub struct Attack(u16);
+
+// Def
+#[derive(Component, Serialize, Deserialize)]
+pub struct Defense(u16);
+
+// Kinds of counters that can be placed on a card or hero
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum CounterKind {
+    // Each adds 1 to the attack power of the card it is on
+    PlusOneAttack,
+    Steam,
+}
+
+// Counters accumulated on an entity
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct Counters(HashMap<CounterKind, u32>);
+
+impl Counters {
+    fn count(&self, kind: CounterKind) -> u32 {
+        self.0.get(&kind).copied().unwrap_or(0)
+    }
+
+    fn add(&mut self, kind: CounterKind, amount: u32) {
+        *self.0.entry(kind).or_insert(0) += amount;
+    }
+
+    // Removes up to the amount and returns how many were removed
+    fn remove(&mut self, kind: CounterKind, amount: u32) -> u32 {
+        let removed = self.count(kind).min(amount);
+        if removed == self.count(kind) {
+            self.0.remove(&kind);
+        } else {
+            self.0.insert(kind, self.count(kind) - removed);
+        }
+        removed
+    }
+
+    // Removes exactly the amount, or nothing if there are not enough
+    fn spend(&mut self, kind: CounterKind, amount: u32) -> bool {
+        if self.count(kind) < amount {
+            return false;
+        }
+        self.remove(kind, amount);
+        true
+    }
+}
+
+// Deferred counter changes for systems that do not query Counters themselves
+pub trait CounterCommands {
+    fn add_counters(&mut self, entity: Entity, kind: CounterKind, amount: u32);
+    fn remove_counters(&mut self, entity: Entity, kind: CounterKind, amount: u32);
+    fn spend_counters(&mut self, entity: Entity, kind: CounterKind, amount: u32);
+}
+
+impl CounterCommands for Commands<'_, '_> {
+    fn add_counters(&mut self, entity: Entity, kind: CounterKind, amount: u32) {
+        self.add(move |world: &mut World| {
+            if let Some(mut entity) = world.get_entity_mut(entity) {
+                if let Some(mut counters) = entity.get_mut::<Counters>() {
+                    counters.add(kind, amount);
+                } else {
+                    let mut counters = Counters::default();
+                    counters.add(kind, amount);
+                    entity.insert(counters);
+                }
+                game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "{} {:?} counter(s) added", amount, kind);
+            }
+        });
+    }
+
+    fn remove_counters(&mut self, entity: Entity, kind: CounterKind, amount: u32) {
+        self.add(move |world: &mut World| {
+            if let Some(mut counters) = world.get_mut::<Counters>(entity) {
+                let removed = counters.remove(kind, amount);
+                game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "{} {:?} counter(s) removed", removed, kind);
+            }
+        });
+    }
+
+    fn spend_counters(&mut self, entity: Entity, kind: CounterKind, amount: u32) {
+        self.add(move |world: &mut World| {
+            let spent = world
+                .get_mut::<Counters>(entity)
+                .map(|mut counters| counters.spend(kind, amount))
+                .unwrap_or(false);
+            if spent {
+                game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "{} {:?} counter(s) spent", amount, kind);
+            } else {
+                game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Not enough {:?} counters to spend {}", kind, amount);
+            }
+        });
+    }
+}
+
+// Deferred damage prevention
+// Each shield is registered after every shield already there, and absorbs damage in that order
+pub trait PreventionCommands {
+    fn register_shield(&mut self, hero: Entity, amount: u16, expires: Expires);
+}
+
+impl PreventionCommands for Commands<'_, '_> {
+    fn register_shield(&mut self, hero: Entity, amount: u16, expires: Expires) {
+        self.add(move |world: &mut World| {
+            let order = world
+                .query::<&DamageModifier>()
+                .iter(world)
+                .filter_map(|v| match *v {
+                    DamageModifier::Prevent { order, .. } => Some(order + 1),
+                    _ => None
+                })
+                .max()
+                .unwrap_or(0);
+            world.spawn((DamageModifier::Prevent { hero, amount, order }, expires));
+            let name = world.get::<CardName>(hero).map(|v| v.0.clone()).unwrap_or_default();
+            game_log!(world.resource_mut::<GameLog>(), Combat, Normal, "\"{}\" is shielded from the next {} damage", name, amount);
+        });
+    }
+}
+
+// Deferred life changes that fire the life triggers
+pub trait LifeCommands {
+    fn gain_life(&mut self, entity: Entity, amount: u16);
+    fn lose_life(&mut self, entity: Entity, amount: u16);
+    fn pay_life(&mut self, entity: Entity, amount: u16);
+}
+
+impl LifeCommands for Commands<'_, '_> {
+    fn gain_life(&mut self, entity: Entity, amount: u16) {
+        self.add(move |world: &mut World| {
+            let Some(mut health) = world.get_mut::<Health>(entity) else {
+                return;
+            };
+            health.gain(amount);
+            let life = health.0;
+            game_log!(world.resource_mut::<GameLog>(), Triggers, Normal, "{} life gained, going to {}", amount, life);
+            world.send_event(LifeGained { entity, amount });
+        });
+    }
+
+    // Only heroes lose life, and never below zero
+    fn lose_life(&mut self, entity: Entity, amount: u16) {
+        self.add(move |world: &mut World| {
+            if world.get::<Hero>(entity).is_none() {
+                return;
+            }
+            let Some(mut health) = world.get_mut::<Health>(entity) else {
+                return;
+            };
+            let lost = health.lose(amount);
+            let name = world.get::<CardName>(entity).map(|v| v.0.clone()).unwrap_or_default();
+            game_log!(world.resource_mut::<GameLog>(), Triggers, Normal, "{} loses {} life.", name, lost);
+            world.send_event(LifeLost { entity, amount: lost });
+        });
+    }
+
+    // Life can only be paid while there is enough of it
+    fn pay_life(&mut self, entity: Entity, amount: u16) {
+        self.add(move |world: &mut World| {
+            let Some(mut health) = world.get_mut::<Health>(entity) else {
+                return;
+            };
+            if health.0 < amount {
+                game_log!(world.resource_mut::<GameLog>(), Triggers, Normal, "Not enough life to pay {}", amount);
+                return;
+            }
+            health.lose(amount);
+            let life = health.0;
+            game_log!(world.resource_mut::<GameLog>(), Triggers, Normal, "{} life paid, going to {}", amount, life);
+            world.send_event(LifeLost { entity, amount });
+        });
+    }
+}
+
+// Effect entity showing a card in hand to other players until it expires
+// A card stops being revealed once it leaves the hand
+#[derive(Component, Serialize, Deserialize)]
+pub struct Revealed {
+    card: Entity,
+    // Every player sees the card when None
+    to: Option<Entity>
+}
+
+// Deferred reveals, for effects that show cards from a hand
+pub trait RevealCommands {
+    fn reveal(&mut self, card: Entity, to: Option<Entity>, expires: Expires);
+    fn reveal_hand(&mut self, hero: Entity, to: Option<Entity>, expires: Expires);
+}
+
+impl RevealCommands for Commands<'_, '_> {
+    fn reveal(&mut self, card: Entity, to: Option<Entity>, expires: Expires) {
+        self.add(move |world: &mut World| {
+            let name = world.get::<CardName>(card).map(|v| v.0.clone()).unwrap_or_default();
+            world.spawn((Revealed { card, to }, expires));
+            game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "\"{}\" revealed", name);
+        });
+    }
+
+    fn reveal_hand(&mut self, hero: Entity, to: Option<Entity>, expires: Expires) {
+        self.add(move |world: &mut World| {
+            let hand = world.get::<HandZone>(hero).map(|v| v.0.clone()).unwrap_or_default();
+            let name = world.get::<PlayerName>(hero).map(|v| v.0.clone()).unwrap_or_default();
+            for card in hand {
+                world.spawn((Revealed { card, to }, expires));
+            }
+            game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "\"{}\" reveals their hand", name);
+        });
+    }
+}
+
+// Which cards a deck search can find
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CardFilter {
+    Any,
+    Attack,
+    Class(CardClassTypes),
+    Type(CardType),
+}
+
+impl CardFilter {
+    fn matches(&self, world: &World, card: Entity) -> bool {
+        match self {
+            CardFilter::Any => true,
+            CardFilter::Attack => world.get::<CardSubTypes>(card).is_some_and(|v| v.has_attack()),
+            CardFilter::Class(class) => world.get::<CardClass>(card).is_some_and(|v| v.contains(*class)),
+            CardFilter::Type(card_type) => world.get::<CardType>(card) == Some(card_type),
+        }
+    }
+
+    // As written in rules text, e.g. "an attack reaction"
+    pub fn render(&self) -> String {
+        match self {
+            CardFilter::Any => String::from("a card"),
+            CardFilter::Attack => String::from("an attack"),
+            CardFilter::Class(class) => {
+                let class = format!("{:?}", class).to_lowercase();
+                let article = if class.starts_with(['a', 'e', 'i', 'o', 'u']) { "an" } else { "a" };
+                format!("{} {} card", article, class)
+            },
+            CardFilter::Type(CardType::Action) => String::from("an action"),
+            CardFilter::Type(CardType::Instant) => String::from("an instant"),
+            CardFilter::Type(CardType::Resource) => String::from("a resource"),
+            CardFilter::Type(CardType::AttackReaction) => String::from("an attack reaction"),
+            CardFilter::Type(CardType::DefenseReaction) => String::from("a defense reaction"),
+            CardFilter::Type(CardType::Item) => String::from("an item"),
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let classes = [CardClassTypes::Assassin, CardClassTypes::Generic, CardClassTypes::Ranger];
+        let types = [
+            CardType::Action,
+            CardType::Instant,
+            CardType::Resource,
+            CardType::AttackReaction,
+            CardType::DefenseReaction,
+            CardType::Item
+        ];
+        [CardFilter::Any, CardFilter::Attack]
+            .into_iter()
+            .chain(classes.into_iter().map(CardFilter::Class))
+            .chain(types.into_iter().map(CardFilter::Type))
+            .find(|v| v.render() == text.trim())
+    }
+}
+
+// Where the card a deck search finds goes
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SearchDestination {
+    Hand,
+    // Put back on top once the rest of the deck is shuffled
+    TopOfDeck,
+}
+
+// A hero looking through their deck for a card to take
+#[derive(Serialize, Deserialize)]
+pub struct DeckSearch {
+    hero: Entity,
+    destination: SearchDestination,
+    // Cards in the deck the hero may take
+    matches: Vec<Entity>
+}
+
+// The deck search waiting on its hero to choose a card
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct PendingSearch(Option<DeckSearch>);
+
+// Deferred deck searches, for tutor effects
+// The hero picks a matching card with "<hero> search [card]"
+pub trait SearchCommands {
+    fn search_deck(&mut self, hero: Entity, filter: CardFilter, destination: SearchDestination);
+}
+
+impl SearchCommands for Commands<'_, '_> {
+    fn search_deck(&mut self, hero: Entity, filter: CardFilter, destination: SearchDestination) {
+        self.add(move |world: &mut World| {
+            let name = world.get::<PlayerName>(hero).map(|v| v.0.clone()).unwrap_or_default();
+            let deck = world.get::<DeckZone>(hero).map(|v| v.0.clone()).unwrap_or_default();
+            let matches = deck
+                .into_iter()
+                .filter(|card| filter.matches(world, *card))
+                .collect::<Vec<Entity>>();
+
+            // Nothing to choose from, so the deck is only shuffled
+            if matches.is_empty() {
+                game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "\"{}\" searches their deck for {} and finds nothing", name, filter.render());
+                let mut deck = std::mem::take(&mut world.get_mut::<DeckZone>(hero).expect("Searching hero should have a deck").0);
+                world.resource_mut::<Dice>().shuffle(&mut deck);
+                world.get_mut::<DeckZone>(hero).expect("Searching hero should have a deck").0 = deck;
+                return;
+            }
+
+            game_log!(world.resource_mut::<GameLog>(),
+                Zones, Normal,
+                "\"{}\" searches their deck for {} (search <card>, or search to take nothing)",
+                name,
+                filter.render()
+            );
+            for card in &matches {
+                let card_name = world.get::<CardName>(*card).map(|v| v.0.clone()).unwrap_or(String::from("Unknown"));
+                game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "  {} \"{}\"", card.index(), card_name);
+            }
+            world.resource_mut::<PendingSearch>().0 = Some(DeckSearch { hero, destination, matches });
+            let mut priority = world.resource_mut::<Priority>();
+            priority.choice = Some(PendingChoice::Search);
+            priority.give_to(hero);
+        });
+    }
+}
+
+// How a hero puts back the cards they looked at, first on top in both lists
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Arrangement {
+    #[serde(with = "entity_ids::list")]
+    pub top: Vec<Entity>,
+    #[serde(with = "entity_ids::list")]
+    pub bottom: Vec<Entity>
+}
+
+impl Arrangement {
+    // Every card looked at goes back exactly once
+    fn fits(&self, looked_at: &[Entity]) -> bool {
+        let mut placed = self.top.iter().chain(&self.bottom).collect::<Vec<&Entity>>();
+        placed.sort();
+        placed.dedup();
+        placed.len() == self.top.len() + self.bottom.len()
+            && placed.len() == looked_at.len()
+            && looked_at.iter().all(|v| placed.contains(&v))
+    }
+
+    fn apply(&self, deck: &mut DeckZone) {
+        deck.put_on_bottom(&self.bottom);
+        deck.reorder(&self.top);
+    }
+}
+
+// Decides where cards looked at from the top of a deck go
+// Returning None leaves the choice to the player
+pub trait DeckArranger: Send + Sync {
+    fn arrange(&mut self, world: &World, hero: Entity, cards: &[Entity]) -> Option<Arrangement>;
+}
+
+// Every player arranges their own cards with "<hero> opt"
+pub struct AskPlayer;
+
+impl DeckArranger for AskPlayer {
+    fn arrange(&mut self, _world: &World, _hero: Entity, _cards: &[Entity]) -> Option<Arrangement> {
+        None
+    }
+}
+
+// For computer players: resources go to the bottom, everything else stays on top in order
+pub struct BottomResources;
+
+impl DeckArranger for BottomResources {
+    fn arrange(&mut self, world: &World, _hero: Entity, cards: &[Entity]) -> Option<Arrangement> {
+        let (bottom, top) = cards
+            .iter()
+            .partition(|v| world.get::<CardType>(**v) == Some(&CardType::Resource));
+        Some(Arrangement { top, bottom })
+    }
+}
+
+// Consulted before a player is asked to arrange their deck
+#[derive(Resource)]
+pub struct DeckChoices(pub Box<dyn DeckArranger>);
+
+impl Default for DeckChoices {
+    fn default() -> Self {
+        DeckChoices(Box::new(AskPlayer))
+    }
+}
+
+// Cards from the top of a deck waiting on their hero to put them back
+#[derive(Serialize, Deserialize)]
+pub struct LookedAt {
+    hero: Entity,
+    cards: Vec<Entity>
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct PendingArrangement(Option<LookedAt>);
+
+// Deferred deck manipulation, for opt effects
+pub trait DeckCommands {
+    fn opt(&mut self, hero: Entity, amount: u16);
+}
+
+impl DeckCommands for Commands<'_, '_> {
+    fn opt(&mut self, hero: Entity, amount: u16) {
+        self.add(move |world: &mut World| {
+            let name = world.get::<PlayerName>(hero).map(|v| v.0.clone()).unwrap_or_default();
+            let cards = world
+                .get::<DeckZone>(hero)
+                .map(|v| v.look_at_top(amount as usize))
+                .unwrap_or_default();
+            if cards.is_empty() {
+                game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "\"{}\" has no cards in their deck to look at", name);
+                return;
+            }
+
+            let arrangement = world.resource_scope(|world, mut choices: Mut<DeckChoices>| {
+                choices.0.arrange(world, hero, &cards)
+            });
+            if let Some(arrangement) = arrangement.filter(|v| v.fits(&cards)) {
+                let mut deck = world.get_mut::<DeckZone>(hero).expect("Hero with cards to look at should have a deck");
+                arrangement.apply(&mut deck);
+                game_log!(world.resource_mut::<GameLog>(),
+                    Zones, Normal,
+                    "\"{}\" puts {} card(s) back on top and {} on the bottom",
+                    name,
+                    arrangement.top.len(),
+                    arrangement.bottom.len()
+                );
+                return;
+            }
+
+            game_log!(world.resource_mut::<GameLog>(),
+                Zones, Normal,
+                "\"{}\" looks at the top {} card(s) of their deck (opt <top cards> [/ <bottom cards>])",
+                name,
+                cards.len()
+            );
+            for card in &cards {
+                let card_name = world.get::<CardName>(*card).map(|v| v.0.clone()).unwrap_or(String::from("Unknown"));
+                game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "  {} \"{}\"", card.index(), card_name);
+            }
+            world.resource_mut::<PendingArrangement>().0 = Some(LookedAt { hero, cards });
+            let mut priority = world.resource_mut::<Priority>();
+            priority.choice = Some(PendingChoice::Arrange);
+            priority.give_to(hero);
+        });
+    }
+}
+
+// Effect entity attached to a card or hero, modifying it while attached
+#[derive(Component, Serialize, Deserialize)]
+pub struct AttachedTo(Entity);
+
+// Stats an attached effect adds to its host
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct StatBonus {
+    attack: i16,
+    defense: i16
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Keyword {
+    GoAgain,
+}
+
+impl Keyword {
+    const ALL: [Keyword; 1] = [Keyword::GoAgain];
+}
+
+// Keyword an attached effect gives its host
+#[derive(Component, Serialize, Deserialize)]
+pub struct GrantsKeyword(Keyword);
+
+// Everything currently granted to a host by its attachments
+// Recalculated from the attachments every run
+#[derive(Component, Default, PartialEq, Serialize, Deserialize)]
+pub struct Granted {
+    attack: i16,
+    defense: i16,
+    keywords: Vec<Keyword>
+}
+
+impl Granted {
+    fn has_keyword(&self, keyword: Keyword) -> bool {
+        self.keywords.contains(&keyword)
+    }
+}
+
+// Stat after attachment bonuses, never below zero
+pub fn granted_stat(base: u16, bonus: i16) -> u16 {
+    (base as i16 + bonus).max(0) as u16
+}
+
+// Hero whose deck the card started in
+// Cards always go back to their owner's zones
+#[derive(Component, Serialize, Deserialize)]
+pub struct Owner(Entity);
+
+// Hero who currently decides what the card does
+// Starts as the owner, and changes with control-changing effects
+#[derive(Component, Serialize, Deserialize)]
+pub struct Controller(Entity);
+
+// Rules text as printed on the card
+// Checked against the effects the engine actually runs
+#[derive(Component, Serialize, Deserialize)]
+pub struct PrintedText(String);
+
+// Full rules text from the card database, for players to read
+// Includes what the engine does not run yet, so it is not checked
+#[derive(Component, Serialize, Deserialize)]
+pub struct CardText(String);
+
+// Arcane damage dealt to the target when the card resolves
+// Arcane damage is not combat damage, so it cannot be blocked
+#[derive(Component, Serialize, Deserialize)]
+pub struct ArcaneDamage(u16);
+
+// What a card must be played targeting
+// Cards without one need a single hero if they target at all
+// Permanents are anything other than a hero that has health
+#[derive(Component, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Targeting {
+    // Exactly one hero
+    Hero,
+    // Exactly one hero or permanent, the default for attacks
+    Attackable,
+    // Up to this many permanents
+    UpToPermanents(usize),
+    // Every opposing hero and permanent, chosen when the card is played
+    AllOpposing,
+}
+
+// What a card does when it resolves from the stack
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Effect {
+    GainResources(u16),
+    GainActionPoints(u16),
+    DrawCards(u16),
+    // Dealt to the card's target
+    DealDamage(u16),
+    // The controller's next attack this turn costs less
+    ReduceNextAttack(u16),
+    GainLife(u16),
+    // The card's target reveals their hand until the end of the turn
+    RevealHand,
+    // The controller searches their deck, reveals the card they take and shuffles
+    SearchDeck(CardFilter, SearchDestination),
+    // The controller looks at the top cards of their deck and puts each on the top or bottom
+    Opt(u16),
+    // Shields the controller from the next damage dealt to them this turn
+    PreventDamage(u16),
+}
+
+impl Effect {
+    fn targeted(&self) -> bool {
+        matches!(self, Effect::DealDamage(_) | Effect::RevealHand)
+    }
+}
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct OnResolve(Vec<Effect>);
+
+// How cards are given up as an additional cost
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CostPayment {
+    // From hand to the graveyard once the card is paid for
+    Discard,
+    // Pitched as soon as the card is played, so they also pay toward its cost
+    Pitch,
+}
+
+// Cards from hand a player may give up when playing the card, named in the play
+// Each one matching the filter gives the attack +attack until the combat chain closes
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct AdditionalCost {
+    payment: CostPayment,
+    // Most cards that can be given up
+    limit: u16,
+    filter: CardFilter,
+    attack: u16
+}
+
+impl OnResolve {
+    fn targeted(&self) -> bool {
+        self.0.iter().any(Effect::targeted)
+    }
+}
+
+// Resources that can be paid to prevent that much arcane damage
+#[derive(Component, Serialize, Deserialize)]
+pub struct ArcaneBarrier(u16);
+
+// Card Type
+#[derive(Component, Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CardType {
+    Action,
+    Instant,
+    Resource,
+    // Played by the attacking hero during the reaction step
+    AttackReaction,
+    // Played by the defending hero during the reaction step
+    DefenseReaction,
+    // Played at action speed and stays in play, see Item
+    Item
+}
+
+impl CardType {
+    fn is_action(&self) -> bool {
+        *self == CardType::Action || *self == CardType::Item
+    }
+
+    fn is_playable(&self) -> bool{
+        self.is_action()
+            || *self == CardType::Instant
+            || self.is_reaction()
+    }
+
+    fn is_reaction(&self) -> bool {
+        *self == CardType::AttackReaction
+            || *self == CardType::DefenseReaction
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CardType::Action => "Action",
+            CardType::Instant => "Instant",
+            CardType::Resource => "Resource",
+            CardType::AttackReaction => "Attack Reaction",
+            CardType::DefenseReaction => "Defense Reaction",
+            CardType::Item => "Item",
+        }
+    }
+}
+
+// How a card in hand may be used, for cards that cannot be used every way their stats allow
+// Cards without it are played, pitched and blocked with as their type, color and defense allow
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ZoneUsage {
+    pub playable: bool,
+    pub pitchable: bool,
+    pub blockable: bool
+}
+
+impl ZoneUsage {
+    // Resource cards are only there to be pitched
+    pub const RESOURCE: ZoneUsage = ZoneUsage { playable: false, pitchable: true, blockable: false };
+    // Wounds take up space in hand and cannot be used at all
+    pub const WOUND: ZoneUsage = ZoneUsage { playable: false, pitchable: false, blockable: false };
+
+    pub fn playable(usage: Option<&Self>) -> bool {
+        usage.is_none_or(|v| v.playable)
+    }
+
+    pub fn pitchable(usage: Option<&Self>) -> bool {
+        usage.is_none_or(|v| v.pitchable)
+    }
+
+    pub fn blockable(usage: Option<&Self>) -> bool {
+        usage.is_none_or(|v| v.blockable)
+    }
+}
+
+// Card Sub Type
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SubType {
+    Attack,
+}
+
+// Card Sub Type Component
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct CardSubTypes(Vec<SubType>);
+
+impl CardSubTypes {
+    fn requires_target(&self) -> bool {
+        self.0.contains(&SubType::Attack)
+    }
+
+    fn has_attack(&self) -> bool {
+        self.0.contains(&SubType::Attack)
+    }
+}
+
+// Classes
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum CardClassTypes {
+    Assassin,
+    Generic,
+    Ranger,
+}
+
+// Card Class Options
+#[derive(Component, Serialize, Deserialize)]
+pub enum CardClass {
+    SingleClass(CardClassTypes),
+    DualClass((CardClassTypes, CardClassTypes))
+}
+
+impl CardClass {
+    fn classes(&self) -> Vec<CardClassTypes> {
+        match &self {
+            CardClass::SingleClass(class) => vec![*class],
+            CardClass::DualClass((class1, class2)) => vec![*class1, *class2]
+        }
+    }
+
+    fn contains(&self, card_class: CardClassTypes) -> bool {
+        match &self {
+            CardClass::SingleClass(class) => *class == card_class,
+            CardClass::DualClass((class1, class2)) => 
+                card_class == *class1 || card_class == *class2
+        }
+    }
+}
+
+// Card Name
+#[derive(Component, Serialize, Deserialize)]
+pub struct CardName(String);
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct Hero;
+
+// Activated ability printed on a hero
+#[derive(Component, Serialize, Deserialize)]
+pub struct HeroAbility {
+    cost: u16,
+    // Activating at action speed costs an action point
+    action: bool
+}
+
+// Activated ability printed on an item, usable while the item is in play
+#[derive(Component, Serialize, Deserialize)]
+pub struct ItemAbility {
+    cost: u16,
+    action: bool,
+    // Exhausting the item is part of the cost
+    exhaust: bool,
+    // Counters removed from the item as part of the cost
+    counters: Option<(CounterKind, u32)>,
+    effects: Vec<Effect>
+}
+
+// An item exhausted for its ability, readied at the start of its controller's turn
+#[derive(Component, Serialize, Deserialize)]
+pub struct Exhausted;
+
+// How often a card can be played or an ability activated
+// Spent when the play is paid for
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum UsageLimit {
+    // Available again at the start of the next turn
+    OncePerTurn { used: bool },
+    OncePerGame { used: bool },
+    // Charges left, never replenished
+    Charges(u16),
+}
+
+impl UsageLimit {
+    pub fn available(&self) -> bool {
+        match self {
+            UsageLimit::OncePerTurn { used } | UsageLimit::OncePerGame { used } => !used,
+            UsageLimit::Charges(charges) => *charges > 0,
+        }
+    }
+
+    fn spend(&mut self) {
+        match self {
+            UsageLimit::OncePerTurn { used } | UsageLimit::OncePerGame { used } => *used = true,
+            UsageLimit::Charges(charges) => *charges = charges.saturating_sub(1),
+        }
+    }
+
+    fn start_turn(&mut self) {
+        if let UsageLimit::OncePerTurn { used } = self {
+            *used = false;
+        }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct GameEvent {
+    targets: Vec<Entity>,
+    card: Entity,
+    actor: Entity,
+    attack: bool,
+    // Cards given up for the card's additional cost
+    #[serde(default)]
+    additional_costs: Vec<Entity>
+}
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct Intellect(u16);
+
+impl Default for Intellect {
+    fn default() -> Self {
+        Intellect(4)
+    }
+}
+
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct PitchZone(VecDeque<Entity>);
+
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct HandZone(Vec<Entity>);
+
+// Top of the deck is the front
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct DeckZone(VecDeque<Entity>);
+
+impl DeckZone {
+    // Top cards, first is the top card
+    pub fn look_at_top(&self, amount: usize) -> Vec<Entity> {
+        self.0.iter().take(amount).copied().collect()
+    }
+
+    // Puts the cards back on top in the order given, first on top
+    pub fn reorder(&mut self, cards: &[Entity]) {
+        self.0.retain(|v| !cards.contains(v));
+        for card in cards.iter().rev() {
+            self.0.push_front(*card);
+        }
+    }
+
+    // Moves the cards to the bottom in the order given, last at the very bottom
+    pub fn put_on_bottom(&mut self, cards: &[Entity]) {
+        self.0.retain(|v| !cards.contains(v));
+        self.0.extend(cards);
+    }
+}
+
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct Graveyard(Vec<Entity>);
+
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct Resources(u16);
+
+#[derive(Component, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Health(u16);
+
+impl Health {
+    // Never goes below zero, returns how much was actually lost
+    pub fn lose(&mut self, amount: u16) -> u16 {
+        let lost = self.0.min(amount);
+        self.0 -= lost;
+        lost
+    }
+
+    // Stops at the most life a counter can hold
+    pub fn gain(&mut self, amount: u16) {
+        self.0 = self.0.saturating_add(amount);
+    }
+}
+
+impl Sub for Health {
+    type Output = Health;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Health(self.0.saturating_sub(rhs.0))
+    }
+}
+
+// Stays in play when it resolves, entering with this much health
+// In play it can be attacked and can block attacks on its controller
+// It leaves play for its owner's graveyard at zero health
+#[derive(Component, Serialize, Deserialize)]
+pub struct Ally {
+    life: u16
+}
+
+// Stays in play when it resolves, entering with these counters
+// In play it can be activated for its ItemAbility, turn after turn
+#[derive(Component, Serialize, Deserialize)]
+pub struct Item {
+    counters: Option<(CounterKind, u32)>
+}
+
+// An item that resolved and is now in play
+#[derive(Component, Serialize, Deserialize)]
+pub struct InPlay;
+
+// A card on the open combat chain: an attack, a block or a reaction
+// It stays there until the chain closes, then goes to the graveyard, apart from allies that blocked
+#[derive(Component, Serialize, Deserialize)]
+pub struct InChain;
+
+// A card in its owner's graveyard, which can no longer be played, pitched or blocked with
+#[derive(Component, Serialize, Deserialize)]
+pub struct InGraveyard;
+
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct Life(u16);
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct Damage(u16);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum HeroAge {
+    Young,
+    #[default]
+    Adult
+}
+
+impl HeroAge {
+    // Young heroes start with half the life printed on their adult card
+    pub fn life(&self, adult: u16) -> u16 {
+        match self {
+            HeroAge::Young => adult / 2,
+            HeroAge::Adult => adult
+        }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct PlayerName(String);
+
+impl PlayerName {
+    fn from(string: &str) -> Self {
+        PlayerName(String::from(string))
+    }
+}
+
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct ActionPoints(u16);
+
+impl ActionPoints {
+    // Go again and "gain an action point" effects grant extra points mid-turn
+    fn grant(&mut self, amount: u16) {
+        self.0 = self.0.saturating_add(amount);
+    }
+
+    // Spends a single point, returns false when there is none left
+    fn spend(&mut self) -> bool {
+        if self.0 == 0 {
+            return false;
+        }
+        self.0 -= 1;
+        true
+    }
+}
+
+#[derive(Bundle)]
+pub struct HeroBundle {
+    player_name: PlayerName,
+    card_name: CardName,
+    intellect: Intellect,
+    health: Health,
+    hero_class: CardClass,
+    hero_age: HeroAge,
+    pitch: PitchZone,
+    hand: HandZone,
+    deck: DeckZone,
+    graveyard: Graveyard,
+    resources: Resources,
+    action_points: ActionPoints,
+    hero: Hero
+}
+
+impl HeroBundle {
+    // Defaults for a hero of the given age, from the life on their adult card
+    fn of_age(age: HeroAge, adult_life: u16) -> Self {
+        HeroBundle {
+            health: Health(age.life(adult_life)),
+            hero_age: age,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for HeroBundle {
+    fn default() -> Self {
+        HeroBundle {
+            player_name: PlayerName(String::from("AI")),
+            card_name: CardName(String::from("Gold Fish")),
+            intellect: Intellect(4),
+            health: Health(40),
+            hero_class: CardClass::SingleClass(CardClassTypes::Generic),
+            hero_age: HeroAge::Adult,
+            pitch: PitchZone::default(),
+            hand: HandZone::default(),
+            deck: DeckZone::default(),
+            graveyard: Graveyard::default(),
+            resources: Resources::default(),
+            action_points: ActionPoints::default(),
+            hero: Hero
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChainLink {
+    targets: Vec<Entity>,
+    attacker: Entity,
+    attack: Entity,
+    blocks: Vec<Entity>,
+    attack_reactions: Vec<Entity>,
+    defense_reactions: Vec<Entity>,
+    hit: bool,
+    closed: bool,
+    // Damage the attack dealt, after modifiers
+    #[serde(default)]
+    damage: u16
+}
+
+impl ChainLink {
+    fn attack(targets: Vec<Entity>, attacker: Entity, attack: Entity) -> ChainLink {
+        ChainLink {
+            targets,
+            attacker,
+            attack,
+            blocks: Vec::new(),
+            attack_reactions: Vec::new(),
+            defense_reactions: Vec::new(),
+            hit: false,
+            closed: false,
+            damage: 0
+        }
+    }
+
+    pub fn attacker(&self) -> Entity {
+        self.attacker
+    }
+
+    pub fn attack_card(&self) -> Entity {
+        self.attack
+    }
+
+    pub fn hit(&self) -> bool {
+        self.hit
+    }
+
+    pub fn damage(&self) -> u16 {
+        self.damage
+    }
+
+    // Heroes attacked, and their teammates, can block
+    pub fn can_block(&self, hero: Entity, teams: &Query<&Team>) -> bool {
+        self.targets.iter().any(|v| same_team(hero, *v, teams))
+    }
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Chain {
+    links: Vec<ChainLink>,
+    open: bool,
+    // Links of combat chains that closed earlier this turn
+    #[serde(default)]
+    history: Vec<ChainLink>
+}
+
+impl Chain {
+    fn add_chain_link(&mut self, chain_link: ChainLink) {
+        self.open = true;
+        self.links.push(chain_link);
+    }
+
+    // Every chain link this turn, oldest first
+    pub fn links_this_turn(&self) -> impl Iterator<Item = &ChainLink> {
+        self.history.iter().chain(&self.links)
+    }
+
+    // Chain links that have resolved this turn, across every combat chain
+    pub fn closed_links_this_turn(&self) -> usize {
+        self.links_this_turn().filter(|v| v.closed).count()
+    }
+
+    // Attacks the hero has put on a chain this turn, e.g. for "if you attacked twice this turn"
+    pub fn attacks_this_turn(&self, hero: Entity) -> usize {
+        self.links_this_turn().filter(|v| v.attacker == hero).count()
+    }
+
+    // Whether any attack hit this turn, across every combat chain
+    pub fn hit_this_turn(&self) -> bool {
+        self.links_this_turn().any(|v| v.hit)
+    }
+
+    // Damage dealt by attacks on the current combat chain
+    pub fn combat_damage(&self) -> u16 {
+        self.links.iter().map(|v| v.damage).fold(0, u16::saturating_add)
+    }
+
+    // Classes of the attacks made this turn, each listed once
+    pub fn attack_classes(&self, class_query: &Query<&CardClass>) -> Vec<CardClassTypes> {
+        let mut classes = Vec::new();
+        for link in self.links_this_turn() {
+            let Ok(class) = class_query.get(link.attack) else {
+                continue;
+            };
+            for class in class.classes() {
+                if !classes.contains(&class) {
+                    classes.push(class);
+                }
+            }
+        }
+        classes
+    }
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Stack(VecDeque<GameEvent>);
+
+impl Stack {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct AttackLayer(Option<GameEvent>);
+
+// Arcane damage waiting on the target to pay arcane barrier
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct PendingArcane(Option<ArcaneHit>);
+
+#[derive(Serialize, Deserialize)]
+pub struct ArcaneHit {
+    source: Entity,
+    target: Entity,
+    amount: u16,
+    // Set once the target has chosen how much barrier to pay
+    paid: Option<u16>
+}
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct Id(CardId);
+
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Serialize, Deserialize)]
+pub struct CardId(String);
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Played(Option<Entity>);
+
+#[derive(Resource, Hash, Eq, PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameState(pub GamePhases);
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum GamePhases {
+    #[default]
+    StartPhase,
+    ActionPhase,
+    EndPhase,
+}
+
+#[derive(Resource, Hash, Eq, PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CombatState(Option<CombatSteps>);
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum CombatSteps {
+    #[default]
+    LayerStep,
+    AttackStep,
+    DefendStep,
+    ReactionStep,
+    // Players see the damage coming and can still shield themselves from it
+    PreventionStep,
+    DamageStep,
+    ResolutionStep,
+    LinkStep,
+    CloseStep
+}
+
+impl CombatSteps {
+    fn name(&self) -> &'static str {
+        match self {
+            CombatSteps::LayerStep => "Layer Step",
+            CombatSteps::AttackStep => "Attack Step",
+            CombatSteps::DefendStep => "Defend Step",
+            CombatSteps::ReactionStep => "Reaction Step",
+            CombatSteps::PreventionStep => "Prevention Step",
+            CombatSteps::DamageStep => "Damage Step",
+            CombatSteps::ResolutionStep => "Resolution Step",
+            CombatSteps::LinkStep => "Link Step",
+            CombatSteps::CloseStep => "Close Step",
+        }
+    }
+}
+
+// Why combat is ready to leave its current step
+#[derive(Clone, PartialEq, Debug)]
+pub enum StepTransition {
+    // Everyone passed during the step, with nothing left on the stack
+    Passed(CombatSteps),
+    // The defending players have finished declaring blocks
+    BlocksDeclared,
+}
+
+// The step transition queued this tick, taken by the system that makes it
+// Always empty between ticks, so it is not saved
+#[derive(Resource, Clone, Default, Debug)]
+pub struct CombatTransition(pub Option<StepTransition>);
+
+
+#[derive(SystemSet, Hash, Eq, PartialEq, Clone, Debug)]
+pub enum ScheduleSets {
+    Read,
+    Process,
+    StartPhase,
+    ActionPhase,
+    EndPhase,
+    // On-hit effects, between the damage step and the resolution step
+    OnHit,
+    // Enter-play triggers, after effects resolve and before lethal damage is checked
+    EnterPlay,
+    // Phase and step hooks, see hooks::OnExit and hooks::OnEnter
+    ExitHooks,
+    EnterHooks,
+}
+
+#[derive(Resource, Serialize, Deserialize)]
+pub struct TurnNumber(u16);
+
+// When a player is asked to act, stored on their hero
+// Everything else is passed for them, see auto_pass
+#[derive(Component, Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Stops {
+    // Passes when nothing in hand, nor the hero's ability, could be played right now
+    pub unless_playable: bool,
+    // During combat, the only steps the player is asked at, or every step when None
+    // The turn player is always asked when they could start or continue the chain
+    pub combat_steps: Option<Vec<CombatSteps>>
+}
+
+impl Stops {
+    // Parses "all", or any of "playable" and step names, e.g. "playable defend reaction"
+    pub fn parse(args: &[&str]) -> Result<Self, CommandError> {
+        let mut stops = Stops::default();
+        for arg in args {
+            let step = match arg.to_lowercase().as_str() {
+                "all" => return Ok(Stops::default()),
+                "playable" => {
+                    stops.unless_playable = true;
+                    continue;
+                },
+                "layer" => CombatSteps::LayerStep,
+                "attack" => CombatSteps::AttackStep,
+                "defend" => CombatSteps::DefendStep,
+                "reaction" => CombatSteps::ReactionStep,
+                "prevention" => CombatSteps::PreventionStep,
+                "damage" => CombatSteps::DamageStep,
+                "resolution" => CombatSteps::ResolutionStep,
+                "link" => CombatSteps::LinkStep,
+                "close" => CombatSteps::CloseStep,
+                other => return Err(CommandError::Invalid(format!("Unknown stop \"{}\"", other)))
+            };
+            stops.combat_steps.get_or_insert_with(Vec::new).push(step);
+        }
+        Ok(stops)
+    }
+
+    pub fn describe(&self) -> String {
+        let steps = match &self.combat_steps {
+            Some(steps) => steps.iter().map(|v| v.name()).collect::<Vec<&str>>().join(", "),
+            None => String::from("every step")
+        };
+        if self.unless_playable {
+            format!("{} in combat, only with something to play", steps)
+        } else {
+            format!("{} in combat", steps)
+        }
+    }
+}
+
+// A choice the priority holder must make before anyone can act freely again
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum PendingChoice {
+    // Declaring blocks in the defend step
+    Blocks,
+    // Discarding down to intellect in the end phase
+    Discard,
+    // Paying for arcane barrier against incoming arcane damage
+    ArcaneBarrier,
+    // Choosing a card found by a deck search
+    Search,
+    // Putting back cards looked at from the top of the deck
+    Arrange
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Priority {
+    // Using a zipper struct for this
+    pub holding: VecDeque<Entity>,
+    passed: VecDeque<Entity>,
+
+    // True when game is holding priority
+    hold: bool,
+
+    // When set, priority is for that choice only
+    #[serde(default)]
+    choice: Option<PendingChoice>,
+
+    // This is hopefully a temporary hack :)
+    // If a player plays a card, priority should be reset at the end of the current cycle of
+    // priorities
+    card_played: bool,
+
+    // Set once the turn player has been reminded of their unused action points this turn
+    #[serde(default)]
+    reminded: bool,
+
+    // Whose turn it is, so resets restore the turn order after priority was handed to
+    // another player first
+    #[serde(default)]
+    turn: Option<Entity>
+
+}
+
+impl Priority {
+    fn hold_priority(&mut self) {
+        self.hold = true
+    }
+
+    fn release_priority(&mut self) {
+        self.hold = false
+    }
+
+    fn has_priority(&self, entity: &Entity) -> bool {
+        self.holding
+            .front()
+            .map(|v| v == entity)
+            .unwrap_or(false)
+        && self.choice.is_none()
+    }
+
+    // The choice the hero has to make, if they hold priority for one
+    pub fn choice_for(&self, entity: &Entity) -> Option<PendingChoice> {
+        self.choice.filter(|_| self.holding.front() == Some(entity))
+    }
+
+    fn is_choosing(&self, entity: &Entity, choice: PendingChoice) -> bool {
+        self.choice_for(entity) == Some(choice)
+    }
+    
+    pub fn priority_hero(&self) -> Option<&Entity> {
+        self.holding.front()
+    }
+
+    fn turn_player(&self) -> &Entity {
+        if let Some(turn) = &self.turn {
+            turn
+        } else if let Some(holding) = self.holding.front() {
+            holding
+        } else {
+            self.passed
+                .front()
+                .expect("At least one player should exist")
+        }
+    }
+
+    // Cycles priority and indicates if all players have passed
+    fn pass_priority(&mut self) {
+        if let Some(hero) = self.holding.pop_front() {
+            self.passed.push_back(hero);
+        }
+
+        if self.passed.is_empty() && self.card_played {
+            self.reset();
+        }
+    }
+
+    // Checks that all players have passed priority
+    fn all_passed(&self) -> bool {
+        self.holding.is_empty() && !self.hold
+    }
+
+    fn cycle_priority(&mut self) -> &Self {
+        self.reset();
+        self.holding.rotate_left(1);
+        self.turn = self.holding.front().copied();
+        self.reminded = false;
+        self
+    }
+
+    fn reset(&mut self) -> &Self {
+        // Shenanigans here:
+        // To reset, we take those who passed and put them back in the front
+        self.passed.append(&mut self.holding);
+        self.holding = self.passed.drain(..).collect();
+        if let Some(position) = self.turn.and_then(|turn| self.holding.iter().position(|v| *v == turn)) {
+            self.holding.rotate_left(position);
+        }
+
+        self
+    }
+
+    // Players ahead of the hero pass so the hero is next to act
+    // Resetting restores the turn order
+    fn give_to(&mut self, hero: Entity) {
+        self.reset();
+        while self.holding.front().is_some_and(|v| *v != hero) {
+            let passed = self.holding.pop_front().unwrap();
+            self.passed.push_back(passed);
+        }
+    }
+
+    // Rotates the turn order so the hero acts first, until the next reset
+    // Unlike give_to, nobody is marked as passed
+    fn start_with(&mut self, hero: Entity) {
+        self.reset();
+        if let Some(position) = self.holding.iter().position(|v| *v == hero) {
+            self.holding.rotate_left(position);
+        }
+    }
+
+    // Players left out pass as they come up, so the turn order is kept
+    fn pass_while(&mut self, left_out: impl Fn(&Entity) -> bool) {
+        while self.holding.front().is_some_and(&left_out) {
+            self.pass_priority();
+        }
+    }
+
+    // Every holding player passes
+    fn pass_all(&mut self) {
+        while let Some(hero) = self.holding.pop_front() {
+            self.passed.push_back(hero);
+        }
+    }
+
+    pub fn someone_has_priority(&self) -> bool {
+        !(self.holding.is_empty() || self.hold)
+    }
+}
+
+// Entities are sent over the wire by index, the same ids the CLI uses
+pub mod entity_ids {
+    use bevy_ecs::entity::Entity;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(entity: &Entity, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(entity.index())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Entity, D::Error> {
+        u32::deserialize(deserializer).map(Entity::from_raw)
+    }
+
+    pub mod option {
+        use super::*;
+        use serde::Serialize;
+
+        pub fn serialize<S: Serializer>(
+            entity: &Option<Entity>,
+            serializer: S
+        ) -> Result<S::Ok, S::Error> {
+            entity.map(|v| v.index()).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D
+        ) -> Result<Option<Entity>, D::Error> {
+            Option::<u32>::deserialize(deserializer).map(|v| v.map(Entity::from_raw))
+        }
+    }
+
+    pub mod list {
+        use super::*;
+        use serde::Serialize;
+
+        pub fn serialize<S: Serializer>(
+            entities: &[Entity],
+            serializer: S
+        ) -> Result<S::Ok, S::Error> {
+            entities.iter().map(|v| v.index()).collect::<Vec<u32>>().serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D
+        ) -> Result<Vec<Entity>, D::Error> {
+            Vec::<u32>::deserialize(deserializer)
+                .map(|v| v.into_iter().map(Entity::from_raw).collect())
+        }
+    }
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct PlayCard {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity,
+    #[serde(with = "entity_ids")]
+    pub card: Entity,
+    #[serde(with = "entity_ids::list", default)]
+    pub targets: Vec<Entity>,
+    // Cards from hand paid toward the card's additional cost
+    #[serde(with = "entity_ids::list", default)]
+    pub additional_costs: Vec<Entity>
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct PitchCard {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity,
+    #[serde(with = "entity_ids")]
+    pub card: Entity,
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct PassPriority {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct DeclareBlocks {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity,
+    #[serde(with = "entity_ids::list")]
+    pub blocks: Vec<Entity>
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct ActivateHeroAbility {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct ActivateItem {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity,
+    #[serde(with = "entity_ids")]
+    pub item: Entity
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct DiscardCard {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity,
+    #[serde(with = "entity_ids")]
+    pub card: Entity
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct PayArcaneBarrier {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity,
+    pub amount: u16
+}
+
+// Takes a card found by a deck search, or nothing when card is None
+#[derive(Event, Serialize, Deserialize)]
+pub struct ChooseSearch {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity,
+    #[serde(with = "entity_ids::option")]
+    pub card: Option<Entity>
+}
+
+// Puts back the cards an opt effect looked at
+#[derive(Event, Serialize, Deserialize)]
+pub struct ArrangeDeck {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity,
+    pub arrangement: Arrangement
+}
+
+#[derive(Event, Serialize, Deserialize)]
+pub struct CancelPlay {
+    #[serde(with = "entity_ids")]
+    pub hero: Entity
+}
+
+#[derive(Event)]
+pub struct End; 
+
+// Fired when a hero's pitch zone goes to the bottom of their deck
+// Cards are in the order they were put on the bottom
+#[derive(Event)]
+pub struct PitchRecycled {
+    hero: Entity,
+    cards: Vec<Entity>
+}
+
+// Fired when an entity gains life
+#[derive(Event)]
+pub struct LifeGained {
+    pub entity: Entity,
+    pub amount: u16
+}
+
+// Fired when an entity loses life other than through damage, such as paying it
+#[derive(Event)]
+pub struct LifeLost {
+    pub entity: Entity,
+    pub amount: u16
+}
+
+// Fired when floating resources are lost, after banks have kept what they can
+#[derive(Event)]
+pub struct ResourcesLost {
+    hero: Entity,
+    amount: u16
+}
+
+// Fired when a card or ability on the stack resolves
+#[derive(Event)]
+pub struct Resolved {
+    card: Entity,
+    actor: Entity,
+    targets: Vec<Entity>
+}
+
+// Fired in the damage step when an attack hits, before the chain link resolves
+#[derive(Event)]
+pub struct Hit {
+    link: usize,
+    attack: Entity,
+    attacker: Entity,
+    target: Entity,
+    damage: u16
+}
+
+// Fired when a permanent enters play
+#[derive(Event)]
+pub struct EnterPlay {
+    pub entity: Entity,
+    pub controller: Entity
+}
+
+// Fired when the combat chain closes, after its cards have gone to the graveyard
+#[derive(Event)]
+pub struct ChainClosed;
+
+// Fired when a permanent is destroyed and leaves play
+// Despawned permanents are gone by the time this is read, so only the id is left
+#[derive(Event)]
+pub struct EntityDestroyed {
+    pub entity: Entity,
+    pub owner: Option<Entity>
+}
+
+// Fired when a hero's life reaches zero
+#[derive(Event)]
+pub struct HeroDefeated {
+    pub hero: Entity
+}
+
+// Fired for each card declared as a block
+#[derive(Event)]
+pub struct OnBlock {
+    hero: Entity,
+    card: Entity,
+    // Attack being defended and the index of its link on the chain
+    attack: Entity,
+    link: usize
+}
+
+// Fired when an input event is refused by the rules
+#[derive(Event)]
+pub struct Rejected {
+    pub hero: Entity,
+    pub reason: validation_systems::Rejection
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct ProposedEvent(Option<GameEvent>);
+
+// Cards pitched toward the proposed play and what each was worth, returned if it is cancelled
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct PendingPitch(Vec<(Entity, u16)>);
+
+pub mod read_systems {
+    use super::*;
+    use validation_systems::{Rejection, TimingState};
+
+    // Tells any listeners why an event was refused
+    fn reject(rejected: &mut EventWriter<Rejected>, hero: Entity, reason: Rejection) {
+        rejected.send(Rejected { hero, reason });
+    }
+
+    // Logs why each event was refused
+    pub fn log_rejections(mut reader: EventReader<Rejected>, mut log: ResMut<GameLog>) {
+        for event in reader.read() {
+            game_log!(log, Actions, Normal, "{}", event.reason);
+        }
+    }
+
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub fn read_card(
+        card_query: Query<(
+            &CardName,
+            Option<&CardType>,
+            Option<&CardSubTypes>,
+            Option<&CardClass>,
+            Option<&Controller>,
+            Option<&ArcaneDamage>,
+            Option<&OnResolve>,
+            Option<&Targeting>,
+            Option<&UsageLimit>,
+            Option<&ZoneUsage>,
+            Has<InChain>,
+            Has<InGraveyard>
+        )>,
+        hero_query: Query<(&HandZone, &CardClass), With<Hero>>,
+        target_query: Query<(Entity, Option<&Hero>, Option<&Controller>), With<Health>>,
+        team_query: Query<&Team>,
+        cost_query: Query<&AdditionalCost>,
+        pitchable_query: Query<Option<&ZoneUsage>, With<Color>>,
+        mut priority: ResMut<Priority>,
+        timing_state: TimingState,
+        mut reader: EventReader<PlayCard>,
+        mut proposed_event: ResMut<ProposedEvent>,
+        mut pitches: EventWriter<PitchCard>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        for event in reader.read() {
+            let Ok((card_name, card_type, card_subtypes, card_class, controller, arcane, on_resolve, targeting, limit, usage, in_chain, in_graveyard)) = card_query.get(event.card) else {
+                reject(&mut rejected, event.hero, Rejection::NotPlayable);
+                return;
+            };
+            let hero = hero_query.get(event.hero).expect("Invalid hero chosen");
+            // Heroes on the player's team and the permanents they control
+            let allied = |target: Entity| match target_query.get(target) {
+                Ok((_, Some(_), _)) => same_team(event.hero, target, &team_query),
+                Ok((_, None, Some(controller))) => same_team(event.hero, controller.0, &team_query),
+                _ => false
+            };
+            // Attacks without their own rule can also target permanents,
+            // other targeted effects need a single hero
+            let targeting = targeting.copied().or(
+                if card_subtypes.is_some_and(|v| v.requires_target()) {
+                    Some(Targeting::Attackable)
+                } else {
+                    (arcane.is_some() || on_resolve.is_some_and(|v| v.targeted())).then_some(Targeting::Hero)
+                }
+            );
+            if let Err(reason) = validation_systems::check_zone(in_chain, in_graveyard)
+            .and_then(|_| validation_systems::validate_play(
+                event,
+                (card_type, card_class, controller, targeting),
+                hero,
+                |target| target_query.get(target).ok().map(|(_, hero, _)| hero.is_some()),
+                &timing_state.timing(&priority)
+            )).and_then(|_| validation_systems::check_usage(limit))
+            .and_then(|_| ZoneUsage::playable(usage).then_some(()).ok_or(Rejection::NotPlayable))
+            .and_then(|_| match card_subtypes.is_some_and(|v| v.has_attack()) {
+                true => validation_systems::check_opponents(&event.targets, allied),
+                false => Ok(())
+            })
+            .and_then(|_| validation_systems::check_additional_costs(
+                event,
+                cost_query.get(event.card).ok(),
+                hero.0,
+                |card| pitchable_query.get(card).is_ok_and(ZoneUsage::pitchable)
+            )) {
+                reject(&mut rejected, event.hero, reason);
+                return;
+            }
+
+            // Area of effect cards hit everything the player's team does not control
+            let targets = match targeting {
+                Some(Targeting::AllOpposing) => target_query
+                    .iter()
+                    .filter(|(target, ..)| !allied(*target))
+                    .map(|(target, ..)| target)
+                    .collect(),
+                _ => event.targets.clone()
+            };
+
+            log.record(&clock, Some(event.hero), LogEvent::CardPlayed {
+                card: event.card,
+                name: card_name.0.clone(),
+                targets: targets
+                    .iter()
+                    .filter_map(|v| card_query.get(*v).ok())
+                    .map(|(target_name, ..)| target_name.0.clone())
+                    .collect()
+            });
+
+            // Cards given up by pitching go toward the cost like any other pitch
+            if cost_query.get(event.card).is_ok_and(|v| v.payment == CostPayment::Pitch) {
+                for card in &event.additional_costs {
+                    pitches.send(PitchCard { hero: event.hero, card: *card });
+                }
+            }
+
+            proposed_event.0 = Some(
+                GameEvent {
+                    targets,
+                    card: event.card,
+                    actor: event.hero,
+                    attack: card_subtypes.is_some_and(|v| v.has_attack()),
+                    additional_costs: event.additional_costs.clone(),
+                }
+            );
+            priority.hold_priority();
+        }
+    }
+
+    pub fn read_priority(
+        query: Query<&PlayerName>,
+        mut reader: EventReader<PassPriority>,
+        mut priority: ResMut<Priority>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        for event in reader.read() {
+            if !priority.has_priority(&event.hero) {
+                reject(&mut rejected, event.hero, Rejection::NoPriority);
+                return;
+            }
+            let player_name = query.get(event.hero).unwrap();
+            log.record(&clock, Some(event.hero), LogEvent::PriorityPassed { name: player_name.0.clone() });
+            priority.pass_priority();
+            if priority.all_passed() {
+                game_log!(log, Priority, Normal, "All players passed priority");
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    pub fn read_pitch(
+        mut reader: EventReader<PitchCard>,
+        mut priority: ResMut<Priority>,
+        proposed_event: Res<ProposedEvent>,
+        mut pending_pitch: ResMut<PendingPitch>,
+        mut hero_query: Query<(&mut HandZone, &mut PitchZone, &mut Resources)>,
+        card_query: Query<(&CardName, &Color, Option<&Controller>, Option<&ZoneUsage>, Has<InChain>, Has<InGraveyard>)>,
+        modifier_query: Query<&PitchModifier>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>
+    ) {
+        for event in reader.read() {
+            // Cards can also be pitched to pay arcane barrier
+            let paying_barrier = priority.is_choosing(&event.hero, PendingChoice::ArcaneBarrier);
+            let (mut hand, mut pitch, mut resources) = hero_query
+                .get_mut(event.hero)
+                .expect("Invalid hero chosen");
+
+            // Confident the payment check is not sufficient
+            // but should work for now
+            // Cards without a color have no pitch value
+            let Ok((card_name, color, controller, usage, in_chain, in_graveyard)) = card_query.get(event.card) else {
+                reject(&mut rejected, event.hero, Rejection::NotPitchable);
+                return;
+            };
+            if !ZoneUsage::pitchable(usage) {
+                reject(&mut rejected, event.hero, Rejection::NotPitchable);
+                return;
+            }
+            if let Err(reason) = validation_systems::check_zone(in_chain, in_graveyard) {
+                reject(&mut rejected, event.hero, reason);
+                return;
+            }
+            if let Err(reason) = validation_systems::validate_pitch(
+                event,
+                &hand,
+                controller,
+                &priority,
+                proposed_event.0.is_some() || paying_barrier
+            ) {
+                reject(&mut rejected, event.hero, reason);
+                return;
+            }
+            let pitch_value = modified_pitch(
+                color,
+                event.hero,
+                event.card,
+                modifier_query.iter()
+            );
+            game_log!(log, Actions, Normal, "Card \"{}\" pitched for \"{}\"", card_name.0, pitch_value);
+
+            // Make this a method of priority
+            hand.0.retain(|c| *c != event.card);
+            pitch.0.push_front(event.card);
+            resources.0 += pitch_value;
+            if !paying_barrier {
+                pending_pitch.0.push((event.card, pitch_value));
+                priority.hold_priority();
+            }
+        }
+    }
+
+    // Takes back a proposed play before it is paid for
+    // Cards pitched toward it return to hand and their resources are given back
+    #[allow(clippy::too_many_arguments)]
+    pub fn read_cancel(
+        mut reader: EventReader<CancelPlay>,
+        mut priority: ResMut<Priority>,
+        mut proposed_event: ResMut<ProposedEvent>,
+        mut pending_pitch: ResMut<PendingPitch>,
+        mut hero_query: Query<(&mut HandZone, &mut PitchZone, &mut Resources)>,
+        card_query: Query<&CardName>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>
+    ) {
+        for event in reader.read() {
+            if proposed_event.0.as_ref().is_none_or(|v| v.actor != event.hero) {
+                reject(&mut rejected, event.hero, Rejection::NothingToCancel);
+                return;
+            }
+            let Ok((mut hand, mut pitch, mut resources)) = hero_query.get_mut(event.hero) else {
+                return;
+            };
+
+            for (card, value) in pending_pitch.0.drain(..) {
+                pitch.0.retain(|c| *c != card);
+                hand.0.push(card);
+                resources.0 = resources.0.saturating_sub(value);
+                if let Ok(card_name) = card_query.get(card) {
+                    game_log!(log, Actions, Normal, "Card \"{}\" returned to hand", card_name.0);
+                }
+            }
+
+            let cancelled = proposed_event.0.take().unwrap();
+            if let Ok(card_name) = card_query.get(cancelled.card) {
+                game_log!(log, Actions, Normal, "Play of \"{}\" cancelled", card_name.0);
+            }
+            priority.release_priority();
+        }
+    }
+
+    pub fn read_activation(
+        hero_query: Query<(&CardName, &HeroAbility, Option<&UsageLimit>)>,
+        mut priority: ResMut<Priority>,
+        timing_state: TimingState,
+        mut reader: EventReader<ActivateHeroAbility>,
+        mut proposed_event: ResMut<ProposedEvent>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>
+    ) {
+        for event in reader.read() {
+            let Ok((hero_name, ability, limit)) = hero_query.get(event.hero) else {
+                reject(&mut rejected, event.hero, Rejection::NoAbility);
+                return;
+            };
+
+            if let Err(reason) = validation_systems::check_speed(
+                event.hero,
+                ability.action,
+                &timing_state.timing(&priority)
+            ) {
+                reject(&mut rejected, event.hero, reason);
+                return;
+            }
+
+            if let Err(reason) = validation_systems::check_usage(limit) {
+                reject(&mut rejected, event.hero, reason);
+                return;
+            }
+
+            game_log!(log, Actions, Normal, "\"{}\" activated their ability", hero_name.0);
+            // The hero is both the source and the controller of the ability
+            proposed_event.0 = Some(
+                GameEvent {
+                    targets: Vec::new(),
+                    card: event.hero,
+                    actor: event.hero,
+                    attack: false,
+                    additional_costs: Vec::new(),
+                }
+            );
+            priority.hold_priority();
+        }
+    }
+
+    // Items can be activated while they are in play and ready, with the counters their ability removes
+    #[allow(clippy::type_complexity)]
+    pub fn read_item_activation(
+        item_query: Query<(&CardName, &ItemAbility, Option<&Controller>, Option<&Counters>, Has<Exhausted>), With<InPlay>>,
+        mut priority: ResMut<Priority>,
+        timing_state: TimingState,
+        mut reader: EventReader<ActivateItem>,
+        mut proposed_event: ResMut<ProposedEvent>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>
+    ) {
+        for event in reader.read() {
+            let Ok((item_name, ability, controller, counters, exhausted)) = item_query.get(event.item) else {
+                reject(&mut rejected, event.hero, Rejection::NotAnItem);
+                return;
+            };
+
+            if let Err(reason) = validation_systems::controls(event.hero, controller) {
+                reject(&mut rejected, event.hero, reason);
+                return;
+            }
+
+            if let Err(reason) = validation_systems::check_speed(
+                event.hero,
+                ability.action,
+                &timing_state.timing(&priority)
+            ) {
+                reject(&mut rejected, event.hero, reason);
+                return;
+            }
+
+            if ability.exhaust && exhausted {
+                reject(&mut rejected, event.hero, Rejection::Exhausted);
+                return;
+            }
+
+            if let Some((kind, amount)) = ability.counters {
+                if counters.map(|v| v.count(kind)).unwrap_or(0) < amount {
+                    reject(&mut rejected, event.hero, Rejection::NotEnoughCounters(kind, amount));
+                    return;
+                }
+            }
+
+            game_log!(log, Actions, Normal, "\"{}\" activated", item_name.0);
+            proposed_event.0 = Some(
+                GameEvent {
+                    targets: Vec::new(),
+                    card: event.item,
+                    actor: event.hero,
+                    attack: false,
+                    additional_costs: Vec::new(),
+                }
+            );
+            priority.hold_priority();
+        }
+    }
+
+    pub fn read_discard(
+        mut reader: EventReader<DiscardCard>,
+        priority: Res<Priority>,
+        mut hero_query: Query<(&mut HandZone, &mut Graveyard)>,
+        card_query: Query<&CardName>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>,
+        mut commands: Commands
+    ) {
+        for event in reader.read() {
+            if !priority.is_choosing(&event.hero, PendingChoice::Discard) {
+                reject(&mut rejected, event.hero, Rejection::NotDiscarding);
+                return;
+            }
+
+            let (mut hand, mut graveyard) = hero_query
+                .get_mut(event.hero)
+                .expect("Invalid hero chosen");
+            if !hand.0.contains(&event.card) {
+                reject(&mut rejected, event.hero, Rejection::NotInHand);
+                return;
+            }
+
+            hand.0.retain(|c| *c != event.card);
+            graveyard.0.push(event.card);
+            commands.entity(event.card).insert(InGraveyard);
+            if let Ok(card_name) = card_query.get(event.card) {
+                game_log!(log, Zones, Normal, "Card \"{}\" discarded", card_name.0);
+            }
+        }
+    }
+
+    pub fn read_barrier(
+        mut reader: EventReader<PayArcaneBarrier>,
+        mut priority: ResMut<Priority>,
+        mut pending: ResMut<PendingArcane>,
+        mut hero_query: Query<(&CardName, &ArcaneBarrier, &mut Resources)>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>
+    ) {
+        for event in reader.read() {
+            if !priority.is_choosing(&event.hero, PendingChoice::ArcaneBarrier) {
+                reject(&mut rejected, event.hero, Rejection::NotPayingBarrier);
+                return;
+            }
+
+            let (hero_name, barrier, mut resources) = hero_query
+                .get_mut(event.hero)
+                .expect("Invalid hero chosen");
+            let hit = pending.0
+                .as_mut()
+                .expect("Arcane damage should be pending while paying barrier");
+
+            // Paying more than the damage dealt prevents nothing more
+            let limit = barrier.0.min(hit.amount);
+            if event.amount > limit {
+                reject(&mut rejected, event.hero, Rejection::BarrierLimit(limit));
+                return;
+            }
+            if event.amount > resources.0 {
+                reject(&mut rejected, event.hero, Rejection::NotEnoughResources);
+                return;
+            }
+
+            resources.0 -= event.amount;
+            hit.paid = Some(event.amount);
+            game_log!(log, Actions, Normal, "\"{}\" paid {} arcane barrier", hero_name.0, event.amount);
+            priority.choice = None;
+            priority.reset();
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn read_search(
+        mut reader: EventReader<ChooseSearch>,
+        mut priority: ResMut<Priority>,
+        mut pending: ResMut<PendingSearch>,
+        mut hero_query: Query<(&PlayerName, &mut HandZone, &mut DeckZone)>,
+        card_query: Query<&CardName>,
+        mut dice: ResMut<Dice>,
+        mut log: ResMut<GameLog>,
+        mut commands: Commands,
+        mut rejected: EventWriter<Rejected>
+    ) {
+        for event in reader.read() {
+            if !priority.is_choosing(&event.hero, PendingChoice::Search) {
+                reject(&mut rejected, event.hero, Rejection::NotSearching);
+                return;
+            }
+            let search = pending.0
+                .as_ref()
+                .expect("A deck search should be pending while searching");
+            if event.card.is_some_and(|card| !search.matches.contains(&card)) {
+                reject(&mut rejected, event.hero, Rejection::NotAMatch);
+                return;
+            }
+            let destination = search.destination;
+            pending.0 = None;
+
+            let (player_name, mut hand, mut deck) = hero_query
+                .get_mut(event.hero)
+                .expect("Invalid hero chosen");
+            deck.0.retain(|c| Some(*c) != event.card);
+            dice.shuffle(&mut deck.0);
+
+            // The card taken is shown to every player
+            let card_name = |card: Entity| card_query.get(card).map(|v| v.0.as_str()).unwrap_or("Unknown");
+            match (event.card, destination) {
+                (None, _) => {
+                    game_log!(log, Zones, Normal, "\"{}\" takes nothing and shuffles their deck", player_name.0);
+                },
+                (Some(card), SearchDestination::Hand) => {
+                    hand.0.push(card);
+                    commands.spawn((Revealed { card, to: None }, Expires::EndOfTurn));
+                    game_log!(log, 
+                        Zones, Normal,
+                        "\"{}\" reveals \"{}\", puts it into their hand and shuffles their deck",
+                        player_name.0,
+                        card_name(card)
+                    );
+                },
+                (Some(card), SearchDestination::TopOfDeck) => {
+                    deck.0.push_front(card);
+                    game_log!(log, 
+                        Zones, Normal,
+                        "\"{}\" reveals \"{}\", shuffles their deck and puts it on top",
+                        player_name.0,
+                        card_name(card)
+                    );
+                },
+            }
+            priority.choice = None;
+            priority.reset();
+        }
+    }
+
+    pub fn read_arrangement(
+        mut reader: EventReader<ArrangeDeck>,
+        mut priority: ResMut<Priority>,
+        mut pending: ResMut<PendingArrangement>,
+        mut hero_query: Query<(&PlayerName, &mut DeckZone)>,
+        mut rejected: EventWriter<Rejected>,
+        mut log: ResMut<GameLog>
+    ) {
+        for event in reader.read() {
+            if !priority.is_choosing(&event.hero, PendingChoice::Arrange) {
+                reject(&mut rejected, event.hero, Rejection::NotArranging);
+                return;
+            }
+            let looked_at = pending.0
+                .as_ref()
+                .expect("Cards should be waiting to be put back while arranging");
+            if !event.arrangement.fits(&looked_at.cards) {
+                reject(&mut rejected, event.hero, Rejection::CardsNotPutBack);
+                return;
+            }
+            pending.0 = None;
+
+            let (player_name, mut deck) = hero_query
+                .get_mut(event.hero)
+                .expect("Invalid hero chosen");
+            event.arrangement.apply(&mut deck);
+            game_log!(log, 
+                Zones, Normal,
+                "\"{}\" puts {} card(s) back on top and {} on the bottom",
+                player_name.0,
+                event.arrangement.top.len(),
+                event.arrangement.bottom.len()
+            );
+            priority.choice = None;
+            priority.reset();
+        }
+    }
+
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    pub fn read_blocks(
+        mut reader: EventReader<DeclareBlocks>,
+        mut chain: ResMut<Chain>,
+        mut priority: ResMut<Priority>,
+        mut on_block: EventWriter<OnBlock>,
+        defense_query: Query<(
+            Option<&Defense>,
+            Option<&Controller>,
+            Option<&CardType>,
+            Option<&Ally>,
+            Has<Health>,
+            Option<&ZoneUsage>,
+            Has<InChain>
+        )>,
+        mut hand_query: Query<&mut HandZone>,
+        team_query: Query<&Team>,
+        mut rejected: EventWriter<Rejected>,
+        mut commands: Commands
+    ) {
+        for event in reader.read() {
+            let hand = hand_query.get(event.hero).expect("Invalid hero chosen");
+            if let Err(reason) = validation_systems::validate_blocks(
+                event,
+                hand,
+                &priority,
+                |card| defense_query.get(card).ok().and_then(|(_, v, ..)| v).map(|v| v.0),
+                |card| matches!(defense_query.get(card), Ok((_, _, _, Some(_), true, ..))),
+                // Defense reactions are played in the reaction step, not declared as blocks
+                // Allies only block from play, and nothing blocks twice in one chain
+                |card| matches!(
+                    defense_query.get(card),
+                    Ok((Some(_), _, card_type, ally, in_play, usage, false))
+                        if card_type != Some(&CardType::DefenseReaction)
+                            && (ally.is_none() || in_play)
+                            && ZoneUsage::blockable(usage)
+                )
+            ) {
+                reject(&mut rejected, event.hero, reason);
+                return;
+            }
+            let blocks = event.blocks.clone();
+
+            // Blocking cards leave the hand for the chain
+            let mut hand = hand_query.get_mut(event.hero).expect("Invalid hero chosen");
+            hand.0.retain(|v| !blocks.contains(v));
+            for card in &blocks {
+                commands.entity(*card).insert(InChain);
+            }
+
+            let link = chain.links.len().saturating_sub(1);
+            let chain_link = chain.links
+                .last_mut()
+                .expect("Chain link missing");
+
+            // Fire block triggers
+            for card in &blocks {
+                on_block.send(OnBlock {
+                    hero: event.hero,
+                    card: *card,
+                    attack: chain_link.attack,
+                    link
+                });
+            }
+            // Teammates add their blocks to those already declared
+            chain_link.blocks.extend(blocks);
+
+            // Hacky fix for progressing blocks
+            priority.pass_priority();
+            priority.pass_while(|hero| !chain_link.can_block(*hero, &team_query));
+        }
+    }
+}
+
+pub mod game_systems {
+    use super::*;
+
+    // Logs when the game starts or stops holding priority
+    pub fn log_priority_hold(priority: Res<Priority>, mut held: Local<bool>, mut log: ResMut<GameLog>) {
+        if priority.hold == *held {
+            return;
+        }
+        *held = priority.hold;
+        if priority.hold {
+            game_log!(log, Priority, Debug, "Game is holding priority");
+        } else {
+            game_log!(log, Priority, Debug, "Game is releasing priority");
+        }
+    }
+
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    pub fn evaluate_cost(
+        cost_query: Query<(&CardName, Option<&CardType>, Option<&Cost>, Option<&LifeCost>)>,
+        modifier_query: Query<(Entity, &CostModifier)>,
+        permanent_query: Query<&Controller, With<Health>>,
+        ability_query: Query<&HeroAbility>,
+        item_query: Query<&ItemAbility, With<InPlay>>,
+        mut limit_query: Query<&mut UsageLimit>,
+        mut resources_query: Query<(&mut Resources, &mut ActionPoints, &mut HandZone, &mut Health), With<Hero>>,
+        mut proposed_event: ResMut<ProposedEvent>,
+        mut priority: ResMut<Priority>,
+        mut stack: ResMut<Stack>,
+        mut attack_layer: ResMut<AttackLayer>,
+        mut pending_pitch: ResMut<PendingPitch>,
+        mut life_lost: EventWriter<LifeLost>,
+        mut commands: Commands,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        // Check if card is being played
+        if let Some(event) = &proposed_event.0 {
+            // Get Details
+            let (card_name, card_type, card_cost, life_cost) = cost_query
+                .get(event.card)
+                .expect("Invalid card referenced");
+
+            // Hero and item abilities carry their own cost and speed
+            let ability = ability_query.get(event.card).ok();
+            let item_ability = item_query.get(event.card).ok();
+            let (printed, is_action) = match (&ability, &item_ability) {
+                (Some(ability), _) => (ability.cost, ability.action),
+                (None, Some(ability)) => (ability.cost, ability.action),
+                (None, None) => (
+                    card_cost.map(|v| v.0).unwrap_or(0),
+                    card_type.map(|v| v.is_action()).unwrap_or(false)
+                )
+            };
+            let activated = ability.is_some() || item_ability.is_some();
+
+            // Only cards have their cost modified
+            let cost = match activated {
+                true => printed,
+                false => modified_cost(
+                    printed,
+                    event.actor,
+                    event.attack,
+                    modifier_query.iter().map(|(_, modifier)| modifier),
+                    |permanent| permanent_query.get(permanent).ok().map(|v| v.0)
+                )
+            };
+
+            // Get resources and action points
+            let (mut resources, mut action_points, mut hand, mut health) = resources_query
+                .get_mut(event.actor)
+                .expect("Heroes should have resources Component");
+
+            // Check action points
+            // This will obviously have to be changed for things like
+            // 'Play next non-attack action as though it were an instant"
+            if is_action && action_points.0 == 0 {
+                game_log!(log, Actions, Normal, "Player does not have any action points.");
+                // Remove card from played card resource
+                proposed_event.0.take();
+                pending_pitch.0.clear();
+                priority.release_priority();
+                return;
+            }
+
+            // Life cannot be pitched for, so a hero without enough of it cannot play the card
+            let life = life_cost.filter(|_| !activated).map(|v| v.0).unwrap_or(0);
+            if health.0 < life {
+                game_log!(log, Actions, Normal, "Not enough life. Player must pay {} life to play.", life);
+                proposed_event.0.take();
+                pending_pitch.0.clear();
+                priority.release_priority();
+                return;
+            }
+
+            // Check if cost is currently payable
+            if resources.0 < cost {
+                let needed = cost - resources.0;
+                game_log!(log, Actions, Normal, "Not enough resources. Player must pitch at least \"{}\" to play.", needed);
+                priority.release_priority();
+                return;
+            }
+
+            // Remove resources
+            // The pitched cards are spent, so the play can no longer be cancelled
+            resources.0 -= cost;
+            if life > 0 {
+                health.lose(life);
+                game_log!(log, Actions, Normal, "\"{}\" costs {} life, going to {}", card_name.0, life, health.0);
+                life_lost.send(LifeLost { entity: event.actor, amount: life });
+            }
+            if cost != printed {
+                log.record(&clock, Some(event.actor), LogEvent::CostModified {
+                    card: event.card,
+                    name: card_name.0.clone(),
+                    printed,
+                    cost
+                });
+            }
+            if !activated {
+                for (entity, modifier) in modifier_query.iter() {
+                    if let CostModifier::NextAttack { hero, .. } = *modifier {
+                        if event.attack && hero == event.actor {
+                            commands.entity(entity).despawn();
+                        }
+                    }
+                }
+            }
+            let pitched = pending_pitch.0.drain(..).map(|(_, value)| value).sum::<u16>();
+
+            // Whatever the pitch added beyond the cost is left floating
+            let over = resources.0.min(pitched);
+            if over > 0 {
+                log.record(&clock, Some(event.actor), LogEvent::OverPitched {
+                    card: event.card,
+                    name: card_name.0.clone(),
+                    amount: over
+                });
+            }
+
+            // This will obviously have to be changed for things like
+            // 'Play next non-attack action as though it were an instant"
+            if is_action {
+                action_points.spend();
+            }
+
+            if let Ok(mut limit) = limit_query.get_mut(event.card) {
+                limit.spend();
+            }
+
+            // Exhausting and removing counters are paid along with the resources
+            if let Some(item_ability) = item_ability {
+                if item_ability.exhaust {
+                    commands.entity(event.card).insert(Exhausted);
+                }
+                if let Some((kind, amount)) = item_ability.counters {
+                    commands.spend_counters(event.card, kind, amount);
+                }
+            }
+
+            if !event.additional_costs.is_empty() {
+                let (hero, card, paid) = (event.actor, event.card, event.additional_costs.clone());
+                commands.add(move |world: &mut World| pay_additional_costs(world, hero, card, &paid));
+            }
+
+            // Add card to the stack
+            // Playing the card moves it out of its owner's hand
+            let event = proposed_event.0.take().unwrap();
+            hand.0.retain(|c| *c != event.card);
+            if event.attack {
+                attack_layer.0 = Some(event);
+                priority.hold_priority();
+            } else {
+                // Players get priority to respond, starting with whoever played the card
+                // Everyone has to pass again before it resolves, so reactions can alternate
+                priority.start_with(event.actor);
+                stack.0.push_front(event);
+                priority.release_priority();
+            }
+            priority.card_played = true;
+
+            game_log!(log, Actions, Normal, "Card \"{}\" added to the stack", card_name.0);
+            game_log!(log, Actions, Debug, "\"{}\" floating", resources.0);
+        }
+    }
+
+    // Discards what the additional cost asks for and powers up the attack
+    // Pitched cards were already paid when the card was played
+    fn pay_additional_costs(world: &mut World, hero: Entity, card: Entity, paid: &[Entity]) {
+        let Some(cost) = world.get::<AdditionalCost>(card).cloned() else {
+            return;
+        };
+        let mut given_up = Vec::new();
+        for paid_card in paid {
+            let in_zone = match cost.payment {
+                CostPayment::Discard => world.get::<HandZone>(hero).is_some_and(|v| v.0.contains(paid_card)),
+                CostPayment::Pitch => world.get::<PitchZone>(hero).is_some_and(|v| v.0.contains(paid_card)),
+            };
+            if !in_zone {
+                continue;
+            }
+            if cost.payment == CostPayment::Discard {
+                if let Some(mut hand) = world.get_mut::<HandZone>(hero) {
+                    hand.0.retain(|v| v != paid_card);
+                }
+                if let Some(mut graveyard) = world.get_mut::<Graveyard>(hero) {
+                    graveyard.0.push(*paid_card);
+                }
+                world.entity_mut(*paid_card).insert(InGraveyard);
+                let name = world.get::<CardName>(*paid_card).map(|v| v.0.clone()).unwrap_or_default();
+                game_log!(world.resource_mut::<GameLog>(), Actions, Normal, "\"{}\" discarded as an additional cost", name);
+            }
+            given_up.push(*paid_card);
+        }
+
+        let matched = given_up.iter().filter(|v| cost.filter.matches(world, **v)).count() as u16;
+        let bonus = matched * cost.attack;
+        if bonus > 0 {
+            world.spawn((
+                AttachedTo(card),
+                StatBonus { attack: bonus as i16, defense: 0 },
+                Expires::EndOfCombatChain
+            ));
+            let name = world.get::<CardName>(card).map(|v| v.0.clone()).unwrap_or_default();
+            game_log!(world.resource_mut::<GameLog>(), Actions, Normal, "\"{}\" gets +{} attack", name, bonus);
+        }
+    }
+
+    // Reports floating resources that were lost, for deck-testing feedback
+    pub fn report_lost_resources(
+        mut reader: EventReader<ResourcesLost>,
+        hero_query: Query<&PlayerName>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        for event in reader.read() {
+            let Ok(name) = hero_query.get(event.hero) else {
+                continue;
+            };
+            log.record(&clock, Some(event.hero), LogEvent::ResourcesWasted {
+                hero: event.hero,
+                name: name.0.clone(),
+                amount: event.amount
+            });
+        }
+    }
+
+    // Maybe want to split this into a different function for triggering attack layer
+    #[allow(clippy::too_many_arguments)]
+    pub fn resolve_stack(
+        card_query: Query<&CardName>,
+        mut stack: ResMut<Stack>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut resolved: EventWriter<Resolved>,
+        mut priority: ResMut<Priority>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        // Only begin resolving stack if all players have passed priority
+        // And the stack is not empty
+        if priority.all_passed() && !stack.0.is_empty() {
+            let next = stack.0.pop_front().unwrap();
+            if let Ok(card_name) = card_query.get(next.card) {
+                game_log!(log, Actions, Normal, "\"{}\" resolves", card_name.0);
+                resolved.send(Resolved {
+                    card: next.card,
+                    actor: next.actor,
+                    targets: next.targets
+                });
+            } else {
+                game_log!(log, Actions, Debug, "Source on stack has ceased to exist.");
+                if next.attack {
+                    combat_systems::enter_step(&mut combat_state, &mut moves, CombatSteps::CloseStep, &mut log, &clock);
+                }
+            }
+
+            // Turn player gets priority again after each resolution
+            priority.reset();
+        }
+    }
+}
+
+pub mod validation_systems {
+    use super::*;
+    use bevy_ecs::system::SystemParam;
+
+    // Why an input event was refused
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub enum Rejection {
+        NoPriority,
+        NotInHand,
+        NotController,
+        NotPlayable,
+        // Actions need the turn player's action phase with an empty stack and no open attack
+        ActionTiming,
+        ClassMismatch,
+        TargetNeeded,
+        // Targets that are missing or not what the card asks for
+        InvalidTargets,
+        NothingToPay,
+        NothingToCancel,
+        NotDefending,
+        CannotBlock,
+        // Reactions need the reaction step of an open chain link
+        ReactionTiming,
+        NotAttacker,
+        NotDefender,
+        NoAbility,
+        LimitReached(UsageLimit),
+        NotDiscarding,
+        NotPayingBarrier,
+        // Barrier paid beyond what the hero has or the damage needs, with the most that can be paid
+        BarrierLimit(u16),
+        NotEnoughResources,
+        NotSearching,
+        NotAMatch,
+        NotArranging,
+        CardsNotPutBack,
+        NotAnOpponent,
+        NoAdditionalCost,
+        // More cards named for an additional cost than it allows, with the most it allows
+        AdditionalCostLimit(u16),
+        // Cards for an additional cost have to be other cards in hand, each named once
+        InvalidAdditionalCost,
+        NotAnItem,
+        Exhausted,
+        NotPitchable,
+        // The item's ability removes this many counters of the kind
+        NotEnoughCounters(CounterKind, u32),
+        OnChain,
+        InGraveyard,
+    }
+
+    impl std::fmt::Display for Rejection {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let reason = match self {
+                Rejection::NoPriority => "Player does not have priority",
+                Rejection::NotInHand => "Card is not in that hero's hand",
+                Rejection::NotController => "Card is controlled by another hero",
+                Rejection::NotPlayable => "Card is not playable",
+                Rejection::ActionTiming => "Actions can only be played by the turn player in their action phase while the stack is empty",
+                Rejection::ClassMismatch => "Card class does not match the hero's class",
+                Rejection::TargetNeeded => "Target needed",
+                Rejection::InvalidTargets => "Card cannot target that",
+                Rejection::NothingToPay => "Cannot pitch to nothing",
+                Rejection::NothingToCancel => "There is no unpaid play of yours to cancel",
+                Rejection::NotDefending => "Player cannot block at this moment",
+                Rejection::CannotBlock => "Card cannot block",
+                Rejection::ReactionTiming => "Reactions can only be played during the reaction step",
+                Rejection::NotAttacker => "Only the attacking hero can play attack reactions",
+                Rejection::NotDefender => "Only the defending hero can play defense reactions",
+                Rejection::NoAbility => "Hero does not have an activated ability",
+                Rejection::LimitReached(UsageLimit::OncePerTurn { .. }) => "Already used this turn",
+                Rejection::LimitReached(UsageLimit::OncePerGame { .. }) => "Already used this game",
+                Rejection::LimitReached(UsageLimit::Charges(_)) => "No charges left",
+                Rejection::NotDiscarding => "Player cannot discard at this moment",
+                Rejection::NotPayingBarrier => "Player cannot pay arcane barrier at this moment",
+                Rejection::BarrierLimit(limit) => return write!(f, "Can pay at most \"{}\" arcane barrier", limit),
+                Rejection::NotEnoughResources => "Not enough resources. Pitch more cards or pay less.",
+                Rejection::NotSearching => "Player is not searching their deck",
+                Rejection::NotAMatch => "Card was not found by the search",
+                Rejection::NotArranging => "Player is not looking at cards from their deck",
+                Rejection::CardsNotPutBack => "Every card looked at must go on the top or bottom exactly once",
+                Rejection::NotAnOpponent => "Attacks can only target opposing heroes and their permanents",
+                Rejection::NoAdditionalCost => "Card has no additional cost to pay",
+                Rejection::AdditionalCostLimit(limit) => {
+                    return write!(f, "Can give up at most \"{}\" card(s) for the additional cost", limit)
+                },
+                Rejection::InvalidAdditionalCost => "Additional costs must be paid with other cards in hand, each named once",
+                Rejection::NotAnItem => "Card is not an item in play",
+                Rejection::Exhausted => "Item is exhausted until its controller's next turn",
+                Rejection::NotPitchable => "Card cannot be pitched",
+                Rejection::NotEnoughCounters(kind, amount) => {
+                    return write!(f, "Item needs {} {:?} counter(s) to activate", amount, kind)
+                },
+                Rejection::OnChain => "Card is already on the combat chain",
+                Rejection::InGraveyard => "Card is in the graveyard",
+            };
+            write!(f, "{}", reason)
+        }
+    }
+
+    // Resources the timing rules read, besides priority
+    #[derive(SystemParam)]
+    pub struct TimingState<'w> {
+        game_state: Res<'w, GameState>,
+        combat_state: Res<'w, CombatState>,
+        stack: Res<'w, Stack>,
+        chain: Res<'w, Chain>
+    }
+
+    impl<'w> TimingState<'w> {
+        pub fn timing<'a>(&'a self, priority: &'a Priority) -> Timing<'a> {
+            Timing {
+                priority,
+                phase: &self.game_state.0,
+                combat_step: self.combat_state.0.as_ref(),
+                stack_empty: self.stack.is_empty(),
+                link: self.chain.links.last().map(|v| (v.attacker, v.targets.as_slice()))
+            }
+        }
+    }
+
+    // Game state the timing rules depend on
+    pub struct Timing<'a> {
+        pub priority: &'a Priority,
+        pub phase: &'a GamePhases,
+        pub combat_step: Option<&'a CombatSteps>,
+        pub stack_empty: bool,
+        // Attacker and defenders of the current chain link
+        pub link: Option<(Entity, &'a [Entity])>
+    }
+
+    impl Timing<'_> {
+        fn action_window(&self, hero: Entity) -> bool {
+            *self.priority.turn_player() == hero
+                && *self.phase == GamePhases::ActionPhase
+                && self.stack_empty
+                && self.combat_step.is_none_or(|v| *v == CombatSteps::LinkStep)
+        }
+    }
+
+    fn in_hand(hand: &HandZone, card: Entity) -> Result<(), Rejection> {
+        hand.0.contains(&card).then_some(()).ok_or(Rejection::NotInHand)
+    }
+
+    // Cards on the combat chain or in the graveyard have been used, and cannot be used again from there
+    pub fn check_zone(in_chain: bool, in_graveyard: bool) -> Result<(), Rejection> {
+        match (in_chain, in_graveyard) {
+            (true, _) => Err(Rejection::OnChain),
+            (_, true) => Err(Rejection::InGraveyard),
+            _ => Ok(())
+        }
+    }
+
+    // Only the controller can play, pitch or block with a card
+    pub fn controls(hero: Entity, controller: Option<&Controller>) -> Result<(), Rejection> {
+        controller
+            .is_some_and(|v| v.0 == hero)
+            .then_some(())
+            .ok_or(Rejection::NotController)
+    }
+
+    // Generic and unclassed cards can be played by any hero
+    pub fn class_allows(card_class: Option<&CardClass>, hero_class: &CardClass) -> Result<(), Rejection> {
+        let allowed = match card_class {
+            None => true,
+            Some(CardClass::SingleClass(CardClassTypes::Generic)) => true,
+            Some(CardClass::SingleClass(class)) => hero_class.contains(*class),
+            Some(CardClass::DualClass((first, second))) =>
+                hero_class.contains(*first) || hero_class.contains(*second),
+        };
+        allowed.then_some(()).ok_or(Rejection::ClassMismatch)
+    }
+
+    // Action-speed plays need an action window, anything else only needs priority
+    pub fn check_speed(hero: Entity, is_action: bool, timing: &Timing) -> Result<(), Rejection> {
+        if !timing.priority.has_priority(&hero) {
+            return Err(Rejection::NoPriority);
+        }
+        if is_action && !timing.action_window(hero) {
+            return Err(Rejection::ActionTiming);
+        }
+        Ok(())
+    }
+
+    // Attack reactions belong to the attacker and defense reactions to the defender
+    pub fn check_reaction(hero: Entity, card_type: &CardType, timing: &Timing) -> Result<(), Rejection> {
+        if !timing.priority.has_priority(&hero) {
+            return Err(Rejection::NoPriority);
+        }
+        let (attacker, defenders) = timing.link
+            .filter(|_| timing.combat_step == Some(&CombatSteps::ReactionStep))
+            .ok_or(Rejection::ReactionTiming)?;
+        match card_type {
+            CardType::AttackReaction if hero != attacker => Err(Rejection::NotAttacker),
+            CardType::DefenseReaction if !defenders.contains(&hero) => Err(Rejection::NotDefender),
+            _ => Ok(())
+        }
+    }
+
+    // Targets must be what the card asks for
+    // is_hero is None for anything that cannot be targeted
+    pub fn check_targets(
+        targets: &[Entity],
+        targeting: Option<Targeting>,
+        is_hero: impl Fn(Entity) -> Option<bool>
+    ) -> Result<(), Rejection> {
+        let Some(targeting) = targeting else {
+            return Ok(());
+        };
+        let heroes = targets
+            .iter()
+            .map(|v| is_hero(*v))
+            .collect::<Option<Vec<bool>>>()
+            .ok_or(Rejection::InvalidTargets)?;
+        match targeting {
+            Targeting::Hero | Targeting::Attackable if heroes.is_empty() => Err(Rejection::TargetNeeded),
+            Targeting::Hero if heroes != [true] => Err(Rejection::InvalidTargets),
+            Targeting::Attackable if heroes.len() != 1 => Err(Rejection::InvalidTargets),
+            Targeting::UpToPermanents(limit) if heroes.len() > limit || heroes.contains(&true) =>
+                Err(Rejection::InvalidTargets),
+            _ => Ok(())
+        }
+    }
+
+    // The last card field is the targeting rule, if the card targets at all
+    pub fn validate_play(
+        event: &PlayCard,
+        card: (Option<&CardType>, Option<&CardClass>, Option<&Controller>, Option<Targeting>),
+        hero: (&HandZone, &CardClass),
+        is_hero: impl Fn(Entity) -> Option<bool>,
+        timing: &Timing
+    ) -> Result<(), Rejection> {
+        let (card_type, card_class, controller, targeting) = card;
+        let (hand, hero_class) = hero;
+
+        let card_type = card_type
+            .filter(|v| v.is_playable())
+            .ok_or(Rejection::NotPlayable)?;
+        if card_type.is_reaction() {
+            check_reaction(event.hero, card_type, timing)?;
+        } else {
+            check_speed(event.hero, card_type.is_action(), timing)?;
+        }
+        controls(event.hero, controller)?;
+        in_hand(hand, event.card)?;
+        class_allows(card_class, hero_class)?;
+
+        check_targets(&event.targets, targeting, is_hero)
+    }
+
+    // Attacks can only go at opposing heroes and the permanents they control
+    pub fn check_opponents(targets: &[Entity], allied: impl Fn(Entity) -> bool) -> Result<(), Rejection> {
+        match targets.iter().any(|v| allied(*v)) {
+            true => Err(Rejection::NotAnOpponent),
+            false => Ok(())
+        }
+    }
+
+    // Cards named for an additional cost have to be other cards in the hero's hand
+    // Cards given up by pitching need a pitch value
+    pub fn check_additional_costs(
+        event: &PlayCard,
+        cost: Option<&AdditionalCost>,
+        hand: &HandZone,
+        pitchable: impl Fn(Entity) -> bool
+    ) -> Result<(), Rejection> {
+        if event.additional_costs.is_empty() {
+            return Ok(());
+        }
+        let cost = cost.ok_or(Rejection::NoAdditionalCost)?;
+        if event.additional_costs.len() > cost.limit as usize {
+            return Err(Rejection::AdditionalCostLimit(cost.limit));
+        }
+        let named = event.additional_costs.iter().collect::<HashSet<&Entity>>();
+        let valid = named.len() == event.additional_costs.len()
+            && !named.contains(&event.card)
+            && event.additional_costs.iter().all(|v| hand.0.contains(v))
+            && (cost.payment != CostPayment::Pitch || event.additional_costs.iter().all(|v| pitchable(*v)));
+        match valid {
+            true => Ok(()),
+            false => Err(Rejection::InvalidAdditionalCost)
+        }
+    }
+
+    // Cards and abilities with a usage limit need a use left
+    pub fn check_usage(limit: Option<&UsageLimit>) -> Result<(), Rejection> {
+        match limit {
+            Some(limit) if !limit.available() => Err(Rejection::LimitReached(*limit)),
+            _ => Ok(())
+        }
+    }
+
+    pub fn validate_pitch(
+        event: &PitchCard,
+        hand: &HandZone,
+        controller: Option<&Controller>,
+        priority: &Priority,
+        paying: bool
+    ) -> Result<(), Rejection> {
+        let paying_barrier = priority.is_choosing(&event.hero, PendingChoice::ArcaneBarrier);
+        if !priority.has_priority(&event.hero) && !paying_barrier {
+            return Err(Rejection::NoPriority);
+        }
+        controls(event.hero, controller)?;
+        in_hand(hand, event.card)?;
+        if !paying {
+            return Err(Rejection::NothingToPay);
+        }
+        Ok(())
+    }
+
+    pub fn validate_blocks(
+        event: &DeclareBlocks,
+        hand: &HandZone,
+        priority: &Priority,
+        controller: impl Fn(Entity) -> Option<Entity>,
+        ally_in_play: impl Fn(Entity) -> bool,
+        can_block: impl Fn(Entity) -> bool
+    ) -> Result<(), Rejection> {
+        if !priority.is_choosing(&event.hero, PendingChoice::Blocks) {
+            return Err(Rejection::NotDefending);
+        }
+        for card in &event.blocks {
+            if controller(*card) != Some(event.hero) {
+                return Err(Rejection::NotController);
+            }
+            if !ally_in_play(*card) {
+                in_hand(hand, *card)?;
+            }
+            if !can_block(*card) {
+                return Err(Rejection::CannotBlock);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub trait Card {
+    type Bundle: Bundle;
+    fn card_id() -> CardId;
+    fn card() -> Self::Bundle;
+    // Systems can go in hooks::OnEnter or hooks::OnExit to run as a phase or step starts or ends
+    fn add_systems(schedule: &mut Schedule);
+}
+
+pub trait HeroCard {
+    type Bundle: Bundle;
+    fn card_id() -> CardId;
+    fn hero(player_name: &str, age: HeroAge) -> Self::Bundle;
+    fn add_systems(schedule: &mut Schedule);
+}
+
+pub mod hero_systems {
+    use super::*;
+
+    // A hero's ability is on the stack with the hero as both source and actor
+    fn is_ability_of(event: &Resolved, id_query: &Query<&Id>, card_id: &CardId) -> bool {
+        event.card == event.actor
+            && id_query
+                .get(event.card)
+                .map(|id| id.0 == *card_id)
+                .unwrap_or(false)
+    }
+
+    pub struct SilvanWayfarer;
+
+    impl SilvanWayfarer {
+        pub const TEXT: &'static str = "Once per Turn Instant - 0: Gain 1 resource.";
+    }
+
+    impl HeroCard for SilvanWayfarer {
+        type Bundle = (HeroBundle, Id, HeroAbility, UsageLimit, CardText);
+
+        fn card_id() -> CardId {
+            CardId("HERO001".to_string())
+        }
+
+        // Once per turn Instant - 0: Gain 1 resource
+        fn hero(player_name: &str, age: HeroAge) -> Self::Bundle {
+            (
+                HeroBundle {
+                    player_name: PlayerName::from(player_name),
+                    card_name: CardName("Silvan, Wayfarer".to_string()),
+                    hero_class: CardClass::SingleClass(CardClassTypes::Ranger),
+                    ..HeroBundle::of_age(age, 40)
+                },
+                Id(Self::card_id()),
+                HeroAbility { cost: 0, action: false },
+                UsageLimit::OncePerTurn { used: false },
+                CardText(Self::TEXT.to_string())
+            )
+        }
+
+        fn add_systems(schedule: &mut Schedule) {
+            schedule.add_systems(Self::resolve.run_if(on_event::<Resolved>()));
+        }
+    }
+
+    impl SilvanWayfarer {
+        fn resolve(
+            mut reader: EventReader<Resolved>,
+            id_query: Query<&Id>,
+            mut hero_query: Query<(&CardName, &mut Resources)>,
+            mut log: ResMut<GameLog>
+        ) {
+            for event in reader.read() {
+                if !is_ability_of(event, &id_query, &Self::card_id()) {
+                    continue;
+                }
+                if let Ok((card_name, mut resources)) = hero_query.get_mut(event.actor) {
+                    resources.0 += 1;
+                    game_log!(log, Triggers, Normal, "\"{}\" gains 1 resource.", card_name.0);
+                }
+            }
+        }
+    }
+
+    pub struct VexNightstalker;
+
+    impl VexNightstalker {
+        pub const TEXT: &'static str = "Once per Turn Action - 2: Each opposing hero loses 1 life.";
+    }
+
+    impl HeroCard for VexNightstalker {
+        type Bundle = (HeroBundle, Id, HeroAbility, UsageLimit, CardText);
+
+        fn card_id() -> CardId {
+            CardId("HERO002".to_string())
+        }
+
+        // Once per turn Action - 2: Each opposing hero loses 1 life
+        fn hero(player_name: &str, age: HeroAge) -> Self::Bundle {
+            (
+                HeroBundle {
+                    player_name: PlayerName::from(player_name),
+                    card_name: CardName("Vex, Nightstalker".to_string()),
+                    hero_class: CardClass::SingleClass(CardClassTypes::Assassin),
+                    ..HeroBundle::of_age(age, 40)
+                },
+                Id(Self::card_id()),
+                HeroAbility { cost: 2, action: true },
+                UsageLimit::OncePerTurn { used: false },
+                CardText(Self::TEXT.to_string())
+            )
+        }
+
+        fn add_systems(schedule: &mut Schedule) {
+            schedule.add_systems(Self::resolve.run_if(on_event::<Resolved>()));
+        }
+    }
+
+    impl VexNightstalker {
+        fn resolve(
+            mut reader: EventReader<Resolved>,
+            id_query: Query<&Id>,
+            hero_query: Query<Entity, With<Hero>>,
+            team_query: Query<&Team>,
+            mut commands: Commands
+        ) {
+            for event in reader.read() {
+                if !is_ability_of(event, &id_query, &Self::card_id()) {
+                    continue;
+                }
+                // Teammates are spared, and the loss goes through the life triggers
+                for entity in hero_query.iter() {
+                    if !same_team(event.actor, entity, &team_query) {
+                        commands.lose_life(entity, 1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub mod card_systems {
+    use super::*;
+
+    pub struct ToxicityRed;
+
+    impl ToxicityRed {
+        pub const SCRIPT: &'static str =
+            "on_play: next assassin or ranger attack gains on_hit: target hero loses 3 life";
+    }
+
+    impl Card for ToxicityRed {
+        type Bundle = (CardName, Cost, Color, Defense, CardType, CardClass, Id, GoAgain, effect_script::Script);
+
+        fn card_id() -> CardId {
+            CardId("OUT165".to_string())
+        }
+
+        fn card() -> Self::Bundle {
+            (
+                CardName("Toxicity".to_string()),
+                Cost(0),
+                Color::Red,
+                Defense(2),
+                CardType::Action,
+                CardClass::SingleClass(CardClassTypes::Assassin),
+                Id(Self::card_id()),
+                GoAgain,
+                effect_script::Script::parse(&[Self::SCRIPT]).expect("Toxicity script should parse")
+            )
+        }
+
+        // The script interpreter runs Toxicity
+        fn add_systems(_schedule: &mut Schedule) {}
+    }
+}
+
+// Card effects written as lines of text, e.g. "on_hit: target hero loses 3 life"
+// Parsed when the card is defined, then run by the interpreter systems at each trigger point
+pub mod effect_script {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+    pub enum Trigger {
+        // When the card resolves
+        OnPlay,
+        // When the attack hits
+        OnHit
+    }
+
+    #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+    pub enum Action {
+        Draw(u16),
+        GainResources(u16),
+        GainLife(u16),
+        // Only heroes lose life
+        TargetLosesLife(u16),
+        // The next attack this turn of one of the classes gets the effect
+        NextAttackGains { classes: Vec<CardClassTypes>, effect: Box<ScriptedEffect> },
+    }
+
+    #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct ScriptedEffect {
+        pub trigger: Trigger,
+        pub action: Action
+    }
+
+    impl ScriptedEffect {
+        // "<trigger>: <action>"
+        pub fn parse(line: &str) -> Result<Self, String> {
+            let (trigger, action) = line
+                .split_once(':')
+                .ok_or(format!("\"{}\" is missing a trigger", line.trim()))?;
+            let trigger = match trigger.trim().to_lowercase().as_str() {
+                "on_play" => Trigger::OnPlay,
+                "on_hit" => Trigger::OnHit,
+                other => return Err(format!("Unknown trigger \"{}\"", other))
+            };
+            Ok(ScriptedEffect { trigger, action: parse_action(action)? })
+        }
+
+        pub fn render(&self) -> String {
+            let trigger = match self.trigger {
+                Trigger::OnPlay => "on_play",
+                Trigger::OnHit => "on_hit",
+            };
+            let action = match &self.action {
+                Action::Draw(amount) => format!("draw {}", amount),
+                Action::GainResources(amount) => format!("gain {} resources", amount),
+                Action::GainLife(amount) => format!("gain {} life", amount),
+                Action::TargetLosesLife(amount) => format!("target hero loses {} life", amount),
+                Action::NextAttackGains { classes, effect } => format!(
+                    "next {} attack gains {}",
+                    classes.iter().map(|v| format!("{:?}", v).to_lowercase()).collect::<Vec<String>>().join(" or "),
+                    effect.render()
+                ),
+            };
+            format!("{}: {}", trigger, action)
+        }
+    }
+
+    fn parse_action(text: &str) -> Result<Action, String> {
+        let text = text.trim().to_lowercase();
+        let words = text.split_whitespace().collect::<Vec<&str>>();
+        let amount = |word: &str| word
+            .parse::<u16>()
+            .map_err(|_| format!("\"{}\" is not an amount", word));
+        match words.as_slice() {
+            ["draw", n] => Ok(Action::Draw(amount(n)?)),
+            ["gain", n, "resource" | "resources"] => Ok(Action::GainResources(amount(n)?)),
+            ["gain", n, "life"] => Ok(Action::GainLife(amount(n)?)),
+            ["target", "hero", "loses", n, "life"] => Ok(Action::TargetLosesLife(amount(n)?)),
+            ["next", ..] => {
+                let (classes, effect) = text["next".len()..]
+                    .split_once(" attack gains ")
+                    .ok_or(format!("\"{}\" should read \"next <class> attack gains <effect>\"", text))?;
+                let classes = classes
+                    .split(" or ")
+                    .map(|v| match v.trim() {
+                        "assassin" => Ok(CardClassTypes::Assassin),
+                        "generic" => Ok(CardClassTypes::Generic),
+                        "ranger" => Ok(CardClassTypes::Ranger),
+                        other => Err(format!("Unknown class \"{}\"", other))
+                    })
+                    .collect::<Result<Vec<CardClassTypes>, String>>()?;
+                Ok(Action::NextAttackGains { classes, effect: Box::new(ScriptedEffect::parse(effect)?) })
+            },
+            _ => Err(format!("Unknown effect \"{}\"", text))
+        }
+    }
+
+    // Every scripted effect a card has
+    #[derive(Component, Clone, Serialize, Deserialize)]
+    pub struct Script(pub Vec<ScriptedEffect>);
+
+    impl Script {
+        pub fn parse(lines: &[&str]) -> Result<Self, String> {
+            lines.iter().map(|v| ScriptedEffect::parse(v)).collect::<Result<Vec<ScriptedEffect>, String>>().map(Script)
+        }
+    }
+
+    // Effect waiting for the next attack of one of the classes
+    // Attacks only come from the turn player, and it expires with the turn
+    #[derive(Component, Serialize, Deserialize)]
+    pub struct PendingGrant {
+        classes: Vec<CardClassTypes>,
+        effect: ScriptedEffect
+    }
+
+    // Effect granted to the attack it is attached to
+    #[derive(Component, Serialize, Deserialize)]
+    pub struct GrantedScript(ScriptedEffect);
+
+    // Runs the action for the hero whose card triggered it
+    fn execute(action: &Action, actor: Entity, targets: &[Entity], commands: &mut Commands) {
+        match action {
+            Action::Draw(amount) => {
+                let amount = *amount;
+                commands.add(move |world: &mut World| {
+                    for _ in 0..amount {
+                        let Some(card) = world.get_mut::<DeckZone>(actor).and_then(|mut v| v.0.pop_front()) else {
+                            break;
+                        };
+                        if let Some(mut hand) = world.get_mut::<HandZone>(actor) {
+                            hand.0.push(card);
+                        }
+                    }
+                    game_log!(world.resource_mut::<GameLog>(), Triggers, Normal, "{} card(s) drawn", amount);
+                });
+            },
+            Action::GainResources(amount) => {
+                let amount = *amount;
+                commands.add(move |world: &mut World| {
+                    if let Some(mut resources) = world.get_mut::<Resources>(actor) {
+                        resources.0 += amount;
+                        game_log!(world.resource_mut::<GameLog>(), Triggers, Normal, "{} resource(s) gained", amount);
+                    }
+                });
+            },
+            Action::GainLife(amount) => commands.gain_life(actor, *amount),
+            Action::TargetLosesLife(amount) => {
+                for target in targets {
+                    commands.lose_life(*target, *amount);
+                }
+            },
+            Action::NextAttackGains { classes, effect } => {
+                commands.spawn((
+                    PendingGrant { classes: classes.clone(), effect: (**effect).clone() },
+                    Expires::EndOfTurn
+                ));
+                let rendered = effect.render();
+                commands.add(move |world: &mut World| {
+                    game_log!(world.resource_mut::<GameLog>(), Triggers, Normal, "Next attack gains \"{}\"", rendered);
+                });
+            },
+        }
+    }
+
+    pub fn run_on_play(
+        mut reader: EventReader<Resolved>,
+        script_query: Query<&Script>,
+        mut commands: Commands
+    ) {
+        for event in reader.read() {
+            let Ok(Script(effects)) = script_query.get(event.card) else {
+                continue;
+            };
+            for effect in effects.iter().filter(|v| v.trigger == Trigger::OnPlay) {
+                execute(&effect.action, event.actor, &event.targets, &mut commands);
+            }
+        }
+    }
+
+    // Hands a waiting effect to the attack once it is played
+    pub fn grant_next_attack(
+        attack_layer: Res<AttackLayer>,
+        pending_query: Query<(Entity, &PendingGrant)>,
+        class_query: Query<&CardClass>,
+        mut log: ResMut<GameLog>,
+        mut commands: Commands
+    ) {
+        let Some(attack) = attack_layer.0.as_ref().map(|v| v.card) else {
+            return;
+        };
+        let Ok(card_class) = class_query.get(attack) else {
+            return;
+        };
+        for (entity, pending) in pending_query.iter() {
+            if pending.classes.iter().any(|v| card_class.contains(*v)) {
+                commands.spawn((GrantedScript(pending.effect.clone()), AttachedTo(attack), Expires::EndOfTurn));
+                commands.entity(entity).despawn();
+                game_log!(log, Triggers, Normal, "Attack gains \"{}\"", pending.effect.render());
+            }
+        }
+    }
+
+    // Granted on-hit effects are used up by the hit
+    pub fn run_on_hit(
+        mut reader: EventReader<Hit>,
+        script_query: Query<&Script>,
+        granted_query: Query<(Entity, &GrantedScript, &AttachedTo)>,
+        mut commands: Commands
+    ) {
+        for event in reader.read() {
+            let printed = script_query
+                .get(event.attack)
+                .map(|v| v.0.iter().collect::<Vec<&ScriptedEffect>>())
+                .unwrap_or_default();
+            for effect in printed.into_iter().filter(|v| v.trigger == Trigger::OnHit) {
+                execute(&effect.action, event.attacker, &[event.target], &mut commands);
+            }
+            for (entity, GrantedScript(effect), AttachedTo(host)) in granted_query.iter() {
+                if *host != event.attack || effect.trigger != Trigger::OnHit {
+                    continue;
+                }
+                execute(&effect.action, event.attacker, &[event.target], &mut commands);
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+// Rules text generated from the effect data the engine executes
+pub mod rules_text {
+    use super::*;
+
+    // One line of rules text
+    #[derive(PartialEq, Debug)]
+    pub enum Clause {
+        GoAgain,
+        ArcaneDamage(u16),
+        ArcaneBarrier(u16),
+        AttackBonus(i16),
+        DefenseBonus(i16),
+        GrantsKeyword(Keyword),
+        OnResolve(Effect),
+        Script(effect_script::ScriptedEffect),
+    }
+
+    impl Clause {
+        pub fn render(&self) -> String {
+            match self {
+                Clause::GoAgain => String::from("Go again"),
+                Clause::ArcaneDamage(amount) => format!("Deal {} arcane damage to target hero.", amount),
+                Clause::ArcaneBarrier(amount) => format!("Arcane Barrier {}", amount),
+                Clause::AttackBonus(amount) => format!("Attached card has {:+} attack.", amount),
+                Clause::DefenseBonus(amount) => format!("Attached card has {:+} defense.", amount),
+                Clause::GrantsKeyword(Keyword::GoAgain) => String::from("Attached card has go again."),
+                Clause::OnResolve(Effect::GainResources(amount)) => format!("Gain {} resource(s).", amount),
+                Clause::OnResolve(Effect::GainActionPoints(amount)) => format!("Gain {} action point(s).", amount),
+                Clause::OnResolve(Effect::DrawCards(amount)) => format!("Draw {} card(s).", amount),
+                Clause::OnResolve(Effect::DealDamage(amount)) => format!("Deal {} damage to target hero.", amount),
+                Clause::OnResolve(Effect::ReduceNextAttack(amount)) =>
+                    format!("Your next attack this turn costs {} less.", amount),
+                Clause::OnResolve(Effect::GainLife(amount)) => format!("Gain {} life.", amount),
+                Clause::OnResolve(Effect::RevealHand) => String::from("Target hero reveals their hand."),
+                Clause::OnResolve(Effect::SearchDeck(filter, SearchDestination::Hand)) => format!(
+                    "Search your deck for {}, reveal it, put it into your hand, then shuffle your deck.",
+                    filter.render()
+                ),
+                Clause::OnResolve(Effect::SearchDeck(filter, SearchDestination::TopOfDeck)) => format!(
+                    "Search your deck for {}, reveal it, shuffle your deck, then put it on top.",
+                    filter.render()
+                ),
+                Clause::OnResolve(Effect::Opt(amount)) => format!("Opt {}", amount),
+                Clause::OnResolve(Effect::PreventDamage(amount)) =>
+                    format!("Prevent the next {} damage that would be dealt to you this turn.", amount),
+                Clause::Script(effect) => effect.render(),
+            }
+        }
+
+        pub fn parse(line: &str) -> Result<Self, String> {
+            let line = line.trim();
+            // Out of range or negative amounts don't parse, so they never match an engine effect
+            let amount = |text: &str| text.trim().parse::<u16>().ok();
+            let bonus = |text: &str| text.trim().parse::<i16>().ok();
+            let parsed = match line {
+                "Go again" => Some(Clause::GoAgain),
+                "Attached card has go again." => Some(Clause::GrantsKeyword(Keyword::GoAgain)),
+                "Target hero reveals their hand." => Some(Clause::OnResolve(Effect::RevealHand)),
+                _ => line.strip_prefix("Deal ")
+                    .and_then(|v| v.strip_suffix(" arcane damage to target hero."))
+                    .and_then(amount)
+                    .map(Clause::ArcaneDamage)
+                    .or_else(|| line.strip_prefix("Arcane Barrier ")
+                        .and_then(amount)
+                        .map(Clause::ArcaneBarrier))
+                    .or_else(|| line.strip_prefix("Attached card has ")
+                        .and_then(|v| v.strip_suffix(" attack."))
+                        .and_then(bonus)
+                        .map(Clause::AttackBonus))
+                    .or_else(|| line.strip_prefix("Attached card has ")
+                        .and_then(|v| v.strip_suffix(" defense."))
+                        .and_then(bonus)
+                        .map(Clause::DefenseBonus))
+                    .or_else(|| line.strip_prefix("Gain ")
+                        .and_then(|v| v.strip_suffix(" resource(s)."))
+                        .and_then(amount)
+                        .map(|v| Clause::OnResolve(Effect::GainResources(v))))
+                    .or_else(|| line.strip_prefix("Gain ")
+                        .and_then(|v| v.strip_suffix(" action point(s)."))
+                        .and_then(amount)
+                        .map(|v| Clause::OnResolve(Effect::GainActionPoints(v))))
+                    .or_else(|| line.strip_prefix("Draw ")
+                        .and_then(|v| v.strip_suffix(" card(s)."))
+                        .and_then(amount)
+                        .map(|v| Clause::OnResolve(Effect::DrawCards(v))))
+                    .or_else(|| line.strip_prefix("Deal ")
+                        .and_then(|v| v.strip_suffix(" damage to target hero."))
+                        .and_then(amount)
+                        .map(|v| Clause::OnResolve(Effect::DealDamage(v))))
+                    .or_else(|| line.strip_prefix("Gain ")
+                        .and_then(|v| v.strip_suffix(" life."))
+                        .and_then(amount)
+                        .map(|v| Clause::OnResolve(Effect::GainLife(v))))
+                    .or_else(|| line.strip_prefix("Your next attack this turn costs ")
+                        .and_then(|v| v.strip_suffix(" less."))
+                        .and_then(amount)
+                        .map(|v| Clause::OnResolve(Effect::ReduceNextAttack(v))))
+                    .or_else(|| line.strip_prefix("Opt ")
+                        .and_then(amount)
+                        .map(|v| Clause::OnResolve(Effect::Opt(v))))
+                    .or_else(|| line.strip_prefix("Prevent the next ")
+                        .and_then(|v| v.strip_suffix(" damage that would be dealt to you this turn."))
+                        .and_then(amount)
+                        .map(|v| Clause::OnResolve(Effect::PreventDamage(v))))
+                    .or_else(|| line.strip_prefix("Search your deck for ")
+                        .and_then(|v| v.strip_suffix(", reveal it, put it into your hand, then shuffle your deck."))
+                        .and_then(CardFilter::parse)
+                        .map(|v| Clause::OnResolve(Effect::SearchDeck(v, SearchDestination::Hand))))
+                    .or_else(|| line.strip_prefix("Search your deck for ")
+                        .and_then(|v| v.strip_suffix(", reveal it, shuffle your deck, then put it on top."))
+                        .and_then(CardFilter::parse)
+                        .map(|v| Clause::OnResolve(Effect::SearchDeck(v, SearchDestination::TopOfDeck))))
+                    .or_else(|| effect_script::ScriptedEffect::parse(line).ok().map(Clause::Script))
+            };
+            parsed.ok_or(format!("\"{}\" has no engine effect", line))
+        }
+    }
+
+    // Clauses for every effect the engine runs for the entity
+    pub fn clauses(world: &World, entity: Entity) -> Vec<Clause> {
+        let mut clauses = Vec::new();
+        if world.get::<GoAgain>(entity).is_some() {
+            clauses.push(Clause::GoAgain);
+        }
+        if let Some(ArcaneDamage(amount)) = world.get::<ArcaneDamage>(entity) {
+            clauses.push(Clause::ArcaneDamage(*amount));
+        }
+        if let Some(ArcaneBarrier(amount)) = world.get::<ArcaneBarrier>(entity) {
+            clauses.push(Clause::ArcaneBarrier(*amount));
+        }
+        if let Some(bonus) = world.get::<StatBonus>(entity) {
+            if bonus.attack != 0 {
+                clauses.push(Clause::AttackBonus(bonus.attack));
+            }
+            if bonus.defense != 0 {
+                clauses.push(Clause::DefenseBonus(bonus.defense));
+            }
+        }
+        if let Some(GrantsKeyword(keyword)) = world.get::<GrantsKeyword>(entity) {
+            clauses.push(Clause::GrantsKeyword(*keyword));
+        }
+        if let Some(OnResolve(effects)) = world.get::<OnResolve>(entity) {
+            clauses.extend(effects.iter().map(|v| Clause::OnResolve(*v)));
+        }
+        if let Some(effect_script::Script(effects)) = world.get::<effect_script::Script>(entity) {
+            clauses.extend(effects.iter().cloned().map(Clause::Script));
+        }
+        clauses
+    }
+
+    pub fn render(world: &World, entity: Entity) -> String {
+        clauses(world, entity)
+            .iter()
+            .map(Clause::render)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // Printed text must describe exactly the effects the engine runs
+    pub fn validate(world: &World, entity: Entity, printed: &str) -> Result<(), String> {
+        let printed = printed
+            .lines()
+            .filter(|v| !v.trim().is_empty())
+            .map(Clause::parse)
+            .collect::<Result<Vec<Clause>, String>>()?;
+        let executed = clauses(world, entity);
+
+        if let Some(missing) = executed.iter().find(|v| !printed.contains(v)) {
+            return Err(format!("\"{}\" is not printed", missing.render()));
+        }
+        if let Some(extra) = printed.iter().find(|v| !executed.contains(v)) {
+            return Err(format!("\"{}\" does not match the engine", extra.render()));
+        }
+        Ok(())
+    }
+
+    // Warns about any card whose printed text has drifted from its effects
+    pub fn check_printed_text(world: &mut World) {
+        let mut query = world.query::<(Entity, &CardName, &PrintedText)>();
+        let outdated = query
+            .iter(world)
+            .filter_map(|(entity, card_name, printed)| {
+                validate(world, entity, &printed.0).err().map(|err| (card_name.0.clone(), err))
+            })
+            .collect::<Vec<(String, String)>>();
+        for (card_name, err) in outdated {
+            game_log!(world.resource_mut::<GameLog>(), Actions, Normal, "Rules text of \"{}\" is out of date: {}", card_name, err);
+        }
+    }
+}
+
+pub mod combat_systems {
+    use super::*;
+
+    // Moves combat to the step and records it
+    pub fn enter_step(combat_state: &mut CombatState, moves: &mut hooks::Moves, step: CombatSteps, log: &mut GameLog, clock: &GameClock) {
+        moves.step(combat_state.0.take(), Some(step.clone()));
+        combat_state.0 = Some(step.clone());
+        log.record(clock, None, LogEvent::StepChanged { step });
+    }
+
+    pub fn trigger_layer_step(
+        mut attack_layer: ResMut<AttackLayer>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        // Layer step is triggered when an attack is added to the stack
+        if !attack_layer.is_changed() || attack_layer.0.is_none(){
+            return;
+        }
+
+        // Can only trigger layer step from no step or link step
+        if !combat_state.0
+            .as_ref()
+            .map(|v| *v == CombatSteps::LinkStep)
+            .unwrap_or(true)
+        {
+            game_log!(log, Combat, Normal, "Attack incorrectly added to the stack");
+            attack_layer.0.take();
+            return;
+        }
+
+        // Switch to LayerStep
+        enter_step(&mut combat_state, &mut moves, CombatSteps::LayerStep, &mut log, &clock);
+        priority.release_priority();
+    }
+
+    // Queues the step combat moves on from once everyone has passed
+    // At most one transition is queued a tick, and each step system waits for its own
+    pub fn queue_transition(
+        combat_state: Res<CombatState>,
+        priority: Res<Priority>,
+        stack: Res<Stack>,
+        mut transition: ResMut<CombatTransition>
+    ) {
+        if !priority.all_passed() {
+            return;
+        }
+
+        transition.0 = match &combat_state.0 {
+            Some(CombatSteps::DefendStep) if priority.choice == Some(PendingChoice::Blocks) => Some(StepTransition::BlocksDeclared),
+            // The attack waits in its own layer, not on the stack
+            Some(CombatSteps::LayerStep) => Some(StepTransition::Passed(CombatSteps::LayerStep)),
+            Some(step @ (
+                CombatSteps::AttackStep
+                | CombatSteps::DefendStep
+                | CombatSteps::ReactionStep
+                | CombatSteps::PreventionStep
+                | CombatSteps::ResolutionStep
+                | CombatSteps::LinkStep
+            )) if stack.is_empty() => Some(StepTransition::Passed(step.clone())),
+            // The damage and close steps move on by themselves
+            _ => None,
+        };
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn trigger_attack_step(
+        mut transition: ResMut<CombatTransition>,
+        mut attack_layer: ResMut<AttackLayer>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut chain: ResMut<Chain>,
+        mut priority: ResMut<Priority>,
+        target_query: Query<Entity, With<Health>>,
+        mut graveyard_query: Query<&mut Graveyard, With<Hero>>,
+        owner_query: Query<&Owner>,
+        mut commands: Commands,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if transition.0.take().is_some() {
+            enter_step(&mut combat_state, &mut moves, CombatSteps::AttackStep, &mut log, &clock);
+
+            // Validate attack layer
+            if attack_layer.0.is_none() {
+                game_log!(log, Combat, Normal, "Attack has ceased to exist.");
+                enter_step(&mut combat_state, &mut moves, CombatSteps::CloseStep, &mut log, &clock);
+                return;
+            }
+
+            // Remove attack from layer
+            let attack = attack_layer.0.take().unwrap();
+            
+            // Check status of targets
+            if attack.targets.is_empty()
+                || attack.targets.iter().any(|v| target_query.get(*v).is_err())
+            {
+                game_log!(log, Combat, Normal, "Invalid target.");
+                // The attack is spent without joining the chain
+                let owner = owner_query.get(attack.card).map(|v| v.0).unwrap_or(attack.actor);
+                if let Ok(mut graveyard) = graveyard_query.get_mut(owner) {
+                    graveyard.0.push(attack.card);
+                    commands.entity(attack.card).insert(InGraveyard);
+                }
+                enter_step(&mut combat_state, &mut moves, CombatSteps::CloseStep, &mut log, &clock);
+                return;
+            }
+
+            // Resolve Attack abilities
+            // ... skipping for now ...
+
+            // Add attack to the chain
+            commands.entity(attack.card).insert(InChain);
+            chain.add_chain_link(
+                ChainLink::attack(
+                    attack.targets.clone(),
+                    attack.actor,
+                    attack.card
+                )
+            );
+            log.record(&clock, Some(attack.actor), LogEvent::AttackDeclared {
+                link: chain.links.len(),
+                attack: attack.card,
+                targets: attack.targets
+            });
+
+            // Turn player gains priority
+            priority.reset();
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn trigger_defend_step(
+        mut transition: ResMut<CombatTransition>,
+        chain: Res<Chain>,
+        target_query: Query<Option<&Hero>>,
+        team_query: Query<&Team>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if transition.0.take().is_some() {
+            enter_step(&mut combat_state, &mut moves, CombatSteps::DefendStep, &mut log, &clock);
+
+            // Check if any target is a hero
+            // if not, no blocks are allowed
+            let link = chain.links
+                .last()
+                .expect("Chain link ceased to exist during defense step");
+            let heroes = link.targets
+                .iter()
+                .filter(|v| target_query.get(**v).expect("Target ceased to exist during defense step").is_some())
+                .count();
+
+            if heroes == 0 {
+                game_log!(log, Combat, Normal, "No target is a hero, so no blocks can be declared.");
+                game_log!(log, Combat, Normal, "Blocks declared");
+                priority.reset();
+            } else {
+                priority.choice = Some(PendingChoice::Blocks);
+                priority.reset();
+                priority.pass_priority();
+                priority.pass_while(|hero| !link.can_block(*hero, &team_query));
+            }
+        }
+    }
+
+    // Defenders get priority again once everyone has declared their blocks
+    pub fn finish_blocks(
+        mut transition: ResMut<CombatTransition>,
+        mut priority: ResMut<Priority>,
+        mut log: ResMut<GameLog>
+    ) {
+        if transition.0.take().is_some() {
+            game_log!(log, Combat, Normal, "Blocks declared");
+            priority.choice = None;
+            priority.reset();
+        }
+    }
+
+    pub fn trigger_reaction_step(
+        mut transition: ResMut<CombatTransition>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        chain: Res<Chain>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        // Waits for the defend step to finish collecting blocks
+        if transition.0.take().is_some() {
+            // The attacker gets the first chance to react, then players alternate
+            let attacker = chain.links.last().expect("Chain link missing").attacker;
+            priority.start_with(attacker);
+            enter_step(&mut combat_state, &mut moves, CombatSteps::ReactionStep, &mut log, &clock);
+        }
+    }
+
+    // Resolved reactions join the current chain link
+    // Attack reactions pump the attack until the chain closes
+    pub fn resolve_reactions(
+        mut reader: EventReader<Resolved>,
+        card_query: Query<(&CardType, Option<&StatBonus>)>,
+        mut chain: ResMut<Chain>,
+        mut commands: Commands
+    ) {
+        for event in reader.read() {
+            let Ok((card_type, bonus)) = card_query.get(event.card) else {
+                continue;
+            };
+            let Some(link) = chain.links.last_mut() else {
+                continue;
+            };
+            match card_type {
+                CardType::AttackReaction => {
+                    commands.entity(event.card).insert(InChain);
+                    link.attack_reactions.push(event.card);
+                    if let Some(bonus) = bonus {
+                        commands.spawn((
+                            AttachedTo(link.attack),
+                            StatBonus { attack: bonus.attack, defense: bonus.defense },
+                            Expires::EndOfCombatChain
+                        ));
+                    }
+                },
+                CardType::DefenseReaction => {
+                    commands.entity(event.card).insert(InChain);
+                    link.defense_reactions.push(event.card);
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // Targets the attack on the link hits and the damage each would be dealt
+    // Each target is dealt the full attack, less the defense it and its teammates put up
+    // Allies defend themselves with their own defense
+    fn incoming_damage(
+        link: &ChainLink,
+        attack_query: &Query<(&Attack, Option<&Counters>, Option<&Granted>)>,
+        defense_query: &Query<(&Defense, Option<&Granted>, Option<&Controller>)>,
+        team_query: &Query<&Team>
+    ) -> Vec<(Entity, u16)> {
+        let (attack, counters, granted) = attack_query.get(link.attack)
+            .expect("Attack has ceased to exist before dealing damage");
+        let bonus = counters
+            .map(|v| v.count(CounterKind::PlusOneAttack))
+            .unwrap_or(0);
+        let attack = granted_stat(attack.0, granted.map(|v| v.attack).unwrap_or(0))
+            .saturating_add(u16::try_from(bonus).unwrap_or(u16::MAX));
+
+        let mut incoming = Vec::new();
+        for defender in link.targets.iter().copied() {
+            let mut total_defense = defense_query
+                .get(defender)
+                .map(|(defense, granted, _)| granted_stat(defense.0, granted.map(|v| v.defense).unwrap_or(0)))
+                .unwrap_or(0);
+            for card in link.blocks.iter().chain(&link.defense_reactions) {
+                if let Ok((defense, granted, Some(Controller(controller)))) = defense_query.get(*card) {
+                    // Teammates who were attacked too defend themselves
+                    let teammate = same_team(*controller, defender, team_query)
+                        && !link.targets.contains(controller);
+                    if *controller == defender || teammate {
+                        total_defense = total_defense.saturating_add(granted_stat(defense.0, granted.map(|v| v.defense).unwrap_or(0)));
+                    }
+                }
+            }
+            if attack >= total_defense {
+                incoming.push((defender, attack - total_defense));
+            }
+        }
+        incoming
+    }
+
+    // Announces the damage the attack will deal, then players get a last chance to prevent it
+    #[allow(clippy::too_many_arguments)]
+    pub fn trigger_prevention_step(
+        mut transition: ResMut<CombatTransition>,
+        attack_query: Query<(&Attack, Option<&Counters>, Option<&Granted>)>,
+        team_query: Query<&Team>,
+        defense_query: Query<(&Defense, Option<&Granted>, Option<&Controller>)>,
+        defender_query: Query<&CardName, With<Health>>,
+        modifier_query: Query<&DamageModifier>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        chain: Res<Chain>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if transition.0.take().is_some() {
+            enter_step(&mut combat_state, &mut moves, CombatSteps::PreventionStep, &mut log, &clock);
+
+            let link = chain.links.last().expect("Chain link missing");
+            for (defender, amount) in incoming_damage(link, &attack_query, &defense_query, &team_query) {
+                let Ok(name) = defender_query.get(defender) else {
+                    continue;
+                };
+                let shielded = modifier_query
+                    .iter()
+                    .filter_map(|v| match *v {
+                        DamageModifier::Prevent { hero, amount, .. } if hero == defender => Some(amount),
+                        _ => None
+                    })
+                    .sum::<u16>();
+                game_log!(log, 
+                    Combat, Normal,
+                    "\"{}\" is about to be dealt {} damage, with shields for {}",
+                    name.0,
+                    amount,
+                    shielded
+                );
+            }
+
+            // Turn player gains priority
+            priority.reset();
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn trigger_damage_step(
+        mut transition: ResMut<CombatTransition>,
+        attack_query: Query<(&Attack, Option<&Counters>, Option<&Granted>)>,
+        team_query: Query<&Team>,
+        defense_query: Query<(&Defense, Option<&Granted>, Option<&Controller>)>,
+        mut defender_query: Query<(&CardName, &mut Health)>,
+        mut modifier_query: Query<(Entity, &mut DamageModifier)>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        mut chain: ResMut<Chain>,
+        mut hits: EventWriter<Hit>,
+        mut commands: Commands,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if transition.0.take().is_some() {
+            // Transition
+            priority.hold_priority();
+            enter_step(&mut combat_state, &mut moves, CombatSteps::DamageStep, &mut log, &clock);
+
+            // Calculate Damage
+            // Worked out again, as the prevention step may have changed the attack or its blocks
+            let number = chain.links.len();
+            let link = chain.links.last_mut().unwrap();
+            for (defender, amount) in incoming_damage(link, &attack_query, &defense_query, &team_query) {
+                // Targets destroyed earlier in the chain are no longer there to hit
+                if defender_query.get(defender).is_err() {
+                    game_log!(log, Combat, Normal, "Target has left play");
+                    continue;
+                }
+
+                // Shields absorb what they can as the damage is dealt
+                let (target, damage) = apply_damage(
+                    defender,
+                    amount,
+                    &mut modifier_query,
+                    &mut defender_query,
+                    &mut commands,
+                    &mut log,
+                    &clock
+                );
+
+                // A fully prevented attack does not hit
+                if damage == 0 {
+                    continue;
+                }
+                link.hit = true;
+                link.damage = link.damage.saturating_add(damage);
+
+                // On-hit effects run before the link resolves
+                hits.send(Hit { link: number, attack: link.attack, attacker: link.attacker, target, damage });
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn trigger_resolution_step(
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        mut chain: ResMut<Chain>,
+        policy: Res<ResourcePolicy>,
+        mut hero_query: Query<(Entity, &mut Resources), With<Hero>>,
+        mut bank_query: Query<(Entity, &mut BankedResources)>,
+        mut lost: EventWriter<ResourcesLost>,
+        mut commands: Commands,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if combat_state.0 == Some(CombatSteps::DamageStep)
+        {
+            // Change state
+            enter_step(&mut combat_state, &mut moves, CombatSteps::ResolutionStep, &mut log, &clock);
+
+            // Close chain link
+            let number = chain.links.len();
+            let link = chain.links
+                .last_mut()
+                .expect("Chain link ceased to exist during resolution step.");
+            link.closed = true;
+            log.record(&clock, Some(link.attacker), LogEvent::LinkClosed { link: number, hit: link.hit });
+
+            // Floating resources can be limited to the chain link they were made in
+            if *policy == ResourcePolicy::EndOfChainLink {
+                for (hero, mut resources) in hero_query.iter_mut() {
+                    clear_resources(hero, &mut resources, &mut bank_query, &mut commands, &mut lost, &mut log);
+                }
+            }
+            
+            // Chain link resolution triggers here
+            // ... skipping for now ...
+
+            // Restore priority
+            priority.reset();
+            priority.release_priority();
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn trigger_link_step(
+        mut transition: ResMut<CombatTransition>,
+        chain: Res<Chain>,
+        go_again_query: Query<(&CardName, Option<&GoAgain>, Option<&Granted>)>,
+        mut hero_query: Query<&mut ActionPoints, With<Hero>>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if transition.0.take().is_some() {
+            // Move to link step
+            enter_step(&mut combat_state, &mut moves, CombatSteps::LinkStep, &mut log, &clock);
+
+            // Calculate go again
+            let link = chain.links
+                .last()
+                .expect("Chain link ceased to exist during link step.");
+            let Ok((card_name, go_again, granted)) = go_again_query.get(link.attack) else {
+                panic!("Attack ceased to exist during link step.");
+            };
+            if go_again.is_some() || granted.is_some_and(|v| v.has_keyword(Keyword::GoAgain)) {
+                if let Ok(mut action_points) = hero_query.get_mut(link.attacker) {
+                    action_points.grant(1);
+                    game_log!(log, Combat, Normal, "\"{}\" has go again", card_name.0);
+                }
+            }
+
+            // Reset priority
+            priority.reset();
+        }
+    }
+
+    pub fn trigger_close_step(
+        mut transition: ResMut<CombatTransition>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if transition.0.take().is_some() {
+            // Move to close step
+            enter_step(&mut combat_state, &mut moves, CombatSteps::CloseStep, &mut log, &clock);
+
+            // Chain close triggers
+            // ... skipping for now ...
+
+            // Reset priority
+            priority.reset();
+        }
+    }
+
+    // Cards go to their owner's graveyard, or the given hero's if they have no owner
+    fn move_to_graveyard(
+        hero: Entity,
+        cards: impl Iterator<Item = Entity>,
+        hero_query: &mut Query<(&mut HandZone, &mut Graveyard), With<Hero>>,
+        owner_query: &Query<&Owner>,
+        commands: &mut Commands
+    ) {
+        for card in cards {
+            let owner = owner_query.get(card).map(|v| v.0).unwrap_or(hero);
+            if let Ok((mut hand, mut graveyard)) = hero_query.get_mut(owner) {
+                hand.0.retain(|c| *c != card);
+                graveyard.0.push(card);
+                commands.entity(card).remove::<InChain>().insert(InGraveyard);
+            }
+        }
+    }
+
+    // Resolves the combat chain once the close step is reached
+    // The turn player then continues their action phase
+    #[allow(clippy::too_many_arguments)]
+    pub fn close_combat_chain(
+        mut chain: ResMut<Chain>,
+        mut combat_state: ResMut<CombatState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut priority: ResMut<Priority>,
+        mut hero_query: Query<(&mut HandZone, &mut Graveyard), With<Hero>>,
+        owner_query: Query<&Owner>,
+        ally_query: Query<(), With<Ally>>,
+        mut closed: EventWriter<ChainClosed>,
+        mut log: ResMut<GameLog>,
+        mut commands: Commands
+    ) {
+        if combat_state.0 != Some(CombatSteps::CloseStep) {
+            return;
+        }
+
+        game_log!(log, Combat, Normal, "Combat chain closed");
+        let links = std::mem::take(&mut chain.links);
+        for link in &links {
+            // Attacks and attack reactions belong to the attacker,
+            // blocks and defense reactions to the defender
+            move_to_graveyard(
+                link.attacker,
+                std::iter::once(link.attack).chain(link.attack_reactions.iter().copied()),
+                &mut hero_query,
+                &owner_query,
+                &mut commands
+            );
+            // Blocks normally have an owner, so the fallback hero rarely matters
+            // Allies stay in play after blocking
+            move_to_graveyard(
+                link.targets.first().copied().unwrap_or(link.attacker),
+                link.blocks
+                    .iter()
+                    .copied()
+                    .filter(|v| ally_query.get(*v).is_err())
+                    .chain(link.defense_reactions.iter().copied()),
+                &mut hero_query,
+                &owner_query,
+                &mut commands
+            );
+            for ally in link.blocks.iter().filter(|v| ally_query.get(**v).is_ok()) {
+                commands.entity(*ally).remove::<InChain>();
+            }
+        }
+        // Kept for effects that look back over the turn
+        chain.history.extend(links);
+        chain.open = false;
+        moves.step(combat_state.0.take(), None);
+        closed.send(ChainClosed);
+
+        // Turn player gains priority
+        priority.reset();
+    }
+}
+
+pub mod attachment_systems {
+    use super::*;
+
+    // Attachments fall off when their host leaves play
+    pub fn detach_from_departed(
+        attachment_query: Query<(Entity, &AttachedTo)>,
+        graveyard_query: Query<&Graveyard>,
+        mut log: ResMut<GameLog>,
+        mut commands: Commands
+    ) {
+        for (entity, AttachedTo(host)) in attachment_query.iter() {
+            let departed = commands.get_entity(*host).is_none()
+                || graveyard_query.iter().any(|v| v.0.contains(host));
+            if departed {
+                game_log!(log, Zones, Normal, "Host left play, attachment removed");
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    // Rebuilds what each host is granted from its current attachments
+    pub fn recalculate_stats(
+        attachment_query: Query<(&AttachedTo, Option<&StatBonus>, Option<&GrantsKeyword>)>,
+        mut host_query: Query<(Entity, Option<&mut Granted>)>,
+        mut commands: Commands
+    ) {
+        let mut granted: HashMap<Entity, Granted> = HashMap::new();
+        for (AttachedTo(host), bonus, keyword) in attachment_query.iter() {
+            let host_granted = granted.entry(*host).or_default();
+            if let Some(bonus) = bonus {
+                host_granted.attack += bonus.attack;
+                host_granted.defense += bonus.defense;
+            }
+            if let Some(GrantsKeyword(keyword)) = keyword {
+                host_granted.keywords.push(*keyword);
+            }
+        }
+
+        for (host, current) in host_query.iter_mut() {
+            match (current, granted.remove(&host)) {
+                // Only write on change so change detection stays meaningful
+                (Some(mut current), Some(new)) => {
+                    if *current != new {
+                        *current = new;
+                    }
+                },
+                (Some(_), None) => {
+                    commands.entity(host).remove::<Granted>();
+                },
+                (None, Some(new)) => {
+                    commands.entity(host).insert(new);
+                },
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+pub mod permanent_systems {
+    use super::*;
+
+    // Keyword and card systems subscribe here to react to anything entering play
+    // Subscribers read EnterPlay and filter on their own components, e.g. With<Crank>
+    pub fn on_enter_play<M>(schedule: &mut Schedule, systems: impl IntoSystemConfigs<M>) {
+        schedule.add_systems(systems.in_set(ScheduleSets::EnterPlay));
+    }
+
+    // Permanents at zero health leave play for their owner's graveyard
+    // Ones without an owner, like tokens, cease to exist
+    pub fn destroy_permanents(
+        permanent_query: Query<(Entity, &CardName, &Health, Option<&Owner>), Without<Hero>>,
+        mut graveyard_query: Query<&mut Graveyard, With<Hero>>,
+        mut destroyed: EventWriter<EntityDestroyed>,
+        mut log: ResMut<GameLog>,
+        mut commands: Commands
+    ) {
+        for (permanent, card_name, health, owner) in permanent_query.iter() {
+            if health.0 > 0 {
+                continue;
+            }
+            match owner.and_then(|v| graveyard_query.get_mut(v.0).ok()) {
+                Some(mut graveyard) => {
+                    commands.entity(permanent).remove::<Health>().remove::<InChain>().insert(InGraveyard);
+                    graveyard.0.push(permanent);
+                },
+                None => commands.entity(permanent).despawn()
+            }
+            game_log!(log, Zones, Normal, "\"{}\" is destroyed", card_name.0);
+            destroyed.send(EntityDestroyed { entity: permanent, owner: owner.map(|v| v.0) });
+        }
+    }
+
+    // Heroes stay in play at zero life, so each is only reported once
+    // A hero brought back above zero can be defeated again
+    #[allow(clippy::type_complexity)]
+    pub fn defeat_heroes(
+        hero_query: Query<(Entity, &CardName, &Health), (With<Hero>, Changed<Health>)>,
+        mut reported: Local<HashSet<Entity>>,
+        mut defeated: EventWriter<HeroDefeated>,
+        mut log: ResMut<GameLog>
+    ) {
+        for (hero, card_name, health) in hero_query.iter() {
+            if health.0 > 0 {
+                reported.remove(&hero);
+            } else if reported.insert(hero) {
+                game_log!(log, Combat, Normal, "\"{}\" has no life left", card_name.0);
+                defeated.send(HeroDefeated { hero });
+            }
+        }
+    }
+}
+
+pub mod effect_systems {
+    use super::*;
+
+    // Runs the effects of cards resolving from the stack
+    // Resolved allies and items enter play, other actions and instants go to their owner's graveyard
+    // Items already in play resolve their ability instead
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub fn resolve_effects(
+        mut reader: EventReader<Resolved>,
+        card_query: Query<(
+            &CardName,
+            Option<&OnResolve>,
+            Option<&CardType>,
+            Option<&Owner>,
+            Option<&Ally>,
+            Option<&Item>,
+            Option<&ItemAbility>,
+            Has<InPlay>
+        )>,
+        mut hero_query: Query<
+            (&mut Resources, &mut ActionPoints, &mut HandZone, &mut DeckZone, &mut Graveyard),
+            With<Hero>
+        >,
+        mut modifier_query: Query<(Entity, &mut DamageModifier)>,
+        mut health_query: Query<(&CardName, &mut Health)>,
+        mut enter_play: EventWriter<EnterPlay>,
+        mut commands: Commands,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        for event in reader.read() {
+            let Ok((card_name, on_resolve, card_type, owner, ally, item, item_ability, in_play)) = card_query.get(event.card) else {
+                continue;
+            };
+            let activated = item_ability.filter(|_| in_play);
+            let effects = match activated {
+                Some(ability) => ability.effects.as_slice(),
+                None => on_resolve.map(|v| v.0.as_slice()).unwrap_or_default()
+            };
+
+            for effect in effects {
+                let Ok((mut resources, mut action_points, mut hand, mut deck, _)) = hero_query.get_mut(event.actor) else {
+                    continue;
+                };
+                match *effect {
+                    Effect::GainResources(amount) => {
+                        resources.0 += amount;
+                        game_log!(log, Actions, Normal, "\"{}\" gains {} resource(s)", card_name.0, amount);
+                    },
+                    Effect::GainActionPoints(amount) => {
+                        action_points.grant(amount);
+                        game_log!(log, Actions, Normal, "\"{}\" gains {} action point(s)", card_name.0, amount);
+                    },
+                    Effect::DrawCards(amount) => {
+                        for _ in 0..amount {
+                            let Some(card) = deck.0.pop_front() else {
+                                break;
+                            };
+                            hand.0.push(card);
+                        }
+                        game_log!(log, Actions, Normal, "\"{}\" draws {} card(s)", card_name.0, amount);
+                    },
+                    Effect::DealDamage(amount) => {
+                        for target in &event.targets {
+                            apply_damage(
+                                *target,
+                                amount,
+                                &mut modifier_query,
+                                &mut health_query,
+                                &mut commands,
+                                &mut log,
+                                &clock
+                            );
+                        }
+                    },
+                    Effect::ReduceNextAttack(amount) => {
+                        commands.spawn((
+                            CostModifier::NextAttack { hero: event.actor, amount: -(amount as i16) },
+                            Expires::EndOfTurn
+                        ));
+                        game_log!(log, Actions, Normal, "Next attack this turn costs {} less", amount);
+                    },
+                    Effect::GainLife(amount) => commands.gain_life(event.actor, amount),
+                    Effect::RevealHand => {
+                        for target in &event.targets {
+                            commands.reveal_hand(*target, None, Expires::EndOfTurn);
+                        }
+                    },
+                    Effect::SearchDeck(filter, destination) => {
+                        commands.search_deck(event.actor, filter, destination);
+                    },
+                    Effect::Opt(amount) => commands.opt(event.actor, amount),
+                    Effect::PreventDamage(amount) => commands.register_shield(event.actor, amount, Expires::EndOfTurn),
+                }
+            }
+
+            // Reactions stay on the chain link, hero abilities have no card to move
+            // and items stay where they are when their ability resolves
+            if activated.is_some() {
+                continue;
+            }
+            if let Some(ally) = ally {
+                commands.entity(event.card).insert(Health(ally.life));
+                game_log!(log, Zones, Normal, "\"{}\" enters play with {} health", card_name.0, ally.life);
+                enter_play.send(EnterPlay { entity: event.card, controller: event.actor });
+            } else if let Some(item) = item {
+                commands.entity(event.card).insert(InPlay);
+                game_log!(log, Zones, Normal, "\"{}\" enters play", card_name.0);
+                if let Some((kind, amount)) = item.counters {
+                    commands.add_counters(event.card, kind, amount);
+                }
+                enter_play.send(EnterPlay { entity: event.card, controller: event.actor });
+            } else if matches!(card_type, Some(CardType::Action) | Some(CardType::Instant)) {
+                let owner = owner.map(|v| v.0).unwrap_or(event.actor);
+                if let Ok((.., mut graveyard)) = hero_query.get_mut(owner) {
+                    graveyard.0.push(event.card);
+                    commands.entity(event.card).insert(InGraveyard);
+                }
+            }
+        }
+    }
+}
+
+pub mod arcane_systems {
+    use super::*;
+
+    // Arcane damage from a resolving card waits for its target to pay arcane barrier
+    pub fn deal_arcane_damage(
+        mut reader: EventReader<Resolved>,
+        card_query: Query<&ArcaneDamage>,
+        barrier_query: Query<(&CardName, &ArcaneBarrier)>,
+        mut pending: ResMut<PendingArcane>,
+        mut priority: ResMut<Priority>,
+        mut log: ResMut<GameLog>
+    ) {
+        for event in reader.read() {
+            // Arcane cards target a single hero
+            let (Ok(damage), Some(&target)) = (card_query.get(event.card), event.targets.first()) else {
+                continue;
+            };
+
+            // Targets without barrier have nothing to pay
+            let paid = match barrier_query.get(target) {
+                Ok((hero_name, barrier)) if barrier.0 > 0 => {
+                    game_log!(log, 
+                        Actions, Normal,
+                        "\"{}\" may pay up to {} to prevent {} arcane damage (barrier <amount>)",
+                        hero_name.0,
+                        barrier.0.min(damage.0),
+                        damage.0
+                    );
+                    priority.choice = Some(PendingChoice::ArcaneBarrier);
+                    priority.give_to(target);
+                    None
+                },
+                _ => Some(0)
+            };
+            pending.0 = Some(ArcaneHit { source: event.card, target, amount: damage.0, paid });
+        }
+    }
+
+    // Deals pending arcane damage once the barrier has been paid
+    // Skips blocks entirely, but damage modifiers still apply
+    pub fn resolve_arcane_damage(
+        mut pending: ResMut<PendingArcane>,
+        mut modifier_query: Query<(Entity, &mut DamageModifier)>,
+        mut health_query: Query<(&CardName, &mut Health)>,
+        mut commands: Commands,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if !pending.is_changed() || pending.0.as_ref().is_none_or(|hit| hit.paid.is_none()) {
+            return;
+        }
+
+        let hit = pending.0.take().unwrap();
+        let prevented = hit.paid.unwrap_or(0);
+        if prevented > 0 {
+            game_log!(log, Combat, Normal, "{} arcane damage prevented by barrier", prevented);
+        }
+        apply_damage(
+            hit.target,
+            hit.amount - prevented,
+            &mut modifier_query,
+            &mut health_query,
+            &mut commands,
+            &mut log,
+            &clock
+        );
+    }
+}
+
+pub mod duration_systems {
+    use super::*;
+
+    // Despawns effects whose duration has run out
+    pub fn expire_effects(
+        game_state: Res<GameState>,
+        combat_state: Res<CombatState>,
+        mut closed: EventReader<ChainClosed>,
+        effect_query: Query<(Entity, &Expires)>,
+        mut commands: Commands
+    ) {
+        let turn_ended = game_state.is_changed()
+            && game_state.0 == GamePhases::StartPhase;
+        // Other changes to the chain, like adding reactions to a link, do not close it
+        let chain_closed = closed.read().count() > 0;
+        let attack_resolved = combat_state.is_changed()
+            && combat_state.0 == Some(CombatSteps::ResolutionStep);
+
+        for (entity, expires) in effect_query.iter() {
+            let expired = match expires {
+                Expires::EndOfTurn => turn_ended,
+                Expires::EndOfCombatChain => chain_closed || turn_ended,
+                Expires::NextAttack => attack_resolved || turn_ended,
+            };
+            if expired {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    // Chain history only covers the current turn
+    pub fn forget_chain_history(
+        game_state: Res<GameState>,
+        mut chain: ResMut<Chain>
+    ) {
+        if game_state.is_changed() && game_state.0 == GamePhases::StartPhase && !chain.history.is_empty() {
+            chain.history.clear();
+        }
+    }
+
+    // Once per turn cards and abilities can be used again when a turn starts
+    pub fn reset_usage_limits(
+        game_state: Res<GameState>,
+        mut limit_query: Query<&mut UsageLimit>
+    ) {
+        if !(game_state.is_changed() && game_state.0 == GamePhases::StartPhase) {
+            return;
+        }
+        for mut limit in limit_query.iter_mut() {
+            limit.start_turn();
+        }
+    }
+
+    // Exhausted items ready at the start of their controller's turn
+    pub fn ready_items(
+        priority: Res<Priority>,
+        item_query: Query<(Entity, &CardName, &Controller), With<Exhausted>>,
+        mut log: ResMut<GameLog>,
+        mut commands: Commands
+    ) {
+        let turn_player = *priority.turn_player();
+        for (item, name, controller) in item_query.iter() {
+            if controller.0 == turn_player {
+                commands.entity(item).remove::<Exhausted>();
+                game_log!(log, Zones, Normal, "\"{}\" readies", name.0);
+            }
+        }
+    }
+}
+
+pub mod team_systems {
+    use super::*;
+
+    // Teammates sharing their life all end up on the team total,
+    // which takes in what each of them gained or lost since the last time
+    pub fn share_life(
+        mut shared: ResMut<SharedLife>,
+        mut hero_query: Query<(&Team, &mut Health), With<Hero>>,
+        mut log: ResMut<GameLog>
+    ) {
+        if shared.0.is_empty() {
+            return;
+        }
+        let mut totals: HashMap<u8, i32> = HashMap::new();
+        for (team, health) in hero_query.iter() {
+            if let Some(last) = shared.0.get(&team.0) {
+                *totals.entry(team.0).or_insert(*last as i32) += health.0 as i32 - *last as i32;
+            }
+        }
+        for (team, total) in totals {
+            let total = total.clamp(0, u16::MAX as i32) as u16;
+            if shared.0.get(&team) != Some(&total) {
+                game_log!(log, Triggers, Normal, "Team {} is on {} life", team + 1, total);
+                shared.0.insert(team, total);
+            }
+        }
+        for (team, mut health) in hero_query.iter_mut() {
+            match shared.0.get(&team.0) {
+                Some(total) if health.0 != *total => health.0 = *total,
+                _ => {}
+            }
+        }
+    }
+}
+
+// Conditions that let systems sit out the ticks where nothing they react to happened
+// Each system still checks its own trigger, so an extra run changes nothing
+pub mod run_conditions {
+    use super::*;
+
+    pub fn in_phase(phase: GamePhases) -> impl Fn(Res<GameState>) -> bool + Clone {
+        move |game_state: Res<GameState>| game_state.0 == phase
+    }
+
+    pub fn in_step(step: CombatSteps) -> impl Fn(Res<CombatState>) -> bool + Clone {
+        move |combat_state: Res<CombatState>| combat_state.0.as_ref() == Some(&step)
+    }
+
+    pub fn play_proposed(proposed_event: Res<ProposedEvent>) -> bool {
+        proposed_event.0.is_some()
+    }
+
+    // The combat transition queued this tick is the given one
+    pub fn transition_is(transition: StepTransition) -> impl Fn(Res<CombatTransition>) -> bool + Clone {
+        move |queued: Res<CombatTransition>| queued.0.as_ref() == Some(&transition)
+    }
+
+    pub fn stack_has_cards(stack: Res<Stack>) -> bool {
+        !stack.0.is_empty()
+    }
+
+    pub fn life_shared(shared: Res<SharedLife>) -> bool {
+        !shared.0.is_empty()
+    }
+
+    // Some entity gained the component or had it changed
+    pub fn any_changed<T: Component>(query: Query<(), Changed<T>>) -> bool {
+        !query.is_empty()
+    }
+}
+
+// Points in the turn that card systems can be scheduled at,
+// e.g. my_system.in_set(OnEnter(CombatSteps::DefendStep)) or .in_set(OnExit(GamePhases::ActionPhase))
+// Hooks run once per move, after the phase and step systems of the run that made it
+pub mod hooks {
+    use super::*;
+    use std::hash::Hash;
+    use std::fmt::Debug;
+
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub enum Move {
+        EnterPhase(GamePhases),
+        ExitPhase(GamePhases),
+        EnterStep(CombatSteps),
+        ExitStep(CombatSteps),
+    }
+
+    // Phases and combat steps entered or left during this run, in the order it happened
+    // Cleared once the hooks have run, so it is not saved
+    #[derive(Resource, Clone, Default, Debug)]
+    pub struct Moves(pub Vec<Move>);
+
+    impl Moves {
+        pub fn phase(&mut self, from: GamePhases, to: GamePhases) {
+            self.0.push(Move::ExitPhase(from));
+            self.0.push(Move::EnterPhase(to));
+        }
+
+        // None is outside of combat
+        pub fn step(&mut self, from: Option<CombatSteps>, to: Option<CombatSteps>) {
+            self.0.extend(from.map(Move::ExitStep));
+            self.0.extend(to.map(Move::EnterStep));
+        }
+    }
+
+    // A phase or combat step
+    pub trait Point: Clone + Eq + Hash + Debug + Send + Sync + 'static {
+        fn entered(&self) -> Move;
+        fn exited(&self) -> Move;
+        fn all() -> Vec<Self>;
+    }
+
+    impl Point for GamePhases {
+        fn entered(&self) -> Move {
+            Move::EnterPhase(self.clone())
+        }
+
+        fn exited(&self) -> Move {
+            Move::ExitPhase(self.clone())
+        }
+
+        fn all() -> Vec<Self> {
+            vec![GamePhases::StartPhase, GamePhases::ActionPhase, GamePhases::EndPhase]
+        }
+    }
+
+    impl Point for CombatSteps {
+        fn entered(&self) -> Move {
+            Move::EnterStep(self.clone())
+        }
+
+        fn exited(&self) -> Move {
+            Move::ExitStep(self.clone())
+        }
+
+        fn all() -> Vec<Self> {
+            vec![
+                CombatSteps::LayerStep,
+                CombatSteps::AttackStep,
+                CombatSteps::DefendStep,
+                CombatSteps::ReactionStep,
+                CombatSteps::PreventionStep,
+                CombatSteps::DamageStep,
+                CombatSteps::ResolutionStep,
+                CombatSteps::LinkStep,
+                CombatSteps::CloseStep,
+            ]
+        }
+    }
+
+    #[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+    pub struct OnEnter<P: Point>(pub P);
+
+    #[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+    pub struct OnExit<P: Point>(pub P);
+
+    pub fn moved(point: Move) -> impl Fn(Res<Moves>) -> bool + Clone {
+        move |moves: Res<Moves>| moves.0.contains(&point)
+    }
+
+    pub fn clear_moves(mut moves: ResMut<Moves>) {
+        moves.0.clear();
+    }
+
+    fn configure_point<P: Point>(schedule: &mut Schedule) {
+        for point in P::all() {
+            schedule.configure_sets((
+                OnExit(point.clone())
+                    .in_set(ScheduleSets::ExitHooks)
+                    .run_if(moved(point.exited())),
+                OnEnter(point.clone())
+                    .in_set(ScheduleSets::EnterHooks)
+                    .run_if(moved(point.entered())),
+            ));
+        }
+    }
+
+    // Leaving comes before entering, and both wait for the phase systems and every system that moves between phases or steps
+    pub fn configure(schedule: &mut Schedule) {
+        configure_point::<GamePhases>(schedule);
+        configure_point::<CombatSteps>(schedule);
+        schedule.configure_sets((
+            ScheduleSets::ExitHooks
+                .after(ScheduleSets::StartPhase)
+                .after(ScheduleSets::ActionPhase)
+                .after(ScheduleSets::EndPhase)
+                .after(state_change_systems::end_start_phase)
+                .after(state_change_systems::end_action_phase)
+                .after(state_change_systems::end_end_phase)
+                .after(game_systems::resolve_stack)
+                .after(combat_systems::trigger_layer_step)
+                .after(combat_systems::trigger_attack_step)
+                .after(combat_systems::trigger_defend_step)
+                .after(combat_systems::trigger_reaction_step)
+                .after(combat_systems::trigger_prevention_step)
+                .after(combat_systems::trigger_damage_step)
+                .after(combat_systems::trigger_resolution_step)
+                .after(combat_systems::trigger_link_step)
+                .after(combat_systems::trigger_close_step)
+                .after(combat_systems::close_combat_chain),
+            ScheduleSets::EnterHooks.after(ScheduleSets::ExitHooks),
+        ));
+        schedule.add_systems(
+            clear_moves
+                .after(ScheduleSets::EnterHooks)
+                .run_if(|moves: Res<Moves>| !moves.0.is_empty())
+        );
+    }
+}
+
+pub mod state_change_systems {
+    use super::*;
+
+    // For now, this does nothing.
+    // In the future, we will query for start of start phase triggers
+    pub fn start_start_phase(
+        game_state: Res<GameState>,
+        priority: Res<Priority>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if game_state.is_changed()
+            && game_state.0 == GamePhases::StartPhase
+        {
+            log.record(&clock, priority.priority_hero().copied(), LogEvent::PhaseChanged { phase: GamePhases::StartPhase });
+        }
+    }
+
+    pub fn end_start_phase(
+        stack: Res<Stack>,
+        mut game_state: ResMut<GameState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut log: ResMut<GameLog>
+    ) {
+        // Start phase ends when the stack is empty
+        // No players get priority
+        if game_state.0 == GamePhases::StartPhase && stack.0.is_empty() {
+            game_state.0 = GamePhases::ActionPhase;
+            moves.phase(GamePhases::StartPhase, GamePhases::ActionPhase);
+
+            game_log!(log, Phases, Normal, "Ending start phase");
+        }
+    }
+
+    pub fn start_action_phase(
+        mut hero_query: Query<&mut ActionPoints, With<Hero>>,
+        mut priority: ResMut<Priority>,
+        game_state: Res<GameState>,
+        config: Res<GameConfig>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if game_state.0 == GamePhases::ActionPhase
+            && game_state.is_changed()
+        {
+            priority.cycle_priority();
+            let turn_player = priority.turn_player();
+            log.record(&clock, Some(*turn_player), LogEvent::PhaseChanged { phase: GamePhases::ActionPhase });
+            let mut ap = hero_query.get_mut(*turn_player).expect("Turn player should exist");
+
+            // Give hero their action points for the turn
+            ap.0 = config.action_points;
+
+        }
+    }
+
+    // True when the hero could still legally play an action card from hand,
+    // counting every other card in hand as pitchable
+    #[allow(clippy::type_complexity)]
+    fn has_playable_action(
+        hand: &HandZone,
+        resources: &Resources,
+        hero_class: &CardClass,
+        hero: Entity,
+        card_query: &Query<(&CardType, Option<&Cost>, Option<&Color>, Option<&CardClass>, Option<&Controller>, Option<&ZoneUsage>), Without<Hero>>,
+        cost_of: impl Fn(Entity, u16) -> u16
+    ) -> bool {
+        let pitch_value = |card: &Entity| card_query
+            .get(*card)
+            .ok()
+            .filter(|(.., usage)| ZoneUsage::pitchable(*usage))
+            .and_then(|(_, _, color, ..)| color)
+            .map(|v| v.pitch())
+            .unwrap_or(0);
+        let total_pitch: u16 = hand.0.iter().map(pitch_value).sum();
+
+        hand.0.iter().any(|card| {
+            let Ok((card_type, cost, _, card_class, controller, usage)) = card_query.get(*card) else {
+                return false;
+            };
+            let cost = cost_of(*card, cost.map(|v| v.0).unwrap_or(0));
+            card_type.is_action()
+                && ZoneUsage::playable(usage)
+                && validation_systems::controls(hero, controller).is_ok()
+                && validation_systems::class_allows(card_class, hero_class).is_ok()
+                && cost <= resources.0 + total_pitch - pitch_value(card)
+        })
+    }
+
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub fn end_action_phase(
+        mut hero_query: Query<(&mut ActionPoints, &Resources, &HandZone, &CardClass), With<Hero>>,
+        card_query: Query<(&CardType, Option<&Cost>, Option<&Color>, Option<&CardClass>, Option<&Controller>, Option<&ZoneUsage>), Without<Hero>>,
+        subtype_query: Query<&CardSubTypes>,
+        modifier_query: Query<&CostModifier>,
+        permanent_query: Query<&Controller, With<Health>>,
+        stack: Res<Stack>,
+        attack_layer: Res<AttackLayer>,
+        chain: Res<Chain>,
+        mut priority: ResMut<Priority>,
+        mut game_state: ResMut<GameState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut log: ResMut<GameLog>
+    ) {
+        // Action phase when the last player passes priority
+        // and nothing is on the stack
+        if stack.0.is_empty()
+            && attack_layer.0.is_none()
+            && priority.is_changed() && priority.all_passed()
+            && !chain.open
+            && game_state.0 == GamePhases::ActionPhase
+        {
+            let turn_player = *priority.turn_player();
+            let (mut ap, resources, hand, hero_class) = hero_query
+                .get_mut(turn_player)
+                .expect("Turn player should exist");
+
+            let cost_of = |card: Entity, printed: u16| modified_cost(
+                printed,
+                turn_player,
+                subtype_query.get(card).is_ok_and(|v| v.has_attack()),
+                modifier_query.iter(),
+                |permanent| permanent_query.get(permanent).ok().map(|v| v.0)
+            );
+
+            // The phase waits once for a turn player who could still use their action points
+            if ap.0 > 0
+                && !priority.reminded
+                && has_playable_action(hand, resources, hero_class, turn_player, &card_query, cost_of)
+            {
+                game_log!(log, 
+                    Phases, Normal,
+                    "Turn player still has {} action point(s) and cards they can play. Pass again to end the action phase.",
+                    ap.0
+                );
+                priority.reminded = true;
+                priority.reset();
+                return;
+            }
+
+            // Set turn player action points to 0
+            ap.0 = 0;
+
+            game_state.0 = GamePhases::EndPhase;
+            moves.phase(GamePhases::ActionPhase, GamePhases::EndPhase);
+
+            game_log!(log, Phases, Normal, "Ending action phase");
+        }
+    }
+
+    // For now, this does nothing.
+    // In the future, we will query for start of end phase triggers
+    pub fn start_end_phase(
+        game_state: Res<GameState>,
+        priority: Res<Priority>,
+        mut log: ResMut<GameLog>,
+        clock: Res<GameClock>
+    ) {
+        if game_state.0 == GamePhases::EndPhase 
+            && game_state.is_changed()
+        {
+            log.record(&clock, Some(*priority.turn_player()), LogEvent::PhaseChanged { phase: GamePhases::EndPhase });
+        }
+    }
+
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub fn end_end_phase(
+        mut hero_query: Query<
+            (Entity, &mut Resources, &mut HandZone, &mut DeckZone, &mut PitchZone, &Intellect, &PlayerName),
+            With<Hero>
+        >,
+        mut bank_query: Query<(Entity, &mut BankedResources)>,
+        mut pitch_recycled: EventWriter<PitchRecycled>,
+        mut lost: EventWriter<ResourcesLost>,
+        mut priority: ResMut<Priority>,
+        stack: Res<Stack>,
+        mut game_state: ResMut<GameState>,
+        mut moves: ResMut<hooks::Moves>,
+        mut log: ResMut<GameLog>,
+        mut commands: Commands
+    ) {
+        // End phase ends when the stack is empty
+        // No players get priority, unless the turn player must discard
+        if game_state.0 == GamePhases::EndPhase && stack.0.is_empty() {
+            let turn_player = *priority.turn_player();
+            let (_, _, mut hand, mut deck, _, intellect, player_name) = hero_query
+                .get_mut(turn_player)
+                .expect("Turn player should exist");
+
+            let discarding = priority.choice == Some(PendingChoice::Discard);
+
+            // Draw up to intellect
+            if !discarding {
+                while hand.0.len() < intellect.0 as usize {
+                    let Some(card) = deck.0.pop_front() else {
+                        break;
+                    };
+                    hand.0.push(card);
+                }
+            }
+
+            // Discard down to intellect before the next turn starts
+            let excess = hand.0.len().saturating_sub(intellect.0 as usize);
+            if excess > 0 {
+                if !discarding {
+                    game_log!(log, Phases, Normal, "\"{}\" must discard {} card(s)", player_name.0, excess);
+                    priority.choice = Some(PendingChoice::Discard);
+                    priority.reset();
+                }
+                return;
+            }
+            if discarding {
+                priority.choice = None;
+                priority.pass_all();
+            }
+
+            // Pitched cards go to the bottom of the deck in the order they were pitched
+            // Every hero's floating resources are lost
+            for (hero, mut resources, _, mut deck, mut pitch, _, player_name) in hero_query.iter_mut() {
+                clear_resources(hero, &mut resources, &mut bank_query, &mut commands, &mut lost, &mut log);
+
+                if pitch.0.is_empty() {
+                    continue;
+                }
+                let cards: Vec<Entity> = pitch.0.drain(..).rev().collect();
+                deck.0.extend(cards.iter().copied());
+                game_log!(log, 
+                    Zones, Normal,
+                    "\"{}\" puts {} pitched card(s) on the bottom of their deck",
+                    player_name.0,
+                    cards.len()
+                );
+                pitch_recycled.send(PitchRecycled { hero, cards });
+            }
+
+            game_state.0 = GamePhases::StartPhase;
+            moves.phase(GamePhases::EndPhase, GamePhases::StartPhase);
+            game_log!(log, Phases, Normal, "Ending end phase");
+        }
+    }
+}
+
+pub mod start_up_systems {
+    use super::*;
+
+    pub fn roll_for_first(
+        query: Query<(Entity, &PlayerName), With<Hero>>,
+        team_query: Query<&Team, With<Hero>>,
+        mut priority: ResMut<Priority>,
+        mut dice: ResMut<Dice>,
+        mut log: ResMut<GameLog>
+    ) {
+        let mut maxes: Vec<(Entity, &PlayerName, u32)> = Vec::new();
+        let mut players: Vec<(Entity, &PlayerName)> = query.iter().collect();
+
+        while maxes.is_empty() {
+            for (entity, player_name) in &players {
+                let first_die = dice.roll(6);
+                let second_die = dice.roll(6);
+                let result = first_die + second_die;
+                game_log!(log, 
+                    Phases, Normal,
+                    "\"{}\" rolled {} + {} = {}",
+                    player_name.0,
+                    first_die,
+                    second_die,
+                    result
+                );
+                maxes.push((*entity, player_name, result));
+                let max = maxes.iter().map(|v| v.2).max().unwrap();
+                maxes.retain(|v| v.2 == max);
+            }
+            if maxes.len() > 1 {
+                game_log!(log, Phases, Normal, "Rerolling ties");
+                players = maxes.iter().map(|v| (v.0, v.1)).collect();
+                maxes.clear();
+            }
+        }
+
+        // Turns go around the table from the winner, who starts
+        let turn_player = maxes.first().unwrap().0;
+        for (entity, _) in query.iter().skip_while(|v| v.0 != turn_player) {
+            priority.holding.push_back(entity);
+        }
+        for (entity, _) in query.iter().take_while(|v| v.0 != turn_player) {
+            priority.holding.push_back(entity);
+        }
+
+        // Teammates are seated apart so the teams take turns in alternation
+        if !team_query.is_empty() {
+            let mut teams: Vec<Vec<Entity>> = Vec::new();
+            for hero in priority.holding.drain(..) {
+                let team = team_query.get(hero).ok();
+                match teams.iter_mut().find(|v| team.is_some() && team_query.get(v[0]).ok() == team) {
+                    Some(members) => members.push(hero),
+                    None => teams.push(vec![hero])
+                }
+            }
+            for round in 0..teams.iter().map(|v| v.len()).max().unwrap_or(0) {
+                for members in &teams {
+                    if let Some(hero) = members.get(round) {
+                        priority.holding.push_back(*hero);
+                    }
+                }
+            }
+        }
+        game_log!(log, Phases, Normal, "Turn order {:?}", priority.holding);
+    }
+
+    pub fn shuffle_decks(mut query: Query<&mut DeckZone>, mut dice: ResMut<Dice>) {
+        for mut deck in query.iter_mut() {
+            dice.shuffle(&mut deck.0);
+        }
+    }
+}
+
+
+// #[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub enum EventType {
+    PlayCard(PlayCard),
+    PassPriority(PassPriority),
+    PitchCard(PitchCard),
+    DeclareBlocks(DeclareBlocks),
+    ActivateHeroAbility(ActivateHeroAbility),
+    ActivateItem(ActivateItem),
+    DiscardCard(DiscardCard),
+    PayArcaneBarrier(PayArcaneBarrier),
+    ChooseSearch(ChooseSearch),
+    ArrangeDeck(ArrangeDeck),
+    // Takes back a proposed play that has not been paid for
+    Cancel(CancelPlay),
+    Save(String),
+    Load(String),
+    // Previews a sequence of actions without playing them
+    Plan(planner::Plan),
+    // Changes logging verbosity, for every subsystem when none is given
+    Log { subsystem: Option<logging::Subsystem>, level: logging::Verbosity },
+    // Proposes stopping the game now and finishing it later
+    Adjourn { hero: Entity },
+    // Looks up how the engine applies a rule, listing topics when empty
+    Rules(String),
+    // Lists every hand's size and the cards in them the hero can see
+    Hands { hero: Entity },
+    // Shows everything printed on a card the hero can see
+    Inspect { hero: Entity, card: Entity },
+    // Changes when the hero is asked to act
+    SetStops { hero: Entity, stops: Stops },
+    End
+}
+
+impl EventType {
+    fn hero(&self) -> Option<Entity> {
+        match &self {
+            EventType::PlayCard(event) => Some(event.hero),
+            EventType::PassPriority(event) => Some(event.hero),
+            EventType::PitchCard(event) => Some(event.hero),
+            EventType::DeclareBlocks(event) => Some(event.hero),
+            EventType::ActivateHeroAbility(event) => Some(event.hero),
+            EventType::ActivateItem(event) => Some(event.hero),
+            EventType::DiscardCard(event) => Some(event.hero),
+            EventType::PayArcaneBarrier(event) => Some(event.hero),
+            EventType::ChooseSearch(event) => Some(event.hero),
+            EventType::ArrangeDeck(event) => Some(event.hero),
+            EventType::Cancel(event) => Some(event.hero),
+            EventType::Plan(plan) => Some(plan.hero),
+            EventType::Adjourn { hero } => Some(*hero),
+            EventType::Hands { hero } => Some(*hero),
+            EventType::Inspect { hero, .. } => Some(*hero),
+            EventType::SetStops { hero, .. } => Some(*hero),
+            EventType::Save(_)
+            | EventType::Load(_)
+            | EventType::Log { .. }
+            | EventType::Rules(_)
+            | EventType::End => None
+        }
+    }
+
+    // Entities named by the event, other than the hero sending it
+    fn entities(&self) -> Vec<Entity> {
+        match &self {
+            EventType::PlayCard(event) => std::iter::once(event.card)
+                .chain(event.targets.iter().copied())
+                .chain(event.additional_costs.iter().copied())
+                .collect(),
+            EventType::PitchCard(event) => vec![event.card],
+            EventType::DeclareBlocks(event) => event.blocks.clone(),
+            EventType::ActivateItem(event) => vec![event.item],
+            EventType::DiscardCard(event) => vec![event.card],
+            EventType::ChooseSearch(event) => event.card.into_iter().collect(),
+            EventType::Inspect { card, .. } => vec![*card],
+            EventType::ArrangeDeck(event) => event.arrangement.top
+                .iter()
+                .chain(&event.arrangement.bottom)
+                .copied()
+                .collect(),
+            EventType::Plan(plan) => plan.steps
+                .iter()
+                .flat_map(|step| match step {
+                    planner::PlanStep::Pitch(card) => vec![*card],
+                    planner::PlanStep::Play { card, targets } => std::iter::once(*card).chain(targets.iter().copied()).collect()
+                })
+                .collect(),
+            _ => Vec::new()
+        }
+    }
+
+    // Rejects events naming entities that are not in the game, before anything acts on them
+    // Ids can come from anywhere, e.g. an old replay or a client's message
+    pub fn check_entities(&self, world: &World) -> Result<(), CommandError> {
+        if let Some(hero) = self.hero() {
+            if world.get_entity(hero).is_none() {
+                return Err(CommandError::NoSuchEntity(hero));
+            }
+            if world.get::<Hero>(hero).is_none() {
+                return Err(CommandError::NotAHero(hero));
+            }
+        }
+        match self.entities().into_iter().find(|v| world.get_entity(*v).is_none()) {
+            Some(entity) => Err(CommandError::NoSuchEntity(entity)),
+            None => Ok(())
+        }
+    }
+
+    // Sends the event into the world
+    // Returns false when the game should end
+    // Events naming entities that do not exist are dropped, with the reason written to the log
+    pub fn send(self, world: &mut World) -> bool {
+        if let Err(err) = self.check_entities(world) {
+            game_log!(world.resource_mut::<GameLog>(), Actions, Normal, "{}", err);
+            return true;
+        }
+        match self {
+            EventType::PlayCard(event) => {
+                world.send_event(event);
+            },
+            EventType::PassPriority(event) => {
+                world.send_event(event);
+            }
+            EventType::PitchCard(event) => {
+                world.send_event(event);
+            }
+            EventType::DeclareBlocks(event) => {
+                world.send_event(event);
+            }
+            EventType::ActivateHeroAbility(event) => {
+                world.send_event(event);
+            }
+            EventType::ActivateItem(event) => {
+                world.send_event(event);
+            }
+            EventType::DiscardCard(event) => {
+                world.send_event(event);
+            }
+            EventType::PayArcaneBarrier(event) => {
+                world.send_event(event);
+            }
+            EventType::ChooseSearch(event) => {
+                world.send_event(event);
+            }
+            EventType::ArrangeDeck(event) => {
+                world.send_event(event);
+            }
+            EventType::Cancel(event) => {
+                world.send_event(event);
+            }
+            EventType::SetStops { hero, stops } => {
+                let name = world.get::<PlayerName>(hero).map(|v| v.0.clone()).unwrap_or_default();
+                game_log!(world.resource_mut::<GameLog>(), Priority, Normal, "\"{}\" stops at {}", name, stops.describe());
+                world.entity_mut(hero).insert(stops);
+            }
+            // Saving, loading, planning, logging, adjourning, rules lookups, hand listings and inspecting are handled by the game loop
+            EventType::Save(_)
+            | EventType::Load(_)
+            | EventType::Plan(_)
+            | EventType::Log { .. }
+            | EventType::Adjourn { .. }
+            | EventType::Rules(_)
+            | EventType::Hands { .. }
+            | EventType::Inspect { .. } => {}
+            EventType::End => {return false;}
+        }
+        true
+    }
+}
+
+// Why a command could not be turned into an event
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CommandError {
+    UnknownCommand(String),
+    // Names the argument, e.g. "Card to play"
+    MissingArgument(&'static str),
+    // Names the argument that should have been an entity id, e.g. "Target"
+    NotAnEntity(&'static str),
+    // A handle that names no one, e.g. "h5" in a two player game
+    UnknownHandle(String),
+    // An id that does not belong to anything in the game, or no longer does
+    NoSuchEntity(Entity),
+    NotAHero(Entity),
+    // An argument that is present but not allowed, e.g. an unknown log level
+    Invalid(String),
+    NotYourCard(Entity),
+    NotYourHero(Entity),
+    NoPriority,
+    Io(String)
+}
+
+impl CommandError {
+    // Stable name for frontends, which may word the message differently
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CommandError::UnknownCommand(_) => "unknown_command",
+            CommandError::MissingArgument(_) => "missing_argument",
+            CommandError::NotAnEntity(_) => "not_an_entity",
+            CommandError::UnknownHandle(_) => "unknown_handle",
+            CommandError::NoSuchEntity(_) => "no_such_entity",
+            CommandError::NotAHero(_) => "not_a_hero",
+            CommandError::Invalid(_) => "invalid",
+            CommandError::NotYourCard(_) => "not_your_card",
+            CommandError::NotYourHero(_) => "not_your_hero",
+            CommandError::NoPriority => "no_priority",
+            CommandError::Io(_) => "io"
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::UnknownCommand(command) => write!(f, "Unknown command \"{}\"", command),
+            CommandError::MissingArgument(argument) => write!(f, "{} is not specified", argument),
+            CommandError::NotAnEntity(argument) => write!(f, "{} must be an int or a handle", argument),
+            CommandError::UnknownHandle(handle) => write!(f, "Unknown handle \"{}\"", handle),
+            CommandError::NoSuchEntity(entity) => write!(f, "Entity {} does not exist", entity.index()),
+            CommandError::NotAHero(entity) => write!(f, "Entity {} is not a hero", entity.index()),
+            CommandError::Invalid(reason) => write!(f, "{}", reason),
+            CommandError::NotYourCard(card) => write!(f, "Card {} is not yours", card.index()),
+            CommandError::NotYourHero(hero) => {
+                write!(f, "Hero {} is not yours. Events must be sent for your own hero", hero.index())
+            },
+            CommandError::NoPriority => write!(f, "No player has priority"),
+            CommandError::Io(err) => write!(f, "IO error: {}", err)
+        }
+    }
+}
+
+// The pass to send instead of asking the priority holder, when their stops allow it
+// Frontends check this before prompting
+pub fn auto_pass(world: &World) -> Option<EventType> {
+    let priority = world.resource::<Priority>();
+    let hero = *priority.priority_hero()?;
+    let stops = world.get::<Stops>(hero)?;
+    // Blocks, discards and other choices always need the player, as does paying for a play
+    if !priority.someone_has_priority()
+        || !priority.has_priority(&hero)
+        || world.resource::<ProposedEvent>().0.is_some()
+    {
+        return None;
+    }
+
+    let chain = world.resource::<Chain>();
+    let combat_step = world.resource::<CombatState>().0.as_ref();
+    let timing = validation_systems::Timing {
+        priority,
+        phase: &world.resource::<GameState>().0,
+        combat_step,
+        stack_empty: world.resource::<Stack>().is_empty(),
+        link: chain.links.last().map(|v| (v.attacker, v.targets.as_slice()))
+    };
+    let action_window = validation_systems::check_speed(hero, true, &timing).is_ok();
+    let skipped_step = !action_window && match (combat_step, &stops.combat_steps) {
+        (Some(step), Some(steps)) => !steps.contains(step),
+        _ => false
+    };
+
+    let playable = |card_type: &CardType| if card_type.is_reaction() {
+        validation_systems::check_reaction(hero, card_type, &timing).is_ok()
+    } else {
+        card_type.is_playable() && validation_systems::check_speed(hero, card_type.is_action(), &timing).is_ok()
+    };
+    let ability = world
+        .get::<HeroAbility>(hero)
+        .filter(|_| world.get::<UsageLimit>(hero).is_none_or(|v| v.available()))
+        .is_some_and(|v| validation_systems::check_speed(hero, v.action, &timing).is_ok())
+        || ready_items(world, hero)
+            .into_iter()
+            .filter_map(|item| world.get::<ItemAbility>(item))
+            .any(|v| validation_systems::check_speed(hero, v.action, &timing).is_ok());
+    let nothing_to_play = stops.unless_playable
+        && !ability
+        && !world
+            .get::<HandZone>(hero)
+            .map(|v| v.0.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter(|card| ZoneUsage::playable(world.get::<ZoneUsage>(**card)))
+            .any(|card| world.get::<CardType>(*card).is_some_and(playable));
+
+    (skipped_step || nothing_to_play).then_some(EventType::PassPriority(PassPriority { hero }))
+}
+
+// Items in play the hero controls that could pay for their ability, ignoring timing
+pub fn ready_items(world: &World, hero: Entity) -> Vec<Entity> {
+    world
+        .iter_entities()
+        .filter(|v| v.contains::<InPlay>() && v.get::<Controller>().is_some_and(|v| v.0 == hero))
+        .filter(|v| {
+            let Some(ability) = v.get::<ItemAbility>() else {
+                return false;
+            };
+            let counters = ability.counters.is_none_or(|(kind, amount)| {
+                v.get::<Counters>().map(|v| v.count(kind)).unwrap_or(0) >= amount
+            });
+            counters && !(ability.exhaust && v.contains::<Exhausted>())
+        })
+        .map(|v| v.id())
+        .collect()
+}
+
+// Parses an entity id, naming the argument if it is not one
+fn parse_entity(argument: &'static str, piece: &str) -> Result<Entity, CommandError> {
+    piece.parse::<u32>()
+        .map(Entity::from_raw)
+        .map_err(|_| CommandError::NotAnEntity(argument))
+}
+
+// Short names players can type instead of entity ids
+// "h1" is the first seat's hero, "c6" is card 6, and plain ids still work
+// Set up with the table, so it is not saved
+#[derive(Resource, Default)]
+pub struct EntityHandles {
+    // Heroes in seat order
+    heroes: Vec<Entity>
+}
+
+impl EntityHandles {
+    pub fn new(heroes: Vec<Entity>) -> Self {
+        EntityHandles { heroes }
+    }
+
+    // The handle a player would type for the entity
+    pub fn handle(&self, entity: Entity) -> String {
+        match self.heroes.iter().position(|v| *v == entity) {
+            Some(seat) => format!("h{}", seat + 1),
+            None => format!("c{}", entity.index())
+        }
+    }
+
+    // Parses a handle or plain id, naming the argument if it is neither
+    pub fn parse(&self, argument: &'static str, piece: &str) -> Result<Entity, CommandError> {
+        let piece = piece.to_lowercase();
+        if let Some(seat) = piece.strip_prefix('h') {
+            return seat.parse::<usize>()
+                .ok()
+                .and_then(|v| v.checked_sub(1))
+                .and_then(|v| self.heroes.get(v))
+                .copied()
+                .ok_or(CommandError::UnknownHandle(piece.clone()));
+        }
+        parse_entity(argument, piece.strip_prefix('c').unwrap_or(&piece))
+    }
+}
+
+// Parses "log <level>" or "log <subsystem> <level>"
+pub fn parse_log_command(args: &str) -> Result<EventType, CommandError> {
+    let mut pieces = args.split_whitespace();
+    let (subsystem, level) = match (pieces.next(), pieces.next()) {
+        (Some(level), None) => (None, level),
+        (Some(subsystem), Some(level)) => (
+            Some(logging::Subsystem::parse(subsystem)
+                .ok_or(CommandError::Invalid(format!("Unknown subsystem \"{}\"", subsystem)))?),
+            level
+        ),
+        _ => return Err(CommandError::Invalid(String::from("Usage: log [subsystem] <quiet|normal|debug>")))
+    };
+    let level = logging::Verbosity::parse(level)
+        .ok_or(CommandError::Invalid(format!("Unknown verbosity \"{}\"", level)))?;
+    Ok(EventType::Log { subsystem, level })
+}
+
+// Real dumbed down method to engage with the system
+#[cfg(feature = "cli")]
+pub fn read_event_from_user(
+    handles: &EntityHandles
+) -> Result<EventType, CommandError> {
+    let mut buffer = String::new();
+    io::stdin().read_line(&mut buffer)
+        .map_err(|err| CommandError::Io(err.to_string()))?;
+
+    parse_event(&buffer, handles)
+}
+
+// Parses one command, e.g. "3 play 6 2" or "h2 play c6 h1"
+pub fn parse_event(buffer: &str, handles: &EntityHandles) -> Result<EventType, CommandError> {
+    let parse_entity = |argument: &'static str, piece: &str| handles.parse(argument, piece);
+
+    let buffer = buffer.trim();
+
+    if buffer.to_lowercase().as_str() == "end" {
+        return Ok(EventType::End);
+    }
+    if buffer.to_lowercase().as_str() == "rules" {
+        return Ok(EventType::Rules(String::new()));
+    }
+
+    // Save and load take a file path instead of a hero
+    if let Some((command, path)) = buffer.split_once(' ') {
+        match command.to_lowercase().as_str() {
+            "save" => return Ok(EventType::Save(String::from(path.trim()))),
+            "load" => return Ok(EventType::Load(String::from(path.trim()))),
+            "log" => return parse_log_command(path),
+            "rules" => return Ok(EventType::Rules(String::from(path.trim()))),
+            _ => {}
+        }
+    }
+
+    // split command into pieces
+    let mut pieces = buffer.split(" ");
+
+    // get hero entity id
+    let hero_entity = parse_entity(
+        "Hero",
+        pieces.next().ok_or(CommandError::MissingArgument("Hero"))?
+    )?;
+
+    // get event keyword
+    let event = pieces.next()
+        .ok_or(CommandError::MissingArgument("Event"))?;
+
+    match event.to_lowercase().as_str().trim() {
+        // Parse event to play card
+        "play" => {
+            // Parse card entity id
+            let card_entity = parse_entity(
+                "Card",
+                pieces.next().ok_or(CommandError::MissingArgument("Card to play"))?
+            )?;
+
+            // Everything after the card is a target, then the cards paid for an additional cost after a "/"
+            let rest = pieces.collect::<Vec<&str>>().join(" ");
+            let (targets, additional_costs) = rest.split_once('/').unwrap_or((rest.as_str(), ""));
+            let targets = targets
+                .split_whitespace()
+                .map(|p| parse_entity("Target", p))
+                .collect::<Result<Vec<Entity>, CommandError>>()?;
+            let additional_costs = additional_costs
+                .split_whitespace()
+                .map(|p| parse_entity("Card", p))
+                .collect::<Result<Vec<Entity>, CommandError>>()?;
+            Ok(EventType::PlayCard(
+                PlayCard {
+                    hero: hero_entity,
+                    card: card_entity,
+                    targets,
+                    additional_costs
+                }
+            ))
+        },
+        // Parse event to pass priority
+        "pass" => Ok(
+            EventType::PassPriority(
+                PassPriority {hero: hero_entity}
+            )
+        ),
+        "pitch" => {
+            // Parse card entity id
+            let card_entity = parse_entity(
+                "Card",
+                pieces.next().ok_or(CommandError::MissingArgument("Card to pitch"))?
+            )?;
+
+            Ok(EventType::PitchCard(
+                PitchCard { hero: hero_entity, card: card_entity }
+            ))
+        },
+        "block" => {
+            // Parse card entities
+            let cards = pieces
+                .map(|p| parse_entity("Card", p))
+                .collect::<Result<Vec<Entity>, CommandError>>()?;
+
+            Ok(EventType::DeclareBlocks(
+                DeclareBlocks { hero: hero_entity, blocks: cards }
+            ))
+        },
+        "discard" => {
+            // Parse card entity id
+            let card_entity = parse_entity(
+                "Card",
+                pieces.next().ok_or(CommandError::MissingArgument("Card to discard"))?
+            )?;
+
+            Ok(EventType::DiscardCard(
+                DiscardCard { hero: hero_entity, card: card_entity }
+            ))
+        },
+        "adjourn" => Ok(EventType::Adjourn { hero: hero_entity }),
+        "hands" => Ok(EventType::Hands { hero: hero_entity }),
+        "inspect" => {
+            let card = parse_entity(
+                "Card",
+                pieces.next().ok_or(CommandError::MissingArgument("Card to inspect"))?
+            )?;
+
+            Ok(EventType::Inspect { hero: hero_entity, card })
+        },
+        // Parse event to change stops, e.g. "stops playable defend reaction"
+        "stops" => Ok(EventType::SetStops {
+            hero: hero_entity,
+            stops: Stops::parse(&pieces.collect::<Vec<&str>>())?
+        }),
+        // Parse a plan, e.g. "play 0 3; pitch 1"
+        "plan" => {
+            let steps = pieces
+                .collect::<Vec<&str>>()
+                .join(" ")
+                .split(';')
+                .map(|v| planner::PlanStep::parse(v, handles))
+                .collect::<Result<Vec<planner::PlanStep>, CommandError>>()?;
+
+            Ok(EventType::Plan(planner::Plan { hero: hero_entity, steps }))
+        },
+        // Parse event to pay arcane barrier
+        "barrier" => {
+            let amount = pieces.next()
+                .ok_or(CommandError::MissingArgument("Amount to pay"))?
+                .parse::<u16>()
+                .map_err(|_| CommandError::Invalid(String::from("Amount must be an int")))?;
+
+            Ok(EventType::PayArcaneBarrier(
+                PayArcaneBarrier { hero: hero_entity, amount }
+            ))
+        },
+        // Parse event to take a card found by a deck search, or nothing
+        "search" => {
+            let card = pieces.next()
+                .map(|v| parse_entity("Card", v))
+                .transpose()?;
+
+            Ok(EventType::ChooseSearch(ChooseSearch { hero: hero_entity, card }))
+        },
+        // Parse event to put back cards looked at, e.g. "opt 21 20 / 22"
+        "opt" => {
+            let rest = pieces.collect::<Vec<&str>>().join(" ");
+            let (top, bottom) = rest.split_once('/').unwrap_or((rest.as_str(), ""));
+            let cards = |text: &str| text
+                .split_whitespace()
+                .map(|v| parse_entity("Card", v))
+                .collect::<Result<Vec<Entity>, CommandError>>();
+
+            Ok(EventType::ArrangeDeck(ArrangeDeck {
+                hero: hero_entity,
+                arrangement: Arrangement { top: cards(top)?, bottom: cards(bottom)? }
+            }))
+        },
+        // Parse event to take back an unpaid play
+        "cancel" => Ok(EventType::Cancel(CancelPlay { hero: hero_entity })),
+        // Parse event to activate the hero's ability, or an item's when one is named
+        "activate" => match pieces.next() {
+            Some(item) => Ok(EventType::ActivateItem(ActivateItem {
+                hero: hero_entity,
+                item: parse_entity("Item to activate", item)?
+            })),
+            None => Ok(EventType::ActivateHeroAbility(ActivateHeroAbility { hero: hero_entity }))
+        },
+        other => Err(CommandError::UnknownCommand(String::from(other)))
+    }
+}
+
+#[cfg(feature = "cli")]
+pub mod input_sources {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    // Where player events come from
+    pub trait InputSource {
+        // Reads the next event from the player currently holding priority
+        fn next_event(
+            &mut self,
+            priority_hero: Option<Entity>,
+            world: &World
+        ) -> Result<EventType, CommandError>;
+
+        // Who state updates are sent to
+        fn viewers(&self) -> Vec<state_view::Viewer> {
+            Vec::new()
+        }
+
+        // Sends the changes in game state since the viewer's last update
+        fn send_state(&mut self, _viewer: state_view::Viewer, _diff: &serde_json::Value) {}
+
+        // Asks one player a yes or no question outside of the normal turn order
+        fn ask(&mut self, hero: Entity, question: &str, world: &World) -> bool {
+            let name = world
+                .get::<PlayerName>(hero)
+                .map(|v| v.0.clone())
+                .unwrap_or(format!("hero {}", hero.index()));
+            println!("\"{}\": {} (y/n)", name, question);
+            let mut buffer = String::new();
+            io::stdin().read_line(&mut buffer).is_ok()
+                && buffer.trim().eq_ignore_ascii_case("y")
+        }
+
+        // Sends a message to every player that is not a state change
+        fn notify(&mut self, _message: &serde_json::Value) {}
+
+        // Lets players be notified from outside the game loop, e.g. on shutdown
+        fn notifier(&self) -> Box<dyn Fn(&serde_json::Value) + Send> {
+            Box::new(|_| {})
+        }
+    }
+
+    // Every player shares one terminal
+    pub struct StdinInput;
+
+    // Shows who is to act and how many resources they have floating
+    fn print_prompt(priority_hero: Option<Entity>, world: &World) {
+        let Some(hero) = priority_hero else {
+            return;
+        };
+        let name = world.get::<PlayerName>(hero).map(|v| v.0.as_str()).unwrap_or("?");
+        let floating = world.get::<Resources>(hero).map(|v| v.0).unwrap_or(0);
+        print!("[\"{}\" ({}) | {} floating] > ", name, hero.index(), floating);
+        let _ = io::stdout().flush();
+    }
+
+    impl InputSource for StdinInput {
+        fn next_event(
+            &mut self,
+            priority_hero: Option<Entity>,
+            world: &World
+        ) -> Result<EventType, CommandError> {
+            print_prompt(priority_hero, world);
+            read_event_from_user(world.resource::<EntityHandles>())
+        }
+    }
+
+    // Players share one terminal but take turns looking at it
+    #[derive(Default)]
+    pub struct HotSeatInput {
+        // Player currently at the keyboard
+        seated: Option<Entity>
+    }
+
+    impl HotSeatInput {
+        fn hand_out(&mut self, hero: Entity, world: &World) -> Result<(), CommandError> {
+            let name = world
+                .get::<PlayerName>(hero)
+                .map(|v| v.0.clone())
+                .unwrap_or(format!("hero {}", hero.index()));
+
+            // Hide whatever the previous player was looking at
+            print!("\x1B[2J\x1B[H");
+            println!("Pass the keyboard to \"{}\" and press enter", name);
+            let mut buffer = String::new();
+            io::stdin().read_line(&mut buffer)
+                .map_err(|err| CommandError::Io(err.to_string()))?;
+            self.seated = Some(hero);
+
+            // Reveal the new player's hand
+            println!("\"{}\" (hero {}) hand:", name, hero.index());
+            for card in world.get::<HandZone>(hero).map(|v| v.0.as_slice()).unwrap_or_default() {
+                let card_name = world
+                    .get::<CardName>(*card)
+                    .map(|v| v.0.as_str())
+                    .unwrap_or("Unknown");
+                println!("  {} \"{}\"", card.index(), card_name);
+            }
+            Ok(())
+        }
+    }
+
+    impl InputSource for HotSeatInput {
+        fn next_event(
+            &mut self,
+            priority_hero: Option<Entity>,
+            world: &World
+        ) -> Result<EventType, CommandError> {
+            if let Some(hero) = priority_hero {
+                if self.seated != Some(hero) {
+                    self.hand_out(hero, world)?;
+                }
+            }
+            print_prompt(priority_hero, world);
+            read_event_from_user(world.resource::<EntityHandles>())
+        }
+    }
+
+    struct Connection {
+        hero: Entity,
+        reader: BufReader<TcpStream>,
+        writer: TcpStream
+    }
+
+    impl Connection {
+        fn send(&mut self, message: &serde_json::Value) {
+            // A dropped client is noticed on the next read
+            let _ = writeln!(self.writer, "{}", message);
+        }
+    }
+
+    // Each player connects over TCP and sends EventType messages as JSON, one per line
+    pub struct TcpInput {
+        connections: Vec<Connection>
+    }
+
+    impl TcpInput {
+        // Waits for one connection per hero, assigned in order of arrival
+        pub fn listen(address: &str, heroes: &[Entity]) -> io::Result<Self> {
+            let listener = TcpListener::bind(address)?;
+            println!("Waiting for {} players on {}", heroes.len(), address);
+
+            let mut connections = Vec::new();
+            for hero in heroes {
+                let (stream, peer) = listener.accept()?;
+                println!("Player connected from {} as hero {}", peer, hero.index());
+                let mut connection = Connection {
+                    hero: *hero,
+                    reader: BufReader::new(stream.try_clone()?),
+                    writer: stream
+                };
+                connection.send(&serde_json::json!({ "hero": hero.index() }));
+                connections.push(connection);
+            }
+
+            Ok(TcpInput { connections })
+        }
+    }
+
+    impl InputSource for TcpInput {
+        fn next_event(
+            &mut self,
+            priority_hero: Option<Entity>,
+            world: &World
+        ) -> Result<EventType, CommandError> {
+            let hero = priority_hero.ok_or(CommandError::NoPriority)?;
+            let connection = self.connections
+                .iter_mut()
+                .find(|c| c.hero == hero)
+                .ok_or(CommandError::Io(format!("No player connected for hero {}", hero.index())))?;
+
+            let mut buffer = String::new();
+            let read = connection.reader.read_line(&mut buffer)
+                .map_err(|err| CommandError::Io(err.to_string()))?;
+
+            // Connection closed
+            if read == 0 {
+                println!("Hero {} disconnected", hero.index());
+                return Ok(EventType::End);
+            }
+
+            let result = serde_json::from_str::<EventType>(buffer.trim())
+                .map_err(|err| CommandError::Invalid(format!("Invalid message: {}", err)))
+                .and_then(|event| {
+                    match event.hero() {
+                        Some(sender) if sender != hero => Err(CommandError::NotYourHero(sender)),
+                        _ => Ok(event)
+                    }
+                })
+                .and_then(|event| {
+                    // Cards someone else controls cannot be played or pitched
+                    let card = match &event {
+                        EventType::PlayCard(v) => Some(v.card),
+                        EventType::PitchCard(v) => Some(v.card),
+                        EventType::DiscardCard(v) => Some(v.card),
+                        _ => None
+                    };
+                    match card.and_then(|v| world.get::<Controller>(v)) {
+                        Some(controller) if controller.0 != hero => {
+                            Err(CommandError::NotYourCard(card.unwrap()))
+                        },
+                        _ => Ok(event)
+                    }
+                });
+
+            if let Err(err) = &result {
+                connection.send(&serde_json::json!({ "error": err.to_string(), "kind": err.kind() }));
+            }
+            result
+        }
+
+        fn viewers(&self) -> Vec<state_view::Viewer> {
+            self.connections
+                .iter()
+                .map(|c| state_view::Viewer::Player(c.hero))
+                .collect()
+        }
+
+        fn send_state(&mut self, viewer: state_view::Viewer, diff: &serde_json::Value) {
+            let message = serde_json::json!({ "diff": diff });
+            for connection in &mut self.connections {
+                if viewer == state_view::Viewer::Player(connection.hero) {
+                    connection.send(&message);
+                }
+            }
+        }
+
+        // Clients answer with {"answer": true} or {"answer": false}
+        fn ask(&mut self, hero: Entity, question: &str, _world: &World) -> bool {
+            let Some(connection) = self.connections.iter_mut().find(|c| c.hero == hero) else {
+                return false;
+            };
+            connection.send(&serde_json::json!({ "question": question }));
+
+            let mut buffer = String::new();
+            match connection.reader.read_line(&mut buffer) {
+                Ok(read) if read > 0 => serde_json::from_str::<serde_json::Value>(buffer.trim())
+                    .ok()
+                    .and_then(|v| v.get("answer").and_then(|v| v.as_bool()))
+                    .unwrap_or(false),
+                _ => false
+            }
+        }
+
+        fn notify(&mut self, message: &serde_json::Value) {
+            for connection in &mut self.connections {
+                connection.send(message);
+            }
+        }
+
+        fn notifier(&self) -> Box<dyn Fn(&serde_json::Value) + Send> {
+            let writers = self.connections
+                .iter()
+                .filter_map(|c| c.writer.try_clone().ok())
+                .collect::<Vec<TcpStream>>();
+            Box::new(move |message| {
+                for mut writer in &writers {
+                    let _ = writeln!(writer, "{}", message);
+                }
+            })
+        }
+    }
+}
+
+pub mod state_view {
+    use super::*;
+    use serde_json::{json, Map, Value};
+
+    // Who a snapshot is for
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+    pub enum Viewer {
+        Player(Entity),
+        // Sees only what every player can see
+        Spectator
+    }
+
+    impl Viewer {
+        fn can_see_hand(&self, hero: Entity) -> bool {
+            *self == Viewer::Player(hero)
+        }
+
+        fn can_see_reveal(&self, to: Option<Entity>) -> bool {
+            to.is_none_or(|hero| *self == Viewer::Player(hero))
+        }
+    }
+
+    // Number of cards in every hero's hand, which every player may know
+    pub fn hand_sizes(world: &mut World) -> Vec<(Entity, usize)> {
+        let mut hand_query = world.query_filtered::<(Entity, &HandZone), With<Hero>>();
+        hand_query.iter(world).map(|(hero, hand)| (hero, hand.0.len())).collect()
+    }
+
+    // Cards in the hero's hand the viewer can see
+    // The hero sees the whole hand, everyone else only what has been revealed to them
+    pub fn visible_hand(world: &mut World, viewer: Viewer, hero: Entity) -> Vec<Entity> {
+        let hand = world.get::<HandZone>(hero).map(|v| v.0.clone()).unwrap_or_default();
+        if viewer.can_see_hand(hero) {
+            return hand;
+        }
+        let mut reveal_query = world.query::<&Revealed>();
+        let revealed = reveal_query
+            .iter(world)
+            .filter(|v| viewer.can_see_reveal(v.to))
+            .map(|v| v.card)
+            .collect::<Vec<Entity>>();
+        hand.into_iter().filter(|card| revealed.contains(card)).collect()
+    }
+
+    // One line per hero with their hand size and the cards in it the viewer can see
+    pub fn describe_hands(world: &mut World, viewer: Viewer) -> String {
+        let name = |world: &World, entity: Entity| world
+            .get::<CardName>(entity)
+            .map(|v| v.0.clone())
+            .unwrap_or(format!("{}", entity.index()));
+        let mut lines = Vec::new();
+        for (hero, size) in hand_sizes(world) {
+            let visible = visible_hand(world, viewer, hero)
+                .into_iter()
+                .map(|card| format!("{} \"{}\"", card.index(), name(world, card)))
+                .collect::<Vec<String>>();
+            let player = world.get::<PlayerName>(hero).map(|v| v.0.clone()).unwrap_or_default();
+            if visible.is_empty() {
+                lines.push(format!("\"{}\" has {} card(s) in hand", player, size));
+            } else {
+                lines.push(format!("\"{}\" has {} card(s) in hand: {}", player, size, visible.join(", ")));
+            }
+        }
+        lines.join("\n")
+    }
+
+    // Cards in a deck, or in a hand the viewer cannot see, are hidden
+    pub fn can_see_card(world: &mut World, viewer: Viewer, card: Entity) -> bool {
+        let mut zone_query = world.query_filtered::<(Entity, &HandZone, &DeckZone), With<Hero>>();
+        let zones = zone_query
+            .iter(world)
+            .map(|(hero, hand, deck)| (hero, hand.0.contains(&card), deck.0.contains(&card)))
+            .collect::<Vec<(Entity, bool, bool)>>();
+        zones.into_iter().all(|(hero, in_hand, in_deck)| {
+            !in_deck && (!in_hand || visible_hand(world, viewer, hero).contains(&card))
+        })
+    }
+
+    // Everything printed on the card, one line each: name, types, stats, keywords and rules text
+    pub fn inspect(world: &mut World, viewer: Viewer, card: Entity) -> String {
+        let handle = world.resource::<EntityHandles>().handle(card);
+        if !can_see_card(world, viewer, card) {
+            return format!("Card {} is hidden from you", handle);
+        }
+        let name = world.get::<CardName>(card).map(|v| v.0.clone()).unwrap_or(String::from("Unknown"));
+        let mut lines = vec![format!("\"{}\" ({})", name, handle)];
+
+        // Type line as printed, e.g. "Generic Action - Attack"
+        let mut types = world
+            .get::<CardClass>(card)
+            .map(|v| v.classes().iter().map(|v| format!("{:?}", v)).collect::<Vec<String>>())
+            .unwrap_or_default();
+        if world.get::<Hero>(card).is_some() {
+            types.push(String::from("Hero"));
+        }
+        if let Some(card_type) = world.get::<CardType>(card) {
+            types.push(String::from(card_type.name()));
+        }
+        let sub_types = world
+            .get::<CardSubTypes>(card)
+            .map(|v| v.0.iter().map(|v| format!("{:?}", v)).collect::<Vec<String>>())
+            .unwrap_or_default();
+        if !sub_types.is_empty() {
+            types.push(format!("- {}", sub_types.join(" ")));
+        }
+        if !types.is_empty() {
+            lines.push(types.join(" "));
+        }
+
+        let mut stats = Vec::new();
+        if let Some(cost) = world.get::<Cost>(card) {
+            stats.push(format!("cost {}", cost.0));
+        }
+        if let Some(life) = world.get::<LifeCost>(card) {
+            stats.push(format!("costs {} life", life.0));
+        }
+        if let Some(color) = world.get::<Color>(card) {
+            stats.push(format!("pitch {} ({:?})", color.pitch(), color));
+        }
+        if let Some(attack) = world.get::<Attack>(card) {
+            stats.push(format!("attack {}", attack.0));
+        }
+        if let Some(defense) = world.get::<Defense>(card) {
+            stats.push(format!("defense {}", defense.0));
+        }
+        if let Some(health) = world.get::<Health>(card) {
+            stats.push(format!("life {}", health.0));
+        }
+        if let Some(intellect) = world.get::<Intellect>(card) {
+            stats.push(format!("intellect {}", intellect.0));
+        }
+        if !stats.is_empty() {
+            lines.push(stats.join(", "));
+        }
+
+        if world.get::<GoAgain>(card).is_some() {
+            lines.push(String::from("Go again"));
+        }
+        if let Some(counters) = world.get::<Counters>(card) {
+            let mut counters = counters.0
+                .iter()
+                .map(|(kind, amount)| format!("{} {:?} counter(s)", amount, kind))
+                .collect::<Vec<String>>();
+            counters.sort();
+            if !counters.is_empty() {
+                lines.push(counters.join(", "));
+            }
+        }
+        if world.get::<Exhausted>(card).is_some() {
+            lines.push(String::from("Exhausted"));
+        }
+        if world.get::<InChain>(card).is_some() {
+            lines.push(String::from("On the combat chain"));
+        } else if world.get::<InGraveyard>(card).is_some() {
+            lines.push(String::from("In the graveyard"));
+        }
+        if let Some(text) = world.get::<CardText>(card) {
+            lines.push(text.0.clone());
+        }
+        lines.join("\n")
+    }
+
+    // Game state as the viewer is allowed to see it
+    // Hands are only shown to their hero, apart from revealed cards, and deck order to no one
+    pub fn snapshot(world: &mut World, viewer: Viewer) -> Value {
+        let heroes_in_game = world
+            .query_filtered::<Entity, With<Hero>>()
+            .iter(world)
+            .collect::<Vec<Entity>>();
+        let revealed = heroes_in_game
+            .into_iter()
+            .map(|hero| (hero, visible_hand(world, viewer, hero)))
+            .collect::<HashMap<Entity, Vec<Entity>>>();
+
+        // Allies in play are listed under the hero controlling them
+        let mut allies: HashMap<Entity, Vec<Value>> = HashMap::new();
+        let mut ally_query = world.query_filtered::<(Entity, &CardName, &Health, &Controller), With<Ally>>();
+        for (ally, name, health, controller) in ally_query.iter(world) {
+            allies.entry(controller.0).or_default().push(json!({
+                "id": ally.index(),
+                "name": name.0,
+                "health": health.0,
+            }));
+        }
+
+        // So are items, with their counters and whether they are exhausted
+        let mut items: HashMap<Entity, Vec<Value>> = HashMap::new();
+        let mut item_query = world.query_filtered::<
+            (Entity, &CardName, &Controller, Option<&Counters>, Has<Exhausted>),
+            With<InPlay>
+        >();
+        for (item, name, controller, counters, exhausted) in item_query.iter(world) {
+            let counters: std::collections::BTreeMap<String, u32> = counters
+                .map(|v| v.0.iter().map(|(kind, amount)| (format!("{:?}", kind), *amount)).collect())
+                .unwrap_or_default();
+            items.entry(controller.0).or_default().push(json!({
+                "id": item.index(),
+                "name": name.0,
+                "counters": counters,
+                "exhausted": exhausted,
+            }));
+        }
+
+        let searching = world
+            .resource::<PendingSearch>()
+            .0
+            .as_ref()
+            .map(|v| (v.hero, v.matches.iter().map(|v| v.index()).collect::<Vec<u32>>()));
+        let arranging = world
+            .resource::<PendingArrangement>()
+            .0
+            .as_ref()
+            .map(|v| (v.hero, v.cards.iter().map(|v| v.index()).collect::<Vec<u32>>()));
+        let mut heroes = Map::new();
+        let mut hero_query = world.query_filtered::<(
+            Entity,
+            &PlayerName,
+            &Health,
+            &Resources,
+            &ActionPoints,
+            &HandZone,
+            &PitchZone,
+            &DeckZone,
+            Option<&Team>
+        ), With<Hero>>();
+        for (entity, name, health, resources, action_points, hand, pitch, deck, team) in hero_query.iter(world) {
+            let mut hero = json!({
+                "name": name.0,
+                "health": health.0,
+                "resources": resources.0,
+                "action_points": action_points.0,
+                "hand_size": hand.0.len(),
+                "deck_size": deck.0.len(),
+                "pitch": pitch.0.iter().map(|v| v.index()).collect::<Vec<u32>>(),
+                "allies": allies.remove(&entity).unwrap_or_default(),
+                "items": items.remove(&entity).unwrap_or_default(),
+            });
+            if let Some(team) = team {
+                hero["team"] = json!(team.0 + 1);
+            }
+            if viewer.can_see_hand(entity) {
+                hero["hand"] = json!(hand.0.iter().map(|v| v.index()).collect::<Vec<u32>>());
+                // Only the hero choosing sees the cards from their deck
+                if let Some((_, matches)) = searching.as_ref().filter(|(hero, _)| *hero == entity) {
+                    hero["search"] = json!(matches);
+                }
+                if let Some((_, cards)) = arranging.as_ref().filter(|(hero, _)| *hero == entity) {
+                    hero["looking_at"] = json!(cards);
+                }
+            } else if let Some(cards) = revealed.get(&entity).filter(|v| !v.is_empty()) {
+                hero["revealed"] = json!(cards.iter().map(|v| v.index()).collect::<Vec<u32>>());
+            }
+            heroes.insert(entity.index().to_string(), hero);
+        }
+
+        let priority = world.resource::<Priority>();
+        let chain = world.resource::<Chain>();
+        json!({
+            "phase": world.resource::<GameState>().0,
+            "combat_step": world.resource::<CombatState>().0,
+            "priority": priority.priority_hero().map(|v| v.index()),
+            "chain_links": chain.links.len(),
+            "stack": world.resource::<Stack>().0.len(),
+            "heroes": heroes,
+        })
+    }
+
+    // Last state sent to each viewer, so only changes need sending
+    pub struct Views(HashMap<Viewer, Value>);
+
+    impl Views {
+        pub fn new(viewers: Vec<Viewer>) -> Self {
+            Views(viewers.into_iter().map(|v| (v, Value::Null)).collect())
+        }
+
+        // Changes since the last update for each viewer that has any
+        // The first update is the full snapshot
+        pub fn update(&mut self, world: &mut World) -> Vec<(Viewer, Value)> {
+            let mut updates = Vec::new();
+            for (viewer, state) in self.0.iter_mut() {
+                let new_state = snapshot(world, *viewer);
+                let changes = diff(state, &new_state);
+                if changes.as_object().map(|v| !v.is_empty()).unwrap_or(true) {
+                    updates.push((*viewer, changes));
+                }
+                *state = new_state;
+            }
+            updates
+        }
+    }
+
+    // Fields of `new` that differ from `old`, recursing into objects
+    pub fn diff(old: &Value, new: &Value) -> Value {
+        match (old, new) {
+            (Value::Object(old), Value::Object(new)) => {
+                let mut changes = Map::new();
+                for (key, value) in new {
+                    match old.get(key) {
+                        Some(previous) if previous == value => {},
+                        Some(previous) => {
+                            changes.insert(key.clone(), diff(previous, value));
+                        },
+                        None => {
+                            changes.insert(key.clone(), value.clone());
+                        }
+                    }
+                }
+                Value::Object(changes)
+            },
+            _ => new.clone()
+        }
+    }
+}
+
+// Every card the engine can spawn, looked up by card id
+pub mod card_registry {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+    pub enum Rarity {
+        Common,
+        Rare,
+        SuperRare,
+        Majestic,
+        Legendary
+    }
+
+    pub struct CardEntry {
+        pub id: &'static str,
+        pub rarity: Rarity,
+        // Rules text the engine does not check, shown after the card's printed text
+        // Empty when the printed text says it all
+        pub text: &'static str,
+        spawn: fn(&mut World) -> Entity
+    }
+
+    impl CardEntry {
+        pub fn card_id(&self) -> CardId {
+            CardId(self.id.to_string())
+        }
+
+        // Spawns a new copy of the card, tagged with its id and text
+        pub fn spawn(&self, world: &mut World) -> Entity {
+            let card = (self.spawn)(world);
+            world.entity_mut(card).insert(Id(self.card_id()));
+            let text = world
+                .get::<PrintedText>(card)
+                .map(|v| v.0.as_str())
+                .into_iter()
+                .chain(Some(self.text))
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<&str>>()
+                .join("\n");
+            if !text.is_empty() {
+                world.entity_mut(card).insert(CardText(text));
+            }
+            card
+        }
+    }
+
+    fn basic_attack(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Basic Attack")),
+            Cost(1),
+            Attack(3),
+            Defense(2),
+            Color::Yellow,
+            CardType::Action,
+            CardSubTypes(vec![SubType::Attack]),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn basic_resource(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Basic Resource")),
+            Color::Yellow,
+            CardType::Resource,
+            ZoneUsage::RESOURCE,
+            CardClass::SingleClass(CardClassTypes::Generic),
+            CardSubTypes::default(),
+        )).id()
+    }
+
+    fn basic_arcane(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Basic Arcane")),
+            Cost(1),
+            ArcaneDamage(2),
+            PrintedText(String::from("Deal 2 arcane damage to target hero.")),
+            Color::Red,
+            CardType::Action,
+            CardSubTypes::default(),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn basic_attack_reaction(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Basic Attack Reaction")),
+            Cost(0),
+            StatBonus { attack: 2, defense: 0 },
+            Color::Red,
+            CardType::AttackReaction,
+            CardSubTypes::default(),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn basic_defense_reaction(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Basic Defense Reaction")),
+            Cost(0),
+            Defense(3),
+            Color::Red,
+            CardType::DefenseReaction,
+            CardSubTypes::default(),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn basic_instant(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Basic Instant")),
+            Cost(0),
+            OnResolve(vec![Effect::DealDamage(1)]),
+            PrintedText(String::from("Deal 1 damage to target hero.")),
+            Color::Blue,
+            CardType::Instant,
+            CardSubTypes::default(),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn basic_ally(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Basic Ally")),
+            Cost(2),
+            Defense(2),
+            Ally { life: 4 },
+            Color::Blue,
+            CardType::Action,
+            CardSubTypes::default(),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn reckless_swing(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Reckless Swing")),
+            Cost(1),
+            Attack(3),
+            Defense(2),
+            AdditionalCost { payment: CostPayment::Discard, limit: 1, filter: CardFilter::Attack, attack: 2 },
+            Color::Red,
+            CardType::Action,
+            CardSubTypes(vec![SubType::Attack]),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn steam_engine(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Steam Engine")),
+            Cost(1),
+            Item { counters: Some((CounterKind::Steam, 3)) },
+            ItemAbility {
+                cost: 0,
+                action: false,
+                exhaust: true,
+                counters: Some((CounterKind::Steam, 1)),
+                effects: vec![Effect::GainResources(1)]
+            },
+            Color::Blue,
+            CardType::Item,
+            CardSubTypes::default(),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn sigil_of_protection(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Sigil of Protection")),
+            Cost(1),
+            OnResolve(vec![Effect::PreventDamage(3)]),
+            PrintedText(String::from("Prevent the next 3 damage that would be dealt to you this turn.")),
+            Color::Yellow,
+            CardType::Instant,
+            CardSubTypes::default(),
+            CardClass::SingleClass(CardClassTypes::Generic)
+        )).id()
+    }
+
+    fn wound(world: &mut World) -> Entity {
+        world.spawn((
+            CardName(String::from("Wound")),
+            CardType::Resource,
+            ZoneUsage::WOUND,
+            CardClass::SingleClass(CardClassTypes::Generic),
+            CardSubTypes::default(),
+        )).id()
+    }
+
+    fn toxicity_red(world: &mut World) -> Entity {
+        world.spawn(<card_systems::ToxicityRed as Card>::card()).id()
+    }
+
+    pub const REGISTRY: &[CardEntry] = &[
+        CardEntry { id: "BASIC001", rarity: Rarity::Common, text: "", spawn: basic_attack },
+        CardEntry { id: "BASIC002", rarity: Rarity::Common, text: "", spawn: basic_resource },
+        CardEntry { id: "BASIC003", rarity: Rarity::Rare, text: "", spawn: basic_arcane },
+        CardEntry {
+            id: "BASIC004",
+            rarity: Rarity::Common,
+            text: "The attack on the current chain link gets +2 attack until the combat chain closes.",
+            spawn: basic_attack_reaction
+        },
+        CardEntry { id: "BASIC005", rarity: Rarity::Common, text: "", spawn: basic_defense_reaction },
+        CardEntry { id: "BASIC006", rarity: Rarity::Common, text: "", spawn: basic_instant },
+        CardEntry {
+            id: "BASIC007",
+            rarity: Rarity::Rare,
+            text: "Ally - enters play with 4 life. It can be attacked, and can block attacks on its controller.",
+            spawn: basic_ally
+        },
+        CardEntry {
+            id: "BASIC008",
+            rarity: Rarity::Common,
+            text: "As an additional cost to play Reckless Swing, you may discard a card. \
+                If an attack is discarded this way, Reckless Swing gets +2 attack.",
+            spawn: reckless_swing
+        },
+        CardEntry {
+            id: "BASIC009",
+            rarity: Rarity::Rare,
+            text: "Steam Engine enters play with 3 steam counters. \
+                Instant - Exhaust Steam Engine, remove a steam counter from it: Gain 1 resource.",
+            spawn: steam_engine
+        },
+        CardEntry {
+            id: "BASIC010",
+            rarity: Rarity::Common,
+            text: "Wound cannot be played, pitched or used to block.",
+            spawn: wound
+        },
+        CardEntry { id: "BASIC011", rarity: Rarity::Common, text: "", spawn: sigil_of_protection },
+        CardEntry {
+            id: "OUT165",
+            rarity: Rarity::Common,
+            text: "The next Assassin or Ranger attack you play this turn gains \"When this hits a hero, they lose 3 life.\"",
+            spawn: toxicity_red
+        },
+    ];
+
+    pub fn find(card_id: &CardId) -> Option<&'static CardEntry> {
+        REGISTRY.iter().find(|v| v.id == card_id.0)
+    }
+
+    // Rules text for any card id, heroes included
+    // Cards are spawned on their own to read the text they would get in a game
+    pub fn text(card_id: &str) -> Option<String> {
+        let hero = [
+            (<hero_systems::SilvanWayfarer as HeroCard>::card_id(), hero_systems::SilvanWayfarer::TEXT),
+            (<hero_systems::VexNightstalker as HeroCard>::card_id(), hero_systems::VexNightstalker::TEXT),
+        ];
+        let card = REGISTRY.iter().find(|v| v.id == card_id).and_then(|entry| {
+            let mut world = World::new();
+            let card = entry.spawn(&mut world);
+            world.get::<CardText>(card).map(|v| v.0.clone())
+        });
+        card
+            .or_else(|| hero.into_iter().find(|v| v.0 .0 == card_id).map(|v| v.1.to_string()))
+            .filter(|v| !v.is_empty())
+    }
+
+    // Spawns a card that is known to be registered
+    pub fn spawn(world: &mut World, id: &str) -> Entity {
+        find(&CardId(id.to_string()))
+            .unwrap_or_else(|| panic!("Card \"{}\" is not registered", id))
+            .spawn(world)
+    }
+}
+
+// Builds a cube from the registry and splits it into sealed pools or draft packs
+pub mod cube {
+    use super::*;
+    use card_registry::{CardEntry, Rarity, REGISTRY};
+
+    // How many cards of a kind the cube holds
+    // Unset filters match every card
+    #[derive(Deserialize)]
+    pub struct Quota {
+        color: Option<Color>,
+        card_type: Option<CardType>,
+        max_cost: Option<u16>,
+        count: usize
+    }
+
+    // What quotas are checked against, read from a spawned copy of the card
+    struct CardStats {
+        entry: &'static CardEntry,
+        color: Option<Color>,
+        card_type: Option<CardType>,
+        cost: u16
+    }
+
+    impl Quota {
+        fn matches(&self, stats: &CardStats) -> bool {
+            self.color.is_none_or(|v| stats.color == Some(v))
+                && self.card_type.is_none_or(|v| stats.card_type == Some(v))
+                && self.max_cost.is_none_or(|v| stats.cost <= v)
+        }
+    }
+
+    fn stats() -> Vec<CardStats> {
+        let mut world = World::new();
+        REGISTRY
+            .iter()
+            .map(|entry| {
+                let card = entry.spawn(&mut world);
+                CardStats {
+                    entry,
+                    color: world.get::<Color>(card).copied(),
+                    card_type: world.get::<CardType>(card).copied(),
+                    cost: world.get::<Cost>(card).map(|v| v.0).unwrap_or(0)
+                }
+            })
+            .collect()
+    }
+
+    // Commoner cards make up more of the cube
+    fn weight(rarity: Rarity) -> usize {
+        match rarity {
+            Rarity::Common => 3,
+            Rarity::Rare => 2,
+            Rarity::SuperRare | Rarity::Majestic | Rarity::Legendary => 1
+        }
+    }
+
+    fn shuffle<T>(items: &mut [T], dice: &mut Dice) {
+        for i in (1..items.len()).rev() {
+            let j = dice.roll(i as u32 + 1) as usize - 1;
+            items.swap(i, j);
+        }
+    }
+
+    // Fills each quota by cycling through its matching cards, so copies stay even
+    pub fn generate(quotas: &[Quota], dice: &mut Dice) -> Result<Vec<CardId>, String> {
+        let stats = stats();
+        let mut cube = Vec::new();
+        for (i, quota) in quotas.iter().enumerate() {
+            let mut candidates = stats
+                .iter()
+                .filter(|v| quota.matches(v))
+                .flat_map(|v| std::iter::repeat_n(v.entry, weight(v.entry.rarity)))
+                .collect::<Vec<&CardEntry>>();
+            if candidates.is_empty() {
+                return Err(format!("No registered card fits quota {}", i + 1));
+            }
+            shuffle(&mut candidates, dice);
+            cube.extend(candidates.iter().cycle().take(quota.count).map(|v| v.card_id()));
+        }
+        Ok(cube)
+    }
+
+    fn check_size(cube: &[CardId], needed: usize) -> Result<(), String> {
+        if cube.len() < needed {
+            return Err(format!("Cube has {} cards but {} are needed", cube.len(), needed));
+        }
+        Ok(())
+    }
+
+    pub fn sealed_pools(
+        mut cube: Vec<CardId>,
+        players: usize,
+        pool_size: usize,
+        dice: &mut Dice
+    ) -> Result<Vec<Vec<CardId>>, String> {
+        check_size(&cube, players * pool_size)?;
+        shuffle(&mut cube, dice);
+        Ok(cube.chunks(pool_size).take(players).map(|v| v.to_vec()).collect())
+    }
+
+    // Every pack leads with a rare or better while any are left
+    pub fn draft_packs(
+        mut cube: Vec<CardId>,
+        players: usize,
+        packs: usize,
+        pack_size: usize,
+        dice: &mut Dice
+    ) -> Result<Vec<Vec<Vec<CardId>>>, String> {
+        check_size(&cube, players * packs * pack_size)?;
+        shuffle(&mut cube, dice);
+        let (mut rares, mut commons): (Vec<CardId>, Vec<CardId>) = cube
+            .into_iter()
+            .partition(|v| card_registry::find(v).is_some_and(|v| v.rarity >= Rarity::Rare));
+
+        let mut pack = || {
+            let mut pack = Vec::new();
+            if let Some(rare) = rares.pop() {
+                pack.push(rare);
+            }
+            while pack.len() < pack_size {
+                match commons.pop().or_else(|| rares.pop()) {
+                    Some(card) => pack.push(card),
+                    None => break
+                }
+            }
+            pack
+        };
+        Ok((0..players).map(|_| (0..packs).map(|_| pack()).collect()).collect())
+    }
+
+    // cube <quotas.json> sealed <players> <pool size>
+    // cube <quotas.json> draft <players> <packs> <pack size>
+    pub fn run(args: &[String], dice: &mut Dice) -> Result<String, String> {
+        let number = |i: usize, name: &str| -> Result<usize, String> {
+            args.get(i)
+                .ok_or(format!("Missing {}", name))?
+                .parse::<usize>()
+                .map_err(|_| format!("{} must be a number", name))
+        };
+
+        let path = args.first().ok_or("Quota file not specified")?;
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read \"{}\": {}", path, err))?;
+        let quotas: Vec<Quota> = serde_json::from_str(&contents)
+            .map_err(|err| format!("Invalid quotas: {}", err))?;
+        let cube = generate(&quotas, dice)?;
+
+        let output = match args.get(1).map(|v| v.as_str()) {
+            Some("sealed") => serde_json::to_value(
+                sealed_pools(cube, number(2, "players")?, number(3, "pool size")?, dice)?
+            ),
+            Some("draft") => serde_json::to_value(
+                draft_packs(cube, number(2, "players")?, number(3, "packs")?, number(4, "pack size")?, dice)?
+            ),
+            None => serde_json::to_value(cube),
+            Some(other) => return Err(format!("Unknown format \"{}\", expected sealed or draft", other))
+        };
+        output
+            .and_then(|v| serde_json::to_string_pretty(&v))
+            .map_err(|err| format!("Failed to write cube: {}", err))
+    }
+}
+
+// Deck lists read from files and checked against deck-building rules
+pub mod deck_list {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Line {
+        id: String,
+        quantity: u32
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct DeckList(pub Vec<(CardId, u32)>);
+
+    pub struct DeckRules {
+        pub min_size: u32,
+        pub max_size: Option<u32>,
+        pub max_copies: u32,
+        pub commons_only: bool
+    }
+
+    impl DeckRules {
+        pub fn for_mode(mode: GameMode) -> Self {
+            match mode {
+                GameMode::Constructed => DeckRules { min_size: 60, max_size: None, max_copies: 3, commons_only: false },
+                // Young heroes play exactly 40 cards
+                GameMode::Blitz => DeckRules { min_size: 40, max_size: Some(40), max_copies: 2, commons_only: false },
+                // Any size, for trying out the handful of registered cards
+                GameMode::Casual => DeckRules { min_size: 0, max_size: None, max_copies: 3, commons_only: false },
+                GameMode::Commoner => DeckRules { min_size: 40, max_size: Some(40), max_copies: 2, commons_only: true },
+            }
+        }
+    }
+
+    impl DeckList {
+        // JSON is a list of {"id", "quantity"}
+        // Text is one "<quantity> <card id>" per line, with # starting a comment
+        pub fn parse(contents: &str) -> Result<Self, String> {
+            if contents.trim_start().starts_with('[') {
+                let lines: Vec<Line> = serde_json::from_str(contents)
+                    .map_err(|err| format!("Invalid deck list: {}", err))?;
+                return Ok(DeckList(lines.into_iter().map(|v| (CardId(v.id), v.quantity)).collect()));
+            }
+
+            let mut cards = Vec::new();
+            for (i, line) in contents.lines().enumerate() {
+                let line = line.split('#').next().unwrap_or_default().trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let (quantity, id) = line
+                    .split_once(char::is_whitespace)
+                    .ok_or(format!("Line {}: expected \"<quantity> <card id>\"", i + 1))?;
+                let quantity = quantity
+                    .parse::<u32>()
+                    .map_err(|_| format!("Line {}: quantity must be a number", i + 1))?;
+                cards.push((CardId(id.trim().to_string()), quantity));
+            }
+            Ok(DeckList(cards))
+        }
+
+        pub fn load(path: &str) -> Result<Self, String> {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|err| format!("Failed to read \"{}\": {}", path, err))?;
+            Self::parse(&contents)
+        }
+
+        pub fn size(&self) -> u32 {
+            self.0.iter().map(|v| v.1).sum()
+        }
+
+        // Every rule the deck breaks, for a hero of the given class
+        pub fn validate(&self, rules: &DeckRules, hero_class: &CardClass) -> Vec<String> {
+            let mut problems = Vec::new();
+            if self.size() < rules.min_size {
+                problems.push(format!("Deck has {} cards, at least {} are needed", self.size(), rules.min_size));
+            }
+            if let Some(max_size) = rules.max_size.filter(|v| self.size() > *v) {
+                problems.push(format!("Deck has {} cards, at most {} are allowed", self.size(), max_size));
+            }
+
+            // The same card may be listed on several lines
+            let mut copies: HashMap<&CardId, u32> = HashMap::new();
+            for (card_id, quantity) in &self.0 {
+                *copies.entry(card_id).or_default() += quantity;
+            }
+
+            let mut scratch = World::new();
+            for (card_id, quantity) in copies {
+                let Some(entry) = card_registry::find(card_id) else {
+                    problems.push(format!("Unknown card \"{}\"", card_id.0));
+                    continue;
+                };
+                if quantity > rules.max_copies {
+                    problems.push(format!(
+                        "{} copies of \"{}\", at most {} are allowed",
+                        quantity, card_id.0, rules.max_copies
+                    ));
+                }
+                if rules.commons_only && entry.rarity != card_registry::Rarity::Common {
+                    problems.push(format!("\"{}\" is not a common", card_id.0));
+                }
+                let card = entry.spawn(&mut scratch);
+                if validation_systems::class_allows(scratch.get::<CardClass>(card), hero_class).is_err() {
+                    problems.push(format!("\"{}\" cannot be played by this hero's class", card_id.0));
+                }
+            }
+            problems.sort();
+            problems
+        }
+
+        // Spawns each card into the hero's deck
+        pub fn spawn(&self, world: &mut World, hero: Entity) {
+            for (card_id, quantity) in &self.0 {
+                for _ in 0..*quantity {
+                    let card = card_registry::spawn(world, &card_id.0);
+                    world.entity_mut(card).insert((Owner(hero), Controller(hero)));
+                    world.get_mut::<DeckZone>(hero).unwrap().0.push_back(card);
+                }
+            }
+        }
+    }
+
+    // Validates against the game mode and spawns a deck for the hero
+    // Returns every rule the deck breaks
+    pub fn add(world: &mut World, hero: Entity, deck: &DeckList) -> Result<(), Vec<String>> {
+        let rules = DeckRules::for_mode(world.resource::<GameConfig>().format);
+        let hero_class = world
+            .get::<CardClass>(hero)
+            .ok_or(vec![format!("Hero {} has no class", hero.index())])?;
+        let problems = deck.validate(&rules, hero_class);
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        deck.spawn(world, hero);
+        game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Hero {} deck: {} cards", hero.index(), deck.size());
+        Ok(())
+    }
+
+    // Loads a deck list and adds it for the hero
+    pub fn build(world: &mut World, hero: Entity, path: &str) -> Result<(), String> {
+        let deck = DeckList::load(path)?;
+        add(world, hero, &deck)
+            .map_err(|problems| format!("\"{}\" is not a legal deck:\n  {}", path, problems.join("\n  ")))
+    }
+}
+
+// How the engine applies each rule, looked up in game with "rules <topic>"
+pub mod rules_lookup {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    pub struct Entry {
+        pub topic: &'static str,
+        pub aliases: &'static [&'static str],
+        pub text: &'static str
+    }
+
+    // The match makes every keyword the engine knows have an entry
+    fn keyword_entry(keyword: Keyword) -> Entry {
+        match keyword {
+            Keyword::GoAgain => Entry {
+                topic: "go again",
+                aliases: &["goagain"],
+                text: "When an attack with go again reaches the link step, its hero gains 1 action point. \
+                    Go again can be printed on the card or granted by an attachment while it stays attached. \
+                    Non-attack actions with go again do not gain an action point yet."
+            },
+        }
+    }
+
+    const TOPICS: &[Entry] = &[
+        Entry {
+            topic: "dominate",
+            aliases: &[],
+            text: "Not implemented yet. Attacks can currently be blocked by any number of cards."
+        },
+        Entry {
+            topic: "reaction windows",
+            aliases: &["reactions", "reaction", "attack reaction", "defense reaction", "reaction step"],
+            text: "After blocks are declared the combat chain moves to the reaction step and the attacker acts first. \
+                Only the attacker may play attack reactions and only the defender may play defense reactions. \
+                Whoever plays a card acts first again and every player must pass in turn before it resolves. \
+                Resolved attack reactions add their bonus to the attack until the chain closes. \
+                Resolved defense reactions add their defense to the blocks. \
+                Defense reactions cannot be declared as blocks."
+        },
+        Entry {
+            topic: "blocking",
+            aliases: &["block", "defend step", "blocks"],
+            text: "In the defend step the defending hero declares blocks once, using cards with defense from their hand \
+                or allies they control in play. \
+                A card can only block once per combat chain. \
+                An attack hits when its attack is at least the total defense of its blocks and defense reactions. \
+                It then deals the difference as damage. \
+                An attack with several targets hits each one separately, less the defense that target put up."
+        },
+        Entry {
+            topic: "targets",
+            aliases: &["target", "targeting"],
+            text: "Targets follow the card when playing it, e.g. \"3 play 6 2\". \
+                Attacks take exactly one hero or ally, and other targeted effects exactly one hero, unless the card says otherwise. \
+                Some cards take up to a number of permanents, anything other than a hero with health. \
+                Cards that hit all opposing heroes and permanents choose their targets when played."
+        },
+        Entry {
+            topic: "allies",
+            aliases: &["ally", "permanents"],
+            text: "An ally enters play when it resolves, with its own health. \
+                Allies can be attacked, defending themselves with their own defense, and cannot be blocked for. \
+                An ally in play can block attacks on its controller and stays in play afterwards. \
+                At zero health it leaves play for its owner's graveyard."
+        },
+        Entry {
+            topic: "priority",
+            aliases: &["pass"],
+            text: "Players act in turn order while they hold priority. \
+                Cards on the stack resolve once every player has passed in succession. \
+                The turn player gets priority again after each resolution."
+        },
+        Entry {
+            topic: "formats",
+            aliases: &["format", "blitz", "commoner", "young", "adult", "config"],
+            text: "Start a game with --format constructed, blitz, commoner or casual. \
+                Blitz heroes are young, with half the life of their adult selves, and play exactly 40 cards with at most 2 copies of each. \
+                Commoner is played like blitz, but with commons only. \
+                Constructed decks need at least 60 cards with at most 3 copies, casual decks can be any size. \
+                --life, --intellect and --action-points change the starting numbers, or --config reads them from a JSON file."
+        },
+        Entry {
+            topic: "classes",
+            aliases: &["class", "legality"],
+            text: "A hero can only play generic cards and cards of their own class. \
+                A dual class card needs the hero to share at least one of its classes. \
+                Deck lists are checked the same way when they are loaded. \
+                Cards of another class can still be pitched or used to block."
+        },
+        Entry {
+            topic: "action points",
+            aliases: &["actions", "action point"],
+            text: "Playing an action, or activating an action-speed hero ability, costs 1 action point. \
+                Actions can only be played by the turn player in their action phase while the stack is empty and no attack is in progress."
+        },
+        Entry {
+            topic: "pitch",
+            aliases: &["pitching", "resources"],
+            text: "A card in hand can be pitched for resources while a cost is being paid, or to pay arcane barrier. \
+                Red pitches for 1, yellow for 2 and blue for 3, before pitch modifiers. \
+                Pitched cards go to the bottom of their owner's deck at the end of the turn. \
+                A play can be cancelled until it is paid for, returning the cards pitched toward it. \
+                Floating resources are lost at the end of the turn, or whenever a chain link closes when started with --float link, \
+                unless a banked resource effect keeps them. \
+                Pitching more than a cost needs, and losing floating resources, are both noted in the log."
+        },
+        Entry {
+            topic: "scripts",
+            aliases: &["script", "on_hit", "on_play", "dsl"],
+            text: "Scripted cards list effects as \"<trigger>: <effect>\", run by the engine instead of card-specific code. \
+                Triggers are on_play, when the card resolves, and on_hit. \
+                Effects are draw N, gain N resources, gain N life, target hero loses N life, \
+                and next <class> [or <class>] attack gains <scripted effect>, which waits for the turn's next matching attack. \
+                Toxicity is written as \"on_play: next assassin or ranger attack gains on_hit: target hero loses 3 life\"."
+        },
+        Entry {
+            topic: "search",
+            aliases: &["tutor", "deck search", "searching"],
+            text: "A card that searches your deck lists the matching cards, and nothing else in the deck is shown. \
+                Choose one with \"<hero> search <card>\", or take nothing with \"<hero> search\". \
+                The card taken is revealed to every player before it goes to your hand or back on top, and the deck is always shuffled. \
+                Nobody can act until the search is finished."
+        },
+        Entry {
+            topic: "opt",
+            aliases: &["scry", "look at top", "deck order"],
+            text: "Opt N shows you the top N cards of your deck. \
+                Put them back with \"<hero> opt <top cards> / <bottom cards>\", listing each card once; the first card listed goes on top, \
+                and the last card after the slash ends up at the very bottom. \
+                Nobody can act until every card is put back. A frontend may arrange the cards itself for computer players."
+        },
+        Entry {
+            topic: "limits",
+            aliases: &["once per turn", "once per game", "charges", "usage"],
+            text: "Some cards and hero abilities can only be used once per turn, once per game, or a set number of times. \
+                A use is spent when the play is paid for, so a cancelled play costs nothing. \
+                Once per turn uses come back when the next turn starts; once per game uses and charges never do. \
+                Hero abilities are once per turn."
+        },
+        Entry {
+            topic: "stops",
+            aliases: &["auto pass", "autopass", "auto-pass"],
+            text: "Stops decide when you are asked to act; the game passes priority for you everywhere else. \
+                \"<hero> stops playable\" passes whenever nothing in your hand, nor your hero's ability, could be played at that moment. \
+                Naming combat steps, e.g. \"<hero> stops defend reaction\", passes during every other combat step, \
+                except when you could attack again. Both can be combined, and \"<hero> stops all\" asks you everywhere again. \
+                Blocking, discarding and other choices always wait for you."
+        },
+        Entry {
+            topic: "teams",
+            aliases: &["2v2", "two vs two", "teammates", "shared life"],
+            text: "With --teams 2v2 four heroes play in two teams, Player 1 and Player 3 against Player 2 and Player 4, \
+                and the teams take turns in alternation. Attacks can only target opposing heroes and their permanents. \
+                When a hero is attacked, they and then their teammate may each declare blocks, which all count against the attack. \
+                With --teams 2v2-shared each team starts with its heroes' life added together and gains and loses it as one."
+        },
+        Entry {
+            topic: "additional costs",
+            aliases: &["additional cost", "discard cost"],
+            text: "Some cards let you give up cards from hand when you play them, named after a \"/\", \
+                e.g. \"<hero> play <card> <target> / <card to give up>\". \
+                Discarded cards go to the graveyard once the card is paid for. \
+                Cards given up by pitching are pitched right away and pay toward the cost. \
+                Each one that matches what the card asks for powers up the attack until the combat chain closes."
+        },
+        Entry {
+            topic: "damage prevention",
+            aliases: &["prevention", "prevent", "shield", "shields", "prevention step"],
+            text: "After the reaction step the combat chain moves to the prevention step. \
+                Each hero the attack hits is told how much damage is coming and how much their shields cover. \
+                Players can still play instants, e.g. to shield themselves, and the step ends once everyone passes. \
+                In the damage step shields absorb damage in the order they were registered. \
+                A shield only partly used keeps the rest for later damage this turn. \
+                An attack that hits still counts as a hit when all of its damage is prevented."
+        },
+        Entry {
+            topic: "card usage",
+            aliases: &["resource cards", "wound", "wounds", "unplayable"],
+            text: "Some cards can only be used in some ways from hand. \
+                Resource cards can be pitched but not played or used to block. \
+                Wounds cannot be played, pitched or used to block."
+        },
+        Entry {
+            topic: "zones",
+            aliases: &["zone", "graveyard", "combat chain", "chain"],
+            text: "Cards are only played, pitched or blocked with from hand, and allies only block from play. \
+                Attacks, blocks and reactions go on the combat chain and stay there until it closes. \
+                Then they go to their owner's graveyard, apart from allies, which stay in play. \
+                Actions and instants go to the graveyard when they resolve, as do discarded cards and attacks with no legal target. \
+                Nothing is used again from the chain or the graveyard."
+        },
+        Entry {
+            topic: "items",
+            aliases: &["item", "activate", "exhaust", "exhausted"],
+            text: "Items are played like actions and stay in play when they resolve, some entering with counters. \
+                Activate one you control with \"<hero> activate <item>\", or \"<hero> activate\" for your hero's ability. \
+                The ability goes on the stack once its cost is paid. \
+                Exhausting the item or removing its counters is part of that cost. \
+                Exhausted items ready at the start of their controller's next turn."
+        },
+        Entry {
+            topic: "hands",
+            aliases: &["hand", "reveal", "hand size"],
+            text: "Every player knows how many cards each hand holds, but only its hero sees what they are. \
+                A revealed card can be seen by the players it was revealed to until the effect expires or the card leaves the hand. \
+                Use \"<hero> hands\" to list hand sizes and every card you can see."
+        },
+        Entry {
+            topic: "life",
+            aliases: &["life gain", "pay life", "life cost"],
+            text: "Some cards cost life on top of resources. Life is paid when the resources are, \
+                and a hero without enough life cannot play the card at all. \
+                Life gained or paid is noted in the log, and either can trigger other effects. Damage is not a life payment."
+        },
+        Entry {
+            topic: "costs",
+            aliases: &["cost", "reduction"],
+            text: "A card's cost is modified before it is paid, and never drops below zero. \
+                Static modifiers from a permanent apply to every card its controller plays while it is in play. \
+                A next attack modifier is used up by the first attack paid for, and ends with the turn otherwise. \
+                Hero abilities always cost what they say. The log shows any card paid for at a modified cost."
+        },
+        Entry {
+            topic: "arcane barrier",
+            aliases: &["barrier", "arcane"],
+            text: "Arcane damage ignores blocks. \
+                A hero with arcane barrier may pay resources up to their barrier to prevent that much arcane damage. \
+                Damage prevention and redirection still apply afterwards."
+        },
+        Entry {
+            topic: "counters",
+            aliases: &["counter", "+1 attack counters"],
+            text: "Counters stay on a card until removed. Each +1 attack counter on an attack adds 1 to its attack in the damage step."
+        },
+    ];
+
+    fn entries() -> Vec<Entry> {
+        Keyword::ALL
+            .iter()
+            .map(|v| keyword_entry(*v))
+            .chain(TOPICS.iter().copied())
+            .collect()
+    }
+
+    // Matches a topic or alias, or failing that the start of one
+    pub fn lookup(query: &str) -> Result<String, String> {
+        let query = query.trim().to_lowercase();
+        let entries = entries();
+        if query.is_empty() {
+            let topics = entries.iter().map(|v| v.topic).collect::<Vec<&str>>();
+            return Ok(format!("Rules topics: {}", topics.join(", ")));
+        }
+
+        let names = |entry: &Entry| std::iter::once(entry.topic).chain(entry.aliases.iter().copied()).collect::<Vec<&str>>();
+        let entry = entries
+            .iter()
+            .find(|v| names(v).contains(&query.as_str()))
+            .or_else(|| entries.iter().find(|v| names(v).iter().any(|name| name.starts_with(&query))))
+            .ok_or(format!("No rules for \"{}\", try \"rules\" for a list of topics", query))?;
+        Ok(format!("{}: {}", entry.topic, entry.text))
+    }
+}
+
+// Cards a player owns, kept per profile
+pub mod collection {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    // Directory holding one collection file per profile
+    const PROFILE_DIR: &str = "profiles";
+
+    #[derive(Default, Serialize, Deserialize)]
+    pub struct Collection {
+        profile: String,
+        // Quantity owned of each printing, keyed by card id
+        owned: BTreeMap<String, u32>
+    }
+
+    impl Collection {
+        pub fn new(profile: &str) -> Self {
+            Collection { profile: String::from(profile), ..Default::default() }
+        }
+
+        fn path(profile: &str) -> String {
+            format!("{}/{}.json", PROFILE_DIR, profile)
+        }
+
+        // Loads the profile's collection, starting empty if it has none yet
+        pub fn load(profile: &str) -> Result<Self, String> {
+            match std::fs::read_to_string(Self::path(profile)) {
+                Ok(contents) => serde_json::from_str(&contents)
+                    .map_err(|err| format!("Invalid collection for \"{}\": {}", profile, err)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::new(profile)),
+                Err(err) => Err(format!("Failed to read collection for \"{}\": {}", profile, err))
+            }
+        }
+
+        pub fn save(&self) -> Result<(), String> {
+            std::fs::create_dir_all(PROFILE_DIR)
+                .map_err(|err| format!("Failed to create \"{}\": {}", PROFILE_DIR, err))?;
+            let contents = serde_json::to_string_pretty(&self)
+                .map_err(|err| format!("Failed to save collection: {}", err))?;
+            std::fs::write(Self::path(&self.profile), contents)
+                .map_err(|err| format!("Failed to save collection: {}", err))
+        }
+
+        pub fn quantity(&self, card_id: &CardId) -> u32 {
+            self.owned.get(&card_id.0).copied().unwrap_or(0)
+        }
+
+        // Adds cards, e.g. from rewards or drafting
+        pub fn add(&mut self, card_id: &CardId, quantity: u32) {
+            *self.owned.entry(card_id.0.clone()).or_default() += quantity;
+        }
+
+        // Removes cards, failing if not enough copies are owned
+        pub fn remove(&mut self, card_id: &CardId, quantity: u32) -> Result<(), String> {
+            let owned = self.quantity(card_id);
+            if owned < quantity {
+                return Err(format!(
+                    "Only {} copies of \"{}\" owned, cannot remove {}",
+                    owned, card_id.0, quantity
+                ));
+            }
+            if owned == quantity {
+                self.owned.remove(&card_id.0);
+            } else {
+                self.owned.insert(card_id.0.clone(), owned - quantity);
+            }
+            Ok(())
+        }
+
+        // Cards in the list that are not sufficiently owned, with how many are missing
+        // Used to restrict decks to the player's collection
+        pub fn missing(&self, cards: &[(CardId, u32)]) -> Vec<(CardId, u32)> {
+            cards
+                .iter()
+                .filter(|(card_id, quantity)| self.quantity(card_id) < *quantity)
+                .map(|(card_id, quantity)| (card_id.clone(), quantity - self.quantity(card_id)))
+                .collect()
+        }
+    }
+}
+
+// Saving and restoring the full game state
+// Upgrades save files and replays written by older builds to the current format
+pub mod migration {
+    use serde_json::{Map, Value};
+
+    // Files written before versioning have no "version" field and count as version 0
+    pub const SAVE_VERSION: u32 = 14;
+    pub const REPLAY_VERSION: u32 = 3;
+
+    // Each converter upgrades a file from its index to the next version
+    type SaveConverter = fn(&mut Value) -> Result<(), String>;
+    type ReplayConverter = fn(&mut Value, &mut [Value]) -> Result<(), String>;
+
+    const SAVE_CONVERTERS: [SaveConverter; SAVE_VERSION as usize] = [save_v0_to_v1, save_v1_to_v2, save_v2_to_v3, save_v3_to_v4, save_v4_to_v5, save_v5_to_v6, save_v6_to_v7, save_v7_to_v8, save_v8_to_v9, save_v9_to_v10, save_v10_to_v11, save_v11_to_v12, save_v12_to_v13, save_v13_to_v14];
+    const REPLAY_CONVERTERS: [ReplayConverter; REPLAY_VERSION as usize] = [replay_v0_to_v1, replay_v1_to_v2, replay_v2_to_v3];
+
+    fn version(value: &Value, current: u32) -> Result<u32, String> {
+        let version = match value.get("version") {
+            None => 0,
+            Some(version) => version
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or(format!("Invalid version {}", version))?
+        };
+        if version > current {
+            return Err(format!("Written by a newer version (v{}, this build reads up to v{})", version, current));
+        }
+        Ok(version)
+    }
+
+    // Runs every converter between the save's version and the current one
+    // Returns the version the save was written in
+    pub fn upgrade_save(save: &mut Value) -> Result<u32, String> {
+        let from = version(save, SAVE_VERSION)?;
+        for converter in &SAVE_CONVERTERS[from as usize..] {
+            converter(save)?;
+        }
+        Ok(from)
+    }
+
+    // The header carries the version, events are upgraded alongside it
+    // Returns the version the replay was written in
+    pub fn upgrade_replay(header: &mut Value, events: &mut [Value]) -> Result<u32, String> {
+        let from = version(header, REPLAY_VERSION)?;
+        for converter in &REPLAY_CONVERTERS[from as usize..] {
+            converter(header, events)?;
+        }
+        Ok(from)
+    }
+
+    fn object<'a>(value: &'a mut Value, name: &str) -> Result<&'a mut Map<String, Value>, String> {
+        value
+            .as_object_mut()
+            .ok_or(format!("Expected {} to be an object", name))
+    }
+
+    // Runs the closure over each saved entity's id and components
+    fn for_each_entity(
+        save: &mut Value,
+        mut f: impl FnMut(&Value, &mut Map<String, Value>) -> Result<(), String>
+    ) -> Result<(), String> {
+        let entities = save
+            .get_mut("entities")
+            .and_then(|v| v.as_array_mut())
+            .ok_or("Save is missing entities")?;
+        for entity in entities {
+            let id = entity.get("entity").cloned().unwrap_or(Value::Null);
+            let components = object(
+                entity.get_mut("components").ok_or("Saved entity is missing components")?,
+                "components"
+            )?;
+            f(&id, components)?;
+        }
+        Ok(())
+    }
+
+    fn for_each_components(
+        save: &mut Value,
+        mut f: impl FnMut(&mut Map<String, Value>) -> Result<(), String>
+    ) -> Result<(), String> {
+        for_each_entity(save, |_, components| f(components))
+    }
+
+    // Saves from before arcane damage have no pending arcane hit,
+    // and heroes saved before decks were tracked have no deck or graveyard
+    fn save_v0_to_v1(save: &mut Value) -> Result<(), String> {
+        let resources = object(
+            save.get_mut("resources").ok_or("Save is missing resources")?,
+            "resources"
+        )?;
+        resources.entry("PendingArcane").or_insert(Value::Null);
+
+        for_each_components(save, |components| {
+            if components.contains_key("Hero") {
+                components.entry("DeckZone").or_insert(Value::Array(Vec::new()));
+                components.entry("Graveyard").or_insert(Value::Array(Vec::new()));
+            }
+            Ok(())
+        })?;
+
+        object(save, "save")?.insert(String::from("version"), Value::from(1));
+        Ok(())
+    }
+
+    // Saves from before plays could be cancelled have nothing pitched toward a pending play
+    fn save_v1_to_v2(save: &mut Value) -> Result<(), String> {
+        let resources = object(
+            save.get_mut("resources").ok_or("Save is missing resources")?,
+            "resources"
+        )?;
+        resources.entry("PendingPitch").or_insert(Value::Array(Vec::new()));
+
+        object(save, "save")?.insert(String::from("version"), Value::from(2));
+        Ok(())
+    }
+
+    // A single optional target becomes a list of targets
+    fn retarget(value: &mut Value) {
+        let Some(fields) = value.as_object_mut() else {
+            return;
+        };
+        if let Some(target) = fields.remove("target") {
+            let targets = match target {
+                Value::Null => Vec::new(),
+                target => vec![target]
+            };
+            fields.insert(String::from("targets"), Value::Array(targets));
+        }
+    }
+
+    // Saves from before multi-target attacks have one target per event and chain link
+    fn save_v2_to_v3(save: &mut Value) -> Result<(), String> {
+        let resources = object(
+            save.get_mut("resources").ok_or("Save is missing resources")?,
+            "resources"
+        )?;
+        for name in ["AttackLayer", "ProposedEvent"] {
+            if let Some(event) = resources.get_mut(name) {
+                retarget(event);
+            }
+        }
+        if let Some(events) = resources.get_mut("Stack").and_then(|v| v.as_array_mut()) {
+            events.iter_mut().for_each(retarget);
+        }
+        let links = resources
+            .get_mut("Chain")
+            .and_then(|v| v.get_mut("links"))
+            .and_then(|v| v.as_array_mut());
+        if let Some(links) = links {
+            links.iter_mut().for_each(retarget);
+        }
+
+        object(save, "save")?.insert(String::from("version"), Value::from(3));
+        Ok(())
+    }
+
+    // Toxicity was saved without its Assassin class, so any hero could play it
+    fn save_v3_to_v4(save: &mut Value) -> Result<(), String> {
+        for_each_components(save, |components| {
+            if components.get("Id").and_then(|v| v.as_str()) == Some("OUT165") {
+                components
+                    .entry("CardClass")
+                    .or_insert(serde_json::json!({ "SingleClass": "Assassin" }));
+            }
+            Ok(())
+        })?;
+
+        object(save, "save")?.insert(String::from("version"), Value::from(4));
+        Ok(())
+    }
+
+    // Toxicity became a scripted card, and its OnAttack and OnHit triggers became scripted effects
+    fn save_v4_to_v5(save: &mut Value) -> Result<(), String> {
+        use crate::effect_script::{Action, Script};
+        let script = Script::parse(&[crate::card_systems::ToxicityRed::SCRIPT])?;
+        let Some(Action::NextAttackGains { classes, effect }) = script.0.first().map(|v| &v.action) else {
+            return Err(String::from("Toxicity script should grant its next attack an effect"));
+        };
+        fn to_value<T: serde::Serialize>(value: &T) -> Result<Value, String> {
+            serde_json::to_value(value).map_err(|err| format!("Failed to upgrade save: {}", err))
+        }
+        let script_value = to_value(&script)?;
+        let pending = serde_json::json!({ "classes": to_value(classes)?, "effect": to_value(effect)? });
+        let granted = to_value(effect)?;
+
+        for_each_components(save, |components| {
+            if components.get("Id").and_then(|v| v.as_str()) == Some("OUT165") {
+                components.insert(String::from("Script"), script_value.clone());
+            }
+            if components.remove("OnAttack").is_some() {
+                components.insert(String::from("PendingGrant"), pending.clone());
+            }
+            if components.remove("OnHit").is_some() {
+                components.insert(String::from("GrantedScript"), granted.clone());
+            }
+            Ok(())
+        })?;
+
+        object(save, "save")?.insert(String::from("version"), Value::from(5));
+        Ok(())
+    }
+
+    // Saves from before deck searches have no pending search
+    fn save_v5_to_v6(save: &mut Value) -> Result<(), String> {
+        let resources = object(
+            save.get_mut("resources").ok_or("Save is missing resources")?,
+            "resources"
+        )?;
+        resources.entry("PendingSearch").or_insert(Value::Null);
+
+        object(save, "save")?.insert(String::from("version"), Value::from(6));
+        Ok(())
+    }
+
+    // Saves from before opt effects have no cards waiting to be put back
+    fn save_v6_to_v7(save: &mut Value) -> Result<(), String> {
+        let resources = object(
+            save.get_mut("resources").ok_or("Save is missing resources")?,
+            "resources"
+        )?;
+        resources.entry("PendingArrangement").or_insert(Value::Null);
+
+        object(save, "save")?.insert(String::from("version"), Value::from(7));
+        Ok(())
+    }
+
+    // Hero abilities tracked their own once per turn use, which is now a usage limit
+    fn save_v7_to_v8(save: &mut Value) -> Result<(), String> {
+        for_each_components(save, |components| {
+            let Some(ability) = components.get_mut("HeroAbility").and_then(|v| v.as_object_mut()) else {
+                return Ok(());
+            };
+            let used = ability.remove("used").unwrap_or(Value::Bool(false));
+            components.insert(String::from("UsageLimit"), serde_json::json!({ "OncePerTurn": { "used": used } }));
+            Ok(())
+        })?;
+
+        object(save, "save")?.insert(String::from("version"), Value::from(8));
+        Ok(())
+    }
+
+    // Games before teams had nobody sharing their life
+    fn save_v8_to_v9(save: &mut Value) -> Result<(), String> {
+        let resources = object(
+            save.get_mut("resources").ok_or("Save is missing resources")?,
+            "resources"
+        )?;
+        resources.entry("SharedLife").or_insert(Value::Object(Map::new()));
+
+        object(save, "save")?.insert(String::from("version"), Value::from(9));
+        Ok(())
+    }
+
+    // Cards saved before card text get it from the card database
+    fn save_v9_to_v10(save: &mut Value) -> Result<(), String> {
+        for_each_components(save, |components| {
+            let text = components
+                .get("Id")
+                .and_then(|v| v.as_str())
+                .and_then(crate::card_registry::text);
+            if let Some(text) = text {
+                components.insert(String::from("CardText"), Value::from(text));
+            }
+            Ok(())
+        })?;
+
+        object(save, "save")?.insert(String::from("version"), Value::from(10));
+        Ok(())
+    }
+
+    // Resource cards saved before zone usage could still be blocked with, they are now only pitched
+    fn save_v10_to_v11(save: &mut Value) -> Result<(), String> {
+        for_each_components(save, |components| {
+            if components.get("CardType").and_then(|v| v.as_str()) == Some("Resource") {
+                components.insert(
+                    String::from("ZoneUsage"),
+                    serde_json::json!({ "playable": false, "pitchable": true, "blockable": false })
+                );
+            }
+            Ok(())
+        })?;
+
+        object(save, "save")?.insert(String::from("version"), Value::from(11));
+        Ok(())
+    }
+
+    // Games before the game config were played with the default numbers
+    // Their format was only used to build the decks, so it is left at the default too
+    fn save_v11_to_v12(save: &mut Value) -> Result<(), String> {
+        let resources = object(
+            save.get_mut("resources").ok_or("Save is missing resources")?,
+            "resources"
+        )?;
+        resources.entry("GameConfig").or_insert(Value::Object(Map::new()));
+
+        object(save, "save")?.insert(String::from("version"), Value::from(12));
+        Ok(())
+    }
+
+    // Cards saved before zone markers get them from the graveyards and the open chain
+    // Blocks used to stay in hand until the chain closed, they now leave it when declared
+    fn save_v12_to_v13(save: &mut Value) -> Result<(), String> {
+        let links = save
+            .pointer("/resources/Chain/links")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let cards_of = |link: &Value, field: &str| link
+            .get(field)
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let mut in_chain = Vec::new();
+        let mut blocks = Vec::new();
+        for link in &links {
+            in_chain.extend(link.get("attack").cloned());
+            in_chain.extend(cards_of(link, "attack_reactions"));
+            in_chain.extend(cards_of(link, "defense_reactions"));
+            blocks.extend(cards_of(link, "blocks"));
+        }
+        in_chain.extend(blocks.iter().cloned());
+
+        let in_graveyard = save
+            .get("entities")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.pointer("/components/Graveyard").and_then(|v| v.as_array()))
+            .flatten()
+            .cloned()
+            .collect::<Vec<Value>>();
+        for_each_entity(save, |id, components| {
+            if in_chain.contains(id) {
+                components.insert(String::from("InChain"), Value::Null);
+            } else if in_graveyard.contains(id) {
+                components.insert(String::from("InGraveyard"), Value::Null);
+            }
+            if let Some(hand) = components.get_mut("HandZone").and_then(|v| v.as_array_mut()) {
+                hand.retain(|v| !blocks.contains(v));
+            }
+            Ok(())
+        })?;
+
+        object(save, "save")?.insert(String::from("version"), Value::from(13));
+        Ok(())
+    }
+
+    // Priority tracked each choice it was waiting on with its own flag, only one of which was ever set
+    fn save_v13_to_v14(save: &mut Value) -> Result<(), String> {
+        let priority = save.pointer_mut("/resources/Priority").ok_or("Save is missing priority")?;
+        let priority = object(priority, "priority")?;
+        let mut choice = Value::Null;
+        for (flag, name) in [
+            ("blocks", "Blocks"),
+            ("discarding", "Discard"),
+            ("paying_barrier", "ArcaneBarrier"),
+            ("searching", "Search"),
+            ("arranging", "Arrange")
+        ] {
+            if priority.remove(flag).and_then(|v| v.as_bool()) == Some(true) && choice.is_null() {
+                choice = Value::from(name);
+            }
+        }
+        priority.insert(String::from("choice"), choice);
+
+        object(save, "save")?.insert(String::from("version"), Value::from(14));
+        Ok(())
+    }
+
+    // Unversioned replays already use the current header and event layout
+    fn replay_v0_to_v1(header: &mut Value, _events: &mut [Value]) -> Result<(), String> {
+        object(header, "replay header")?.insert(String::from("version"), Value::from(1));
+        Ok(())
+    }
+
+    // Replays from before multi-target attacks play cards, and plan plays, with one target
+    fn replay_v1_to_v2(header: &mut Value, events: &mut [Value]) -> Result<(), String> {
+        for event in events.iter_mut() {
+            if let Some(play) = event.get_mut("PlayCard") {
+                retarget(play);
+            }
+            let steps = event
+                .get_mut("Plan")
+                .and_then(|v| v.get_mut("steps"))
+                .and_then(|v| v.as_array_mut())
+                .into_iter()
+                .flatten();
+            for step in steps {
+                if let Some(play) = step.get_mut("Play") {
+                    retarget(play);
+                }
+            }
+        }
+
+        object(header, "replay header")?.insert(String::from("version"), Value::from(2));
+        Ok(())
+    }
+
+    // Replays from before the table setup was recorded were one on one games with the default setup
+    fn replay_v2_to_v3(header: &mut Value, _events: &mut [Value]) -> Result<(), String> {
+        let header = object(header, "replay header")?;
+        header.entry("setup").or_insert(Value::Object(Map::new()));
+        header.insert(String::from("version"), Value::from(3));
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // Each fixture holds a file as it was before one converter ran, and as it should be after
+        fn fixture(contents: &str) -> (Value, Value) {
+            let mut fixture: Value = serde_json::from_str(contents).expect("Fixture should be valid JSON");
+            (fixture["before"].take(), fixture["after"].take())
+        }
+
+        fn check_save(converter: SaveConverter, contents: &str) {
+            let (mut save, after) = fixture(contents);
+            converter(&mut save).expect("Converter should accept the fixture");
+            assert_eq!(save, after);
+        }
+
+        fn check_replay(converter: ReplayConverter, contents: &str) {
+            let (mut replay, after) = fixture(contents);
+            let mut header = replay["header"].take();
+            let mut events = replay["events"].as_array().cloned().unwrap_or_default();
+            converter(&mut header, &mut events).expect("Converter should accept the fixture");
+            assert_eq!(serde_json::json!({ "header": header, "events": events }), after);
+        }
+
+        macro_rules! converter_tests {
+            ($check:ident: $($converter:ident),*) => {
+                $(
+                    #[test]
+                    fn $converter() {
+                        $check(
+                            super::$converter,
+                            include_str!(concat!("../tests/fixtures/migration/", stringify!($converter), ".json"))
+                        );
+                    }
+                )*
+            };
+        }
+
+        converter_tests!(check_save:
+            save_v0_to_v1, save_v1_to_v2, save_v2_to_v3, save_v3_to_v4, save_v4_to_v5, save_v5_to_v6, save_v6_to_v7,
+            save_v7_to_v8, save_v8_to_v9, save_v9_to_v10, save_v10_to_v11, save_v11_to_v12, save_v12_to_v13,
+            save_v13_to_v14
+        );
+        converter_tests!(check_replay: replay_v0_to_v1, replay_v1_to_v2, replay_v2_to_v3);
+
+        #[test]
+        fn every_version_has_a_converter() {
+            assert_eq!(SAVE_CONVERTERS.len(), SAVE_VERSION as usize);
+            assert_eq!(REPLAY_CONVERTERS.len(), REPLAY_VERSION as usize);
+        }
+
+        // A save from before versioning upgrades all the way, then loads into a new game
+        #[test]
+        fn upgrade_save_from_v0() {
+            let contents = include_str!("../tests/fixtures/migration/save_v0.json");
+            let mut save: Value = serde_json::from_str(contents).unwrap();
+            upgrade_save(&mut save).expect("v0 save should upgrade");
+            assert_eq!(save["version"], Value::from(SAVE_VERSION));
+            assert_eq!(save["resources"]["GameConfig"], serde_json::json!({}));
+
+            let mut world = bevy_ecs::world::World::new();
+            crate::setup_world(&mut world);
+            crate::save_state::from_json(&mut world, contents).expect("Upgraded save should load");
+            let mut heroes = world.query_filtered::<&crate::UsageLimit, bevy_ecs::query::With<crate::Hero>>();
+            assert_eq!(heroes.iter(&world).count(), 2);
+            let mut scripted = world.query::<(&crate::Id, &crate::effect_script::Script)>();
+            assert!(scripted.iter(&world).any(|(id, _)| id.0 .0 == "OUT165"));
+        }
+
+        // Already current saves are left alone
+        #[test]
+        fn upgrade_current_save_is_a_no_op() {
+            let mut save = serde_json::json!({ "version": SAVE_VERSION, "entities": [], "resources": {} });
+            let before = save.clone();
+            upgrade_save(&mut save).unwrap();
+            assert_eq!(save, before);
+        }
+
+        #[test]
+        fn newer_files_are_rejected() {
+            let mut save = serde_json::json!({ "version": SAVE_VERSION + 1, "entities": [], "resources": {} });
+            let err = upgrade_save(&mut save).unwrap_err();
+            assert!(err.contains("newer version"), "{}", err);
+
+            let mut header = serde_json::json!({ "version": REPLAY_VERSION + 1 });
+            let err = upgrade_replay(&mut header, &mut []).unwrap_err();
+            assert!(err.contains("newer version"), "{}", err);
+        }
+
+        #[test]
+        fn invalid_versions_are_rejected() {
+            let mut save = serde_json::json!({ "version": "two", "entities": [], "resources": {} });
+            assert!(upgrade_save(&mut save).is_err());
+        }
+    }
+}
+
+pub mod save_state {
+    use super::*;
+    use effect_script::{GrantedScript, PendingGrant, Script};
+    use std::collections::{HashMap, HashSet};
+    use serde_json::{Map, Value};
+
+    // Saved entities are respawned with new ids, so references need updating
+    trait RemapEntities {
+        fn remap_entities(&mut self, _map: &HashMap<Entity, Entity>) {}
+    }
+
+    fn remap(entity: &mut Entity, map: &HashMap<Entity, Entity>) {
+        if let Some(new) = map.get(entity) {
+            *entity = *new;
+        }
+    }
+
+    impl RemapEntities for GameEvent {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.targets.iter_mut().for_each(|v| remap(v, map));
+            remap(&mut self.card, map);
+            remap(&mut self.actor, map);
+            self.additional_costs.iter_mut().for_each(|v| remap(v, map));
+        }
+    }
+
+    impl RemapEntities for ChainLink {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            remap(&mut self.attacker, map);
+            remap(&mut self.attack, map);
+            self.targets
+                .iter_mut()
+                .chain(self.blocks.iter_mut())
+                .chain(self.attack_reactions.iter_mut())
+                .chain(self.defense_reactions.iter_mut())
+                .for_each(|v| remap(v, map));
+        }
+    }
+
+    impl RemapEntities for Chain {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.links
+                .iter_mut()
+                .chain(self.history.iter_mut())
+                .for_each(|v| v.remap_entities(map));
+        }
+    }
+
+    impl RemapEntities for Stack {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|v| v.remap_entities(map));
+        }
+    }
+
+    impl RemapEntities for AttackLayer {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|v| v.remap_entities(map));
+        }
+    }
+
+    impl RemapEntities for AttachedTo {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            remap(&mut self.0, map);
+        }
+    }
+
+    impl RemapEntities for Owner {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            remap(&mut self.0, map);
+        }
+    }
+
+    impl RemapEntities for Controller {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            remap(&mut self.0, map);
+        }
+    }
+
+    impl RemapEntities for PendingArcane {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            if let Some(hit) = self.0.as_mut() {
+                remap(&mut hit.source, map);
+                remap(&mut hit.target, map);
+            }
+        }
+    }
+
+    impl RemapEntities for PendingSearch {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            if let Some(search) = self.0.as_mut() {
+                remap(&mut search.hero, map);
+                search.matches.iter_mut().for_each(|v| remap(v, map));
+            }
+        }
+    }
+
+    impl RemapEntities for PendingArrangement {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            if let Some(looked_at) = self.0.as_mut() {
+                remap(&mut looked_at.hero, map);
+                looked_at.cards.iter_mut().for_each(|v| remap(v, map));
+            }
+        }
+    }
+
+    impl RemapEntities for PendingPitch {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|(card, _)| remap(card, map));
+        }
+    }
+
+    impl RemapEntities for ProposedEvent {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|v| v.remap_entities(map));
+        }
+    }
+
+    impl RemapEntities for Played {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|v| remap(v, map));
+        }
+    }
+
+    impl RemapEntities for Priority {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.holding
+                .iter_mut()
+                .chain(self.passed.iter_mut())
+                .chain(self.turn.iter_mut())
+                .for_each(|v| remap(v, map));
+        }
+    }
+
+    impl RemapEntities for PitchZone {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|v| remap(v, map));
+        }
+    }
+
+    impl RemapEntities for HandZone {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|v| remap(v, map));
+        }
+    }
+
+    impl RemapEntities for DeckZone {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|v| remap(v, map));
+        }
+    }
+
+    impl RemapEntities for Graveyard {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            self.0.iter_mut().for_each(|v| remap(v, map));
+        }
+    }
+
+    impl RemapEntities for PitchModifier {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            match self {
+                PitchModifier::Card { card, .. } => remap(card, map),
+                PitchModifier::Hero { hero, .. } => remap(hero, map),
+            }
+        }
+    }
+
+    impl RemapEntities for CostModifier {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            match self {
+                CostModifier::Static { permanent, .. } => remap(permanent, map),
+                CostModifier::NextAttack { hero, .. } => remap(hero, map),
+            }
+        }
+    }
+
+    impl RemapEntities for Revealed {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            remap(&mut self.card, map);
+            if let Some(to) = self.to.as_mut() {
+                remap(to, map);
+            }
+        }
+    }
+
+    impl RemapEntities for BankedResources {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            remap(&mut self.hero, map);
+        }
+    }
+
+    impl RemapEntities for DamageModifier {
+        fn remap_entities(&mut self, map: &HashMap<Entity, Entity>) {
+            match self {
+                DamageModifier::Prevent { hero, .. } => remap(hero, map),
+                DamageModifier::Increase { hero, .. } => remap(hero, map),
+                DamageModifier::Redirect { from, to } => {
+                    remap(from, map);
+                    remap(to, map);
+                }
+            }
+        }
+    }
+
+    impl RemapEntities for GameState {}
+    impl RemapEntities for CombatState {}
+    impl RemapEntities for SharedLife {}
+    impl RemapEntities for GameConfig {}
+
+    // Generates the save and load code for every persisted component and resource
+    macro_rules! persisted {
+        (
+            components: [$($plain:ty),*],
+            entity_components: [$($mapped:ty),*],
+            resources: [$($resource:ty),*]
+        ) => {
+            $(impl RemapEntities for $plain {})*
+
+            fn save_components(world: &World, entity: Entity) -> Result<Map<String, Value>, String> {
+                let mut components = Map::new();
+                $(
+                    if let Some(component) = world.get::<$plain>(entity) {
+                        components.insert(stringify!($plain).to_string(), to_value(component)?);
+                    }
+                )*
+                $(
+                    if let Some(component) = world.get::<$mapped>(entity) {
+                        components.insert(stringify!($mapped).to_string(), to_value(component)?);
+                    }
+                )*
+                Ok(components)
+            }
+
+            fn load_components(
+                world: &mut World,
+                entity: Entity,
+                components: &Map<String, Value>,
+                map: &HashMap<Entity, Entity>
+            ) -> Result<(), String> {
+                $(
+                    if let Some(value) = components.get(stringify!($plain)) {
+                        let component: $plain = from_value(value)?;
+                        world.entity_mut(entity).insert(component);
+                    }
+                )*
+                $(
+                    if let Some(value) = components.get(stringify!($mapped)) {
+                        let mut component: $mapped = from_value(value)?;
+                        component.remap_entities(map);
+                        world.entity_mut(entity).insert(component);
+                    }
+                )*
+                Ok(())
+            }
+
+            fn save_resources(world: &World) -> Result<Map<String, Value>, String> {
+                let mut resources = Map::new();
+                $(
+                    resources.insert(
+                        stringify!($resource).to_string(),
+                        to_value(world.resource::<$resource>())?
+                    );
+                )*
+                Ok(resources)
+            }
+
+            // Resources are replaced without triggering change detection,
+            // so the schedule picks up exactly where the saved game left off
+            fn load_resources(
+                world: &mut World,
+                resources: &Map<String, Value>,
+                map: &HashMap<Entity, Entity>
+            ) -> Result<(), String> {
+                $(
+                    let value = resources
+                        .get(stringify!($resource))
+                        .ok_or(format!("Save is missing {}", stringify!($resource)))?;
+                    let mut resource: $resource = from_value(value)?;
+                    resource.remap_entities(map);
+                    *world.resource_mut::<$resource>().bypass_change_detection() = resource;
+                )*
+                Ok(())
+            }
+        };
+    }
+
+    persisted! {
+        components: [
+            CardName, Cost, Color, Attack, Defense, CardType, CardSubTypes, CardClass,
+            GoAgain, Id, Script, PendingGrant, GrantedScript, Expires, Hero, HeroAbility, UsageLimit, PlayerName, Intellect, Health, HeroAge,
+            Resources, ActionPoints, ArcaneDamage, ArcaneBarrier, Counters, StatBonus, GrantsKeyword,
+            Granted, PrintedText, CardText, OnResolve, Targeting, Ally, LifeCost, Stops, Team, AdditionalCost,
+            Item, ItemAbility, InPlay, Exhausted, ZoneUsage, InChain, InGraveyard
+        ],
+        entity_components: [
+            PitchZone, HandZone, DeckZone, Graveyard, PitchModifier, DamageModifier, CostModifier,
+            AttachedTo, Owner, Controller, BankedResources, Revealed
+        ],
+        resources: [
+            Priority, Chain, Stack, AttackLayer, ProposedEvent, GameState, CombatState, Played,
+            PendingArcane, PendingPitch, PendingSearch, PendingArrangement, SharedLife, GameConfig
+        ]
+    }
+
+    fn to_value<T: Serialize>(value: &T) -> Result<Value, String> {
+        serde_json::to_value(value).map_err(|err| format!("Failed to save: {}", err))
+    }
+
+    fn from_value<T: for<'de> Deserialize<'de>>(value: &Value) -> Result<T, String> {
+        T::deserialize(value).map_err(|err| format!("Invalid save: {}", err))
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SavedEntity {
+        entity: Entity,
+        components: Map<String, Value>
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SaveFile {
+        version: u32,
+        entities: Vec<SavedEntity>,
+        resources: Map<String, Value>
+    }
+
+    pub fn save(world: &mut World, path: &str) -> Result<(), String> {
+        write(path, &to_json(world)?)
+    }
+
+    pub fn write(path: &str, contents: &str) -> Result<(), String> {
+        // Write beside the save first so a crash mid-write leaves the old save intact
+        let partial = format!("{}.partial", path);
+        std::fs::write(&partial, contents)
+            .map_err(|err| format!("Failed to write \"{}\": {}", partial, err))?;
+        std::fs::rename(&partial, path)
+            .map_err(|err| format!("Failed to write \"{}\": {}", path, err))
+    }
+
+    // Serializes every entity and game resource
+    pub fn to_json(world: &mut World) -> Result<String, String> {
+        let entities = world
+            .iter_entities()
+            .map(|v| v.id())
+            .collect::<Vec<Entity>>()
+            .into_iter()
+            .map(|entity| {
+                save_components(world, entity)
+                    .map(|components| SavedEntity { entity, components })
+            })
+            .collect::<Result<Vec<SavedEntity>, String>>()?;
+
+        let save_file = SaveFile {
+            version: migration::SAVE_VERSION,
+            entities,
+            resources: save_resources(world)?
+        };
+        serde_json::to_string_pretty(&save_file)
+            .map_err(|err| format!("Failed to save: {}", err))
+    }
+
+    // Written at every turn boundary so a crashed game can be resumed
+    pub const AUTOSAVE_PATH: &str = "autosave.json";
+
+    pub fn autosave_exists() -> bool {
+        std::path::Path::new(AUTOSAVE_PATH).exists()
+    }
+
+    pub fn autosave(world: &mut World) -> Result<(), String> {
+        save(world, AUTOSAVE_PATH).map_err(|err| format!("Autosave failed: {}", err))
+    }
+
+    // A finished game has nothing to resume
+    pub fn clear_autosave() {
+        let _ = std::fs::remove_file(AUTOSAVE_PATH);
+    }
+
+    // Where a game stopped by agreement or by server shutdown is kept
+    pub const ADJOURNED_PATH: &str = "adjourned.json";
+
+    // Saves the game, including whoever must act next, and tells the players where it went
+    // Returns the message for the terminal
+    #[cfg(feature = "cli")]
+    pub fn adjourn(world: &mut World, input: &mut dyn input_sources::InputSource) -> Result<String, String> {
+        save(world, ADJOURNED_PATH).map_err(|err| format!("Adjourning failed: {}", err))?;
+        input.notify(&serde_json::json!({ "adjourned": ADJOURNED_PATH }));
+        clear_autosave();
+        Ok(format!("Game adjourned to \"{}\", resume it with --resume {}", ADJOURNED_PATH, ADJOURNED_PATH))
+    }
+
+    // Asks on the terminal whether to resume the last autosave
+    #[cfg(feature = "cli")]
+    pub fn prompt_resume() -> bool {
+        if !autosave_exists() {
+            return false;
+        }
+        println!("An unfinished game was autosaved. Resume it? (y/n)");
+        let mut buffer = String::new();
+        io::stdin().read_line(&mut buffer).is_ok()
+            && buffer.trim().eq_ignore_ascii_case("y")
+    }
+
+    // Replaces every entity and game resource with the saved state
+    pub fn load(world: &mut World, path: &str) -> Result<(), String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read \"{}\": {}", path, err))?;
+        from_json(world, &contents)
+    }
+
+    pub fn from_json(world: &mut World, contents: &str) -> Result<(), String> {
+        // Older saves are upgraded before they are read
+        let mut value: Value = serde_json::from_str(contents)
+            .map_err(|err| format!("Invalid save: {}", err))?;
+        let version = migration::upgrade_save(&mut value).map_err(|err| format!("Invalid save: {}", err))?;
+        let save_file: SaveFile = from_value(&value)?;
+
+        // Despawn anything that is not part of the save
+        let saved_ids = save_file.entities
+            .iter()
+            .map(|v| v.entity)
+            .collect::<HashSet<Entity>>();
+        let live = world.iter_entities().map(|v| v.id()).collect::<Vec<Entity>>();
+        for entity in live {
+            if !saved_ids.contains(&entity) {
+                world.despawn(entity);
+            }
+        }
+
+        // Keep saved ids where possible so players can keep using the same ids
+        let mut map = HashMap::new();
+        for saved in &save_file.entities {
+            if let Some(mut entity) = world.get_or_spawn(saved.entity) {
+                entity.retain::<()>();
+                map.insert(saved.entity, entity.id());
+            }
+        }
+        for saved in &save_file.entities {
+            map.entry(saved.entity).or_insert_with(|| world.spawn_empty().id());
+        }
+
+        for saved in &save_file.entities {
+            load_components(world, map[&saved.entity], &saved.components, &map)?;
+        }
+        load_resources(world, &save_file.resources, &map)?;
+
+        let mut log = world.resource_mut::<GameLog>();
+        if version < migration::SAVE_VERSION {
+            game_log!(log, Actions, Normal, "Upgraded save from v{} to v{}", version, migration::SAVE_VERSION);
+        }
+        for (old, new) in &map {
+            if old != new {
+                game_log!(log, Actions, Normal, "Entity {} is now entity {}", old.index(), new.index());
+            }
+        }
+        Ok(())
+    }
+}
+
+// Recording games and auditing them afterwards
+pub mod replay {
+    use super::*;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader, Write};
+
+    // First line of a replay, so the table and the random roll for first are reproduced
+    #[derive(Serialize, Deserialize)]
+    struct ReplayHeader {
+        version: u32,
+        turn_order: Vec<u32>,
+        setup: TableSetup
+    }
+
+    // How the table was set up before the first turn, so a replay sets it up the same way
+    #[derive(Clone, Default, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct TableSetup {
+        pub config: GameConfig,
+        pub teams: TeamPlay,
+        pub policy: ResourcePolicy,
+        // Deck lists in hero order
+        pub decks: Vec<deck_list::DeckList>,
+        // Each hero's deck once shuffled, as entity ids, since the shuffle cannot be rolled again
+        pub deck_order: Vec<Vec<u32>>
+    }
+
+    impl TableSetup {
+        // Spawns the heroes and their decks into an empty world, returning every hero in spawn order
+        // Fails if a deck list is not legal in the format, or a deck order does not match its deck
+        pub fn set(&self, world: &mut World) -> Result<Vec<Entity>, String> {
+            world.insert_resource(self.config);
+            world.insert_resource(self.teams);
+            world.insert_resource(self.policy);
+            setup_world(world);
+
+            let heroes = all_heroes(world);
+            if self.decks.len() > heroes.len() {
+                return Err(format!("{} deck lists for {} heroes", self.decks.len(), heroes.len()));
+            }
+            for (hero, deck) in heroes.iter().zip(&self.decks) {
+                deck_list::add(world, *hero, deck).map_err(|problems| {
+                    format!("The deck for hero {} is not legal:\n  {}", hero.index(), problems.join("\n  "))
+                })?;
+            }
+
+            for (hero, order) in heroes.iter().zip(&self.deck_order) {
+                let mut deck = world.get_mut::<DeckZone>(*hero).expect("Heroes should have a deck");
+                let order = order.iter().copied().map(Entity::from_raw).collect::<VecDeque<Entity>>();
+                let mut sorted = order.iter().collect::<Vec<&Entity>>();
+                let mut cards = deck.0.iter().collect::<Vec<&Entity>>();
+                sorted.sort();
+                cards.sort();
+                if sorted != cards {
+                    return Err(format!("The deck order for hero {} does not match their deck", hero.index()));
+                }
+                deck.0 = order;
+            }
+            Ok(heroes)
+        }
+
+        // Records each hero's deck order, once the decks are shuffled
+        pub fn keep_deck_order(&mut self, world: &World, heroes: &[Entity]) {
+            self.deck_order = heroes
+                .iter()
+                .map(|hero| {
+                    world.get::<DeckZone>(*hero).map(|v| v.0.iter().map(|v| v.index()).collect()).unwrap_or_default()
+                })
+                .collect();
+        }
+    }
+
+    // Writes the table setup and turn order, then every player event, one JSON value per line
+    pub struct Recorder {
+        file: File
+    }
+
+    impl Recorder {
+        pub fn create(path: &str, setup: &TableSetup, turn_order: &VecDeque<Entity>) -> Result<Self, String> {
+            let mut file = File::create(path)
+                .map_err(|err| format!("Failed to create replay \"{}\": {}", path, err))?;
+            let header = ReplayHeader {
+                version: migration::REPLAY_VERSION,
+                turn_order: turn_order.iter().map(|v| v.index()).collect(),
+                setup: setup.clone()
+            };
+            writeln!(file, "{}", serde_json::to_string(&header).unwrap())
+                .map_err(|err| format!("Failed to write replay: {}", err))?;
+            Ok(Recorder { file })
+        }
+
+        pub fn record(&mut self, event: &EventType) -> Result<(), String> {
+            let line = serde_json::to_string(event)
+                .map_err(|err| format!("Failed to write replay: {}", err))?;
+            writeln!(self.file, "{}", line).map_err(|err| format!("Failed to write replay: {}", err))
+        }
+    }
+
+    // Returns the header, the events and the version the replay was written in
+    fn load(path: &str) -> Result<(ReplayHeader, Vec<EventType>, u32), String> {
+        let file = File::open(path)
+            .map_err(|err| format!("Failed to open replay \"{}\": {}", path, err))?;
+        let mut lines = BufReader::new(file).lines();
+
+        let parse = |line: Result<String, std::io::Error>| {
+            line.map_err(|err| format!("Failed to read replay: {}", err))
+                .and_then(|line| {
+                    serde_json::from_str::<serde_json::Value>(&line)
+                        .map_err(|err| format!("Invalid replay line: {}", err))
+                })
+        };
+        let mut header = parse(lines.next().ok_or("Replay is empty")?)?;
+        let mut events = lines.map(parse).collect::<Result<Vec<serde_json::Value>, String>>()?;
+
+        // Older replays are upgraded before they are read
+        let version = migration::upgrade_replay(&mut header, &mut events)
+            .map_err(|err| format!("Invalid replay: {}", err))?;
+
+        let header = ReplayHeader::deserialize(&header)
+            .map_err(|err| format!("Invalid replay header: {}", err))?;
+        let events = events
+            .iter()
+            .map(|event| {
+                EventType::deserialize(event).map_err(|err| format!("Invalid replay event: {}", err))
+            })
+            .collect::<Result<Vec<EventType>, String>>()?;
+
+        Ok((header, events, version))
+    }
+
+    // One decision point: who held priority, what they could do, and what they did
+    struct PriorityWindow {
+        phase: GamePhases,
+        combat_step: Option<CombatSteps>,
+        holder: Option<String>,
+        legal: Vec<&'static str>,
+        action: String
+    }
+
+    // Broad kinds of action open to the priority holder
+    fn legal_actions(world: &World, hero: Entity) -> Vec<&'static str> {
+        let priority = world.resource::<Priority>();
+        match priority.choice_for(&hero) {
+            Some(PendingChoice::Blocks) => return vec!["block"],
+            Some(PendingChoice::Discard) => return vec!["discard"],
+            Some(PendingChoice::ArcaneBarrier) => return vec!["barrier", "pitch"],
+            Some(PendingChoice::Search) => return vec!["search"],
+            Some(PendingChoice::Arrange) => return vec!["opt"],
+            None => {}
+        }
+
+        let mut legal = vec!["pass", "play"];
+        if world.resource::<ProposedEvent>().0.is_some() {
+            legal.push("pitch");
+        }
+        if world.resource::<ProposedEvent>().0.as_ref().is_some_and(|v| v.actor == hero) {
+            legal.push("cancel");
+        }
+        let hero_ability = world.get::<HeroAbility>(hero).is_some()
+            && world.get::<UsageLimit>(hero).is_none_or(|v| v.available());
+        if hero_ability || !ready_items(world, hero).is_empty() {
+            legal.push("activate");
+        }
+        legal
+    }
+
+    fn describe(world: &World, event: &EventType) -> String {
+        let name = |entity: Entity| {
+            world
+                .get::<CardName>(entity)
+                .map(|v| format!("\"{}\"", v.0))
+                .unwrap_or(format!("entity {}", entity.index()))
+        };
+        match event {
+            EventType::PlayCard(event) if event.targets.is_empty() => format!("play {}", name(event.card)),
+            EventType::PlayCard(event) => format!(
+                "play {} targeting {}",
+                name(event.card),
+                event.targets.iter().map(|v| name(*v)).collect::<Vec<String>>().join(", ")
+            ),
+            EventType::PassPriority(_) => String::from("pass"),
+            EventType::PitchCard(event) => format!("pitch {}", name(event.card)),
+            EventType::DeclareBlocks(event) => format!(
+                "block with [{}]",
+                event.blocks.iter().map(|v| name(*v)).collect::<Vec<String>>().join(", ")
+            ),
+            EventType::ActivateHeroAbility(_) => String::from("activate hero ability"),
+            EventType::ActivateItem(event) => format!("activate {}", name(event.item)),
+            EventType::DiscardCard(event) => format!("discard {}", name(event.card)),
+            EventType::PayArcaneBarrier(event) => format!("pay {} arcane barrier", event.amount),
+            EventType::ChooseSearch(ChooseSearch { card: Some(card), .. }) => format!("take {} from the deck", name(*card)),
+            EventType::ChooseSearch(ChooseSearch { card: None, .. }) => String::from("take nothing from the deck"),
+            EventType::ArrangeDeck(event) => format!(
+                "put {} card(s) on top and {} on the bottom",
+                event.arrangement.top.len(),
+                event.arrangement.bottom.len()
+            ),
+            EventType::Cancel(_) => String::from("cancel the unpaid play"),
+            EventType::Plan(_) => String::from("plan"),
+            EventType::Save(path) => format!("save \"{}\"", path),
+            EventType::Load(path) => format!("load \"{}\"", path),
+            EventType::Log { subsystem: Some(subsystem), level } => format!("log {:?} {:?}", subsystem, level),
+            EventType::Log { subsystem: None, level } => format!("log {:?}", level),
+            EventType::Adjourn { .. } => String::from("propose adjourning"),
+            EventType::Rules(topic) => format!("look up rules for \"{}\"", topic),
+            EventType::Hands { .. } => String::from("look at hand sizes"),
+            EventType::Inspect { .. } => String::from("inspect a card"),
+            EventType::SetStops { stops, .. } => format!("stop at {}", stops.describe()),
+            EventType::End => String::from("end"),
+        }
+    }
+
+    // Replays a recorded game and renders its priority windows
+    pub fn timeline(path: &str) -> Result<String, String> {
+        let (header, events, version) = load(path)?;
+
+        let mut world = World::new();
+        let heroes = header.setup.set(&mut world)?;
+
+        // Every hero at the table takes a turn, so the turn order must name each of them once
+        let turn_order = header.turn_order.iter().copied().map(Entity::from_raw).collect::<VecDeque<Entity>>();
+        if let Some(unknown) = turn_order.iter().find(|v| !heroes.contains(v)) {
+            return Err(format!("The turn order names hero {}, who is not at the table", unknown.index()));
+        }
+        if turn_order.len() != heroes.len() || heroes.iter().any(|v| !turn_order.contains(v)) {
+            return Err(format!("The turn order does not name each of the {} heroes once", heroes.len()));
+        }
+        world.resource_mut::<Priority>().holding = turn_order;
+        let mut schedule = game_schedule();
+        schedule.run(&mut world);
+
+        let mut windows = Vec::new();
+        for event in events {
+            // The game loop keeps running until someone can act
+            settle(&mut world, &mut schedule);
+
+            let priority = world.resource::<Priority>();
+            let holder = priority.priority_hero().copied();
+            windows.push(PriorityWindow {
+                phase: world.resource::<GameState>().0.clone(),
+                combat_step: world.resource::<CombatState>().0.clone(),
+                holder: holder.map(|hero| {
+                    world
+                        .get::<PlayerName>(hero)
+                        .map(|v| v.0.clone())
+                        .unwrap_or(format!("entity {}", hero.index()))
+                }),
+                legal: holder.map(|hero| legal_actions(&world, hero)).unwrap_or_default(),
+                action: match event.hero().and_then(|hero| world.get::<PlayerName>(hero)) {
+                    Some(sender) => format!("{} sent {}", sender.0, describe(&world, &event)),
+                    None => describe(&world, &event)
+                }
+            });
+
+            // Saves, loads, plans, logging changes, adjournments and lookups are not replayed
+            match event {
+                EventType::Save(_)
+                | EventType::Load(_)
+                | EventType::Plan(_)
+                | EventType::Log { .. }
+                | EventType::Adjourn { .. }
+                | EventType::Rules(_)
+                | EventType::Hands { .. }
+                | EventType::Inspect { .. } => continue,
+                event => {
+                    if !event.send(&mut world) {
+                        break;
+                    }
+                }
+            }
+            schedule.run(&mut world);
+        }
+
+        let mut output = String::new();
+        if version < migration::REPLAY_VERSION {
+            output.push_str(&format!("Upgraded replay from v{} to v{}\n", version, migration::REPLAY_VERSION));
+        }
+        for (index, window) in windows.iter().enumerate() {
+            let step = window.combat_step
+                .as_ref()
+                .map(|v| format!(" / {:?}", v))
+                .unwrap_or_default();
+            output.push_str(&format!(
+                "{:>4}  {:?}{}\n      priority: {}\n      legal: {}\n      did: {}\n",
+                index + 1,
+                window.phase,
+                step,
+                window.holder.as_deref().unwrap_or("nobody"),
+                window.legal.join(", "),
+                window.action
+            ));
+        }
+        Ok(output)
+    }
+}
+
+// Previewing a sequence of actions for pitch math
+pub mod planner {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub enum PlanStep {
+        Pitch(#[serde(with = "entity_ids")] Entity),
+        Play {
+            #[serde(with = "entity_ids")]
+            card: Entity,
+            #[serde(with = "entity_ids::list", default)]
+            targets: Vec<Entity>
+        },
+    }
+
+    impl PlanStep {
+        // Parses "pitch <card>", "play <card> [targets]" or "attack <card> <target> [targets]"
+        pub fn parse(step: &str, handles: &EntityHandles) -> Result<Self, CommandError> {
+            let mut pieces = step.split_whitespace();
+            let action = pieces.next().ok_or(CommandError::MissingArgument("Plan step"))?;
+            let card = pieces.next().map(|v| handles.parse("Card", v)).transpose()?;
+            let targets = pieces
+                .map(|v| handles.parse("Target", v))
+                .collect::<Result<Vec<Entity>, CommandError>>()?;
+            match action.to_lowercase().as_str() {
+                "pitch" => Ok(PlanStep::Pitch(card.ok_or(CommandError::MissingArgument("Card to pitch"))?)),
+                "play" => Ok(PlanStep::Play {
+                    card: card.ok_or(CommandError::MissingArgument("Card to play"))?,
+                    targets
+                }),
+                "attack" => {
+                    let card = card.ok_or(CommandError::MissingArgument("Attack"))?;
+                    if targets.is_empty() {
+                        return Err(CommandError::MissingArgument("Attack target"));
+                    }
+                    Ok(PlanStep::Play { card, targets })
+                },
+                other => Err(CommandError::UnknownCommand(format!("plan {}", other)))
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Plan {
+        #[serde(with = "entity_ids")]
+        pub hero: Entity,
+        pub steps: Vec<PlanStep>
+    }
+
+    // Runs the plan against a copy of the game and reports what would happen
+    // The game itself is left untouched
+    pub fn preview(world: &mut World, plan: &Plan) -> Result<String, String> {
+        let state = save_state::to_json(world)?;
+        run(&state, plan)
+    }
+
+    fn run(state: &str, plan: &Plan) -> Result<String, String> {
+        // Settle a fresh game first so the copied state is not seen as newly changed
+        let mut sandbox = World::new();
+        let mut schedule = game_schedule();
+        setup_world(&mut sandbox);
+        sandbox.resource_mut::<GameLog>().set_all(logging::Verbosity::Quiet);
+        start_up_schedule().run(&mut sandbox);
+        schedule.run(&mut sandbox);
+        save_state::from_json(&mut sandbox, state)?;
+
+        let mut report = Vec::new();
+        let mut legal = true;
+        for step in &plan.steps {
+            pass_opponents(&mut sandbox, &mut schedule, plan.hero);
+            let (line, accepted) = match step {
+                PlanStep::Pitch(card) => pitch(&mut sandbox, &mut schedule, plan.hero, *card),
+                PlanStep::Play { card, targets } => play(&mut sandbox, &mut schedule, plan.hero, *card, targets.clone())
+            };
+            report.push(line);
+            if !accepted {
+                legal = false;
+                break;
+            }
+        }
+
+        if legal && sandbox.resource::<ProposedEvent>().0.is_some() {
+            legal = false;
+            report.push(String::from("The last card played is not paid for"));
+        }
+
+        report.push(format!(
+            "{}: {} resource(s) and {} action point(s) left",
+            if legal { "Legal" } else { "Not legal" },
+            sandbox.get::<Resources>(plan.hero).map(|v| v.0).unwrap_or(0),
+            sandbox.get::<ActionPoints>(plan.hero).map(|v| v.0).unwrap_or(0)
+        ));
+        Ok(report.join("\n"))
+    }
+
+    // Opponents pass, and declare no blocks, until the planning hero can act
+    fn pass_opponents(world: &mut World, schedule: &mut Schedule, hero: Entity) {
+        for _ in 0..MAX_SETTLE_RUNS {
+            settle(world, schedule);
+            let priority = world.resource::<Priority>();
+            let other = match priority.priority_hero().copied() {
+                Some(other) if other != hero && priority.someone_has_priority() => other,
+                _ => return
+            };
+            let event = if priority.is_choosing(&other, PendingChoice::Blocks) {
+                EventType::DeclareBlocks(DeclareBlocks { hero: other, blocks: Vec::new() })
+            } else {
+                EventType::PassPriority(PassPriority { hero: other })
+            };
+            event.send(world);
+            schedule.run(world);
+        }
+    }
+
+    // Acts on what was just sent, then runs until someone can act, as the game loop would between inputs
+    fn advance(world: &mut World, schedule: &mut Schedule) {
+        schedule.run(world);
+        settle(world, schedule);
+    }
+
+    fn name(world: &World, entity: Entity) -> String {
+        world.get::<CardName>(entity)
+            .map(|v| v.0.clone())
+            .unwrap_or(format!("{}", entity.index()))
+    }
+
+    fn pitch(world: &mut World, schedule: &mut Schedule, hero: Entity, card: Entity) -> (String, bool) {
+        let mut modifiers = world.query::<&PitchModifier>();
+        let value = world.get::<Color>(card)
+            .map(|color| modified_pitch(color, hero, card, modifiers.iter(world)))
+            .unwrap_or(0);
+        EventType::PitchCard(PitchCard { hero, card }).send(world);
+        advance(world, schedule);
+
+        let pitched = world.get::<PitchZone>(hero).is_some_and(|v| v.0.contains(&card));
+        let floating = world.get::<Resources>(hero).map(|v| v.0).unwrap_or(0);
+        if pitched {
+            (format!("Pitch \"{}\" for {}, {} resource(s) floating", name(world, card), value, floating), true)
+        } else {
+            (format!("Cannot pitch \"{}\" now", name(world, card)), false)
+        }
+    }
+
+    fn play(
+        world: &mut World,
+        schedule: &mut Schedule,
+        hero: Entity,
+        card: Entity,
+        targets: Vec<Entity>
+    ) -> (String, bool) {
+        EventType::PlayCard(PlayCard { hero, card, targets, additional_costs: Vec::new() }).send(world);
+        advance(world, schedule);
+
+        let awaiting_payment = world.resource::<ProposedEvent>().0
+            .as_ref()
+            .is_some_and(|v| v.card == card);
+        let played = world.resource::<Stack>().0.iter().any(|v| v.card == card)
+            || world.resource::<AttackLayer>().0.as_ref().is_some_and(|v| v.card == card)
+            || world.resource::<Chain>().links.iter().any(|v| v.attack == card);
+
+        if awaiting_payment {
+            let printed = world.get::<Cost>(card).map(|v| v.0).unwrap_or(0);
+            let attack = world.get::<CardSubTypes>(card).is_some_and(|v| v.has_attack());
+            let mut modifiers = world.query::<&CostModifier>();
+            let cost = modified_cost(
+                printed,
+                hero,
+                attack,
+                modifiers.iter(world),
+                |permanent| world.get::<Health>(permanent).and(world.get::<Controller>(permanent)).map(|v| v.0)
+            );
+            let resources = world.get::<Resources>(hero).map(|v| v.0).unwrap_or(0);
+            (format!("Play \"{}\", pitch at least {} more to pay for it", name(world, card), cost.saturating_sub(resources)), true)
+        } else if played {
+            (format!("Play \"{}\"", name(world, card)), true)
+        } else {
+            (format!("Cannot play \"{}\" now", name(world, card)), false)
+        }
+    }
+}
+
+// Gym-style interface for training agents against the rules engine
+pub mod environment {
+    use super::*;
+
+    // Compact observation of the game from one hero's perspective:
+    // [phase, combat step, has priority, is blocking,
+    //  health, resources, action points, hand size,
+    //  opponent health, opponent resources, opponent hand size,
+    //  chain links, stack size]
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Observation(pub Vec<u16>);
+
+    pub struct Step {
+        pub observation: Observation,
+        pub reward: f32,
+        pub done: bool
+    }
+
+    pub struct Environment {
+        world: World,
+        schedule: Schedule,
+        heroes: [Entity; 2],
+        // Hero the observations and rewards are for
+        agent: usize,
+        done: bool
+    }
+
+    impl Environment {
+        // Agent plays as the hero at the given index in turn setup
+        pub fn new(agent: usize) -> Self {
+            let mut env = Environment {
+                world: World::new(),
+                schedule: game_schedule(),
+                heroes: [Entity::PLACEHOLDER; 2],
+                agent: agent.min(1),
+                done: false
+            };
+            env.reset();
+            env
+        }
+
+        pub fn reset(&mut self) -> Observation {
+            self.world = World::new();
+            self.schedule = game_schedule();
+            self.heroes = setup_world(&mut self.world);
+            self.done = false;
+
+            start_up_schedule().run(&mut self.world);
+            settle(&mut self.world, &mut self.schedule);
+            self.observation()
+        }
+
+        pub fn agent(&self) -> Entity {
+            self.heroes[self.agent]
+        }
+
+        pub fn opponent(&self) -> Entity {
+            self.heroes[1 - self.agent]
+        }
+
+        // Hero expected to act next
+        pub fn acting_hero(&self) -> Option<Entity> {
+            self.world.resource::<Priority>().priority_hero().copied()
+        }
+
+        // Applies an action for the hero holding priority
+        // Reward is the change in life difference in the agent's favor
+        pub fn step(&mut self, action: EventType) -> Step {
+            if self.done {
+                return Step { observation: self.observation(), reward: 0.0, done: true };
+            }
+
+            let before = self.life_difference();
+            if !action.send(&mut self.world) {
+                self.done = true;
+            } else {
+                self.schedule.run(&mut self.world);
+                settle(&mut self.world, &mut self.schedule);
+            }
+            let reward = (self.life_difference() - before) as f32;
+
+            self.done = self.done || self.life(self.agent()) == 0 || self.life(self.opponent()) == 0;
+            Step { observation: self.observation(), reward, done: self.done }
+        }
+
+        pub fn observation(&self) -> Observation {
+            let priority = self.world.resource::<Priority>();
+            let agent = self.agent();
+            let opponent = self.opponent();
+
+            let phase = match self.world.resource::<GameState>().0 {
+                GamePhases::StartPhase => 0,
+                GamePhases::ActionPhase => 1,
+                GamePhases::EndPhase => 2,
+            };
+            let combat_step = match &self.world.resource::<CombatState>().0 {
+                None => 0,
+                Some(CombatSteps::LayerStep) => 1,
+                Some(CombatSteps::AttackStep) => 2,
+                Some(CombatSteps::DefendStep) => 3,
+                Some(CombatSteps::ReactionStep) => 4,
+                Some(CombatSteps::DamageStep) => 5,
+                Some(CombatSteps::ResolutionStep) => 6,
+                Some(CombatSteps::LinkStep) => 7,
+                Some(CombatSteps::CloseStep) => 8,
+                Some(CombatSteps::PreventionStep) => 9,
+            };
+
+            Observation(vec![
+                phase,
+                combat_step,
+                priority.has_priority(&agent) as u16,
+                priority.is_choosing(&agent, PendingChoice::Blocks) as u16,
+                self.life(agent),
+                self.resources(agent),
+                self.world.get::<ActionPoints>(agent).map(|v| v.0).unwrap_or(0),
+                self.hand_size(agent),
+                self.life(opponent),
+                self.resources(opponent),
+                self.hand_size(opponent),
+                self.world.resource::<Chain>().links.len() as u16,
+                self.world.resource::<Stack>().0.len() as u16,
+            ])
+        }
+
+        pub fn is_done(&self) -> bool {
+            self.done
+        }
+
+        fn life(&self, hero: Entity) -> u16 {
+            self.world.get::<Health>(hero).map(|v| v.0).unwrap_or(0)
+        }
+
+        fn resources(&self, hero: Entity) -> u16 {
+            self.world.get::<Resources>(hero).map(|v| v.0).unwrap_or(0)
+        }
+
+        fn hand_size(&self, hero: Entity) -> u16 {
+            self.world.get::<HandZone>(hero).map(|v| v.0.len() as u16).unwrap_or(0)
+        }
+
+        fn life_difference(&self) -> i32 {
+            self.life(self.agent()) as i32 - self.life(self.opponent()) as i32
+        }
+    }
+}
+
+// Scripted games for exercising the rules engine without stdin
+#[cfg(feature = "harness")]
+pub mod harness {
+    use super::*;
+    use bevy_ecs::event::ManualEventReader;
+
+    pub struct GameHarness {
+        world: World,
+        schedule: Schedule,
+        heroes: [Entity; 2],
+        script: VecDeque<EventType>,
+        // Turn order and every event sent, so the game can be saved as a replay
+        turn_order: VecDeque<Entity>,
+        history: Vec<serde_json::Value>,
+        rejections: ManualEventReader<Rejected>,
+        rejected: Vec<(Entity, validation_systems::Rejection)>,
+        // Schedule runs so far, for measuring throughput
+        ticks: usize
+    }
+
+    impl GameHarness {
+        // Rolls decide who goes first, e.g. [6, 6, 1, 1] gives the second hero the first turn
+        pub fn new(rolls: &[u32]) -> Self {
+            let mut world = World::new();
+            let heroes = setup_world(&mut world);
+            world.insert_resource(Dice(Box::new(dice::ScriptedRolls::new(rolls.to_vec()))));
+            start_up_schedule().run(&mut world);
+
+            let turn_order = world.resource::<Priority>().holding.clone();
+            let rejections = world.resource::<Events<Rejected>>().get_reader();
+            let mut harness = GameHarness {
+                world,
+                schedule: game_schedule(),
+                heroes,
+                script: VecDeque::new(),
+                turn_order,
+                history: Vec::new(),
+                rejections,
+                rejected: Vec::new(),
+                ticks: 0
+            };
+            harness.settle();
+            harness
+        }
+
+        pub fn heroes(&self) -> [Entity; 2] {
+            self.heroes
+        }
+
+        // Verbosity of this game's log, every subsystem at once
+        pub fn set_verbosity(&mut self, level: logging::Verbosity) -> &mut Self {
+            self.world.resource_mut::<GameLog>().set_all(level);
+            self
+        }
+
+        pub fn world(&self) -> &World {
+            &self.world
+        }
+
+        pub fn hand(&self, hero: Entity) -> Vec<Entity> {
+            self.world.get::<HandZone>(hero).map(|v| v.0.clone()).unwrap_or_default()
+        }
+
+        pub fn graveyard(&self, hero: Entity) -> Vec<Entity> {
+            self.world.get::<Graveyard>(hero).map(|v| v.0.clone()).unwrap_or_default()
+        }
+
+        pub fn life(&self, hero: Entity) -> Option<u16> {
+            self.world.get::<Health>(hero).map(|v| v.0)
+        }
+
+        pub fn enqueue(&mut self, events: impl IntoIterator<Item = EventType>) -> &mut Self {
+            self.script.extend(events);
+            self
+        }
+
+        // Queues CLI commands, e.g. "3 play 6 2"
+        pub fn enqueue_commands(&mut self, commands: &[&str]) -> Result<&mut Self, CommandError> {
+            let handles = self.world.resource::<EntityHandles>();
+            let events = commands
+                .iter()
+                .map(|v| parse_event(v, handles))
+                .collect::<Result<Vec<EventType>, CommandError>>()?;
+            Ok(self.enqueue(events))
+        }
+
+        // Sends each queued event, running the schedule until a player can act again
+        // Stops early when the script ends the game
+        pub fn run(&mut self) -> &mut Self {
+            while let Some(event) = self.script.pop_front() {
+                self.history.extend(serde_json::to_value(&event).ok());
+                if !event.send(&mut self.world) {
+                    self.script.clear();
+                    break;
+                }
+                self.run_schedule();
+                self.settle();
+            }
+            self
+        }
+
+        fn settle(&mut self) {
+            self.ticks += settle(&mut self.world, &mut self.schedule);
+            self.read_rejections();
+        }
+
+        // Runs the schedule once without sending anything, as happens while the game waits on a player
+        pub fn tick(&mut self) -> &mut Self {
+            self.run_schedule();
+            self
+        }
+
+        pub fn ticks(&self) -> usize {
+            self.ticks
+        }
+
+        fn run_schedule(&mut self) {
+            self.schedule.run(&mut self.world);
+            self.ticks += 1;
+            self.read_rejections();
+        }
+
+        fn read_rejections(&mut self) {
+            let events = self.world.resource::<Events<Rejected>>();
+            self.rejected.extend(self.rejections.read(events).map(|v| (v.hero, v.reason.clone())));
+        }
+
+        // Writes the events sent so far as a replay, for auditing with the timeline command
+        pub fn save_replay(&self, path: &str) -> Result<(), String> {
+            let mut recorder = replay::Recorder::create(path, &replay::TableSetup::default(), &self.turn_order)?;
+            for value in &self.history {
+                let event = EventType::deserialize(value)
+                    .map_err(|err| format!("Invalid event: {}", err))?;
+                recorder.record(&event)?;
+            }
+            Ok(())
+        }
+
+        // Every combat step the game passed through, in order
+        pub fn steps(&self) -> Vec<CombatSteps> {
+            self.log()
+                .iter()
+                .filter_map(|v| match &v.event {
+                    logging::LogEvent::StepChanged { step } => Some(step.clone()),
+                    _ => None
+                })
+                .collect()
+        }
+
+        pub fn rejected(&self) -> &[(Entity, validation_systems::Rejection)] {
+            &self.rejected
+        }
+
+        pub fn log(&self) -> &[logging::LogEntry] {
+            &self.world.resource::<logging::GameLog>().entries
+        }
+
+        pub fn assert_life(&self, hero: Entity, expected: u16) -> &Self {
+            assert_eq!(self.life(hero), Some(expected), "life of hero {}", hero.index());
+            self
+        }
+
+        pub fn assert_resources(&self, hero: Entity, expected: u16) -> &Self {
+            let resources = self.world.get::<Resources>(hero).map(|v| v.0);
+            assert_eq!(resources, Some(expected), "resources of hero {}", hero.index());
+            self
+        }
+
+        pub fn assert_action_points(&self, hero: Entity, expected: u16) -> &Self {
+            let action_points = self.world.get::<ActionPoints>(hero).map(|v| v.0);
+            assert_eq!(action_points, Some(expected), "action points of hero {}", hero.index());
+            self
+        }
+
+        pub fn assert_hand_size(&self, hero: Entity, expected: usize) -> &Self {
+            assert_eq!(self.hand(hero).len(), expected, "hand size of hero {}", hero.index());
+            self
+        }
+
+        pub fn assert_phase(&self, expected: GamePhases) -> &Self {
+            assert_eq!(self.world.resource::<GameState>().0, expected, "game phase");
+            self
+        }
+
+        pub fn assert_priority(&self, expected: Entity) -> &Self {
+            let holder = self.world.resource::<Priority>().priority_hero().copied();
+            assert_eq!(holder, Some(expected), "priority holder");
+            self
+        }
+
+        // The combat steps visited so far, e.g. layer -> attack -> defend -> ... -> close
+        pub fn assert_steps(&self, expected: &[CombatSteps]) -> &Self {
+            assert_eq!(self.steps(), expected, "combat steps");
+            self
+        }
+
+        pub fn assert_rejected(&self, hero: Entity, reason: validation_systems::Rejection) -> &Self {
+            assert!(
+                self.rejected.contains(&(hero, reason.clone())),
+                "hero {} was not rejected with {:?}, rejections: {:?}", hero.index(), reason, self.rejected
+            );
+            self
+        }
+
+        pub fn assert_logged(&self, description: &str, matches: impl Fn(&logging::LogEvent) -> bool) -> &Self {
+            assert!(
+                self.log().iter().any(|v| matches(&v.event)),
+                "log has no {}: {:?}", description, self.log().iter().map(|v| &v.event).collect::<Vec<_>>()
+            );
+            self
+        }
+    }
+}
+
+// Random event sequences checked against the priority invariants
+#[cfg(feature = "harness")]
+pub mod fuzz {
+    use super::*;
+    use harness::GameHarness;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    // Holding and passed hold every hero exactly once, the game is not left holding priority,
+    // and someone can act
+    fn check_priority(world: &World, heroes: &[Entity]) -> Result<(), String> {
+        let priority = world.resource::<Priority>();
+        let seats = priority.holding.iter().chain(priority.passed.iter()).collect::<Vec<&Entity>>();
+        for hero in heroes {
+            let count = seats.iter().filter(|v| **v == hero).count();
+            if count != 1 {
+                return Err(format!("Hero {} appears {} time(s) in the priority order", hero.index(), count));
+            }
+        }
+        if seats.len() != heroes.len() {
+            return Err(format!("Priority order has {} seats for {} heroes", seats.len(), heroes.len()));
+        }
+        if priority.hold {
+            return Err(String::from("The game is still holding priority"));
+        }
+        if !priority.someone_has_priority() {
+            return Err(String::from("Nobody has priority"));
+        }
+        Ok(())
+    }
+
+    // Something the acting hero could plausibly send, rejections are fine
+    pub fn random_event(world: &World, heroes: [Entity; 2], rng: &mut StdRng) -> Option<EventType> {
+        let priority = world.resource::<Priority>();
+        let hero = *priority.priority_hero()?;
+        let opponent = heroes.into_iter().find(|v| *v != hero)?;
+        let hand = world.get::<HandZone>(hero).map(|v| v.0.clone()).unwrap_or_default();
+        let card = hand.choose(rng).copied();
+
+        match priority.choice_for(&hero) {
+            Some(PendingChoice::Blocks) => {
+                let blocks = hand.into_iter().filter(|_| rng.gen_bool(0.3)).collect();
+                return Some(EventType::DeclareBlocks(DeclareBlocks { hero, blocks }));
+            },
+            Some(PendingChoice::Discard) => {
+                return card.map(|card| EventType::DiscardCard(DiscardCard { hero, card }));
+            },
+            Some(PendingChoice::ArcaneBarrier) => {
+                let resources = world.get::<Resources>(hero).map(|v| v.0).unwrap_or(0);
+                let amount = rng.gen_range(0..=resources);
+                return Some(EventType::PayArcaneBarrier(PayArcaneBarrier { hero, amount }));
+            },
+            Some(PendingChoice::Search) => {
+                let matches = world
+                    .resource::<PendingSearch>()
+                    .0
+                    .as_ref()
+                    .map(|v| v.matches.clone())
+                    .unwrap_or_default();
+                let card = matches.choose(rng).copied().filter(|_| rng.gen_bool(0.8));
+                return Some(EventType::ChooseSearch(ChooseSearch { hero, card }));
+            },
+            Some(PendingChoice::Arrange) => {
+                let mut cards = world
+                    .resource::<PendingArrangement>()
+                    .0
+                    .as_ref()
+                    .map(|v| v.cards.clone())
+                    .unwrap_or_default();
+                cards.shuffle(rng);
+                let (top, bottom) = cards.into_iter().partition(|_| rng.gen_bool(0.5));
+                return Some(EventType::ArrangeDeck(ArrangeDeck { hero, arrangement: Arrangement { top, bottom } }));
+            },
+            None => {}
+        }
+
+        let paying = world.resource::<ProposedEvent>().0.as_ref().is_some_and(|v| v.actor == hero);
+        let event = match (rng.gen_range(0..10), card) {
+            (0..=5, Some(card)) if paying => EventType::PitchCard(PitchCard { hero, card }),
+            (6, _) if paying => EventType::Cancel(CancelPlay { hero }),
+            (0..=4, Some(card)) => EventType::PlayCard(PlayCard { hero, card, targets: vec![opponent], additional_costs: Vec::new() }),
+            (5, _) => match ready_items(world, hero).choose(rng) {
+                Some(&item) => EventType::ActivateItem(ActivateItem { hero, item }),
+                None => EventType::ActivateHeroAbility(ActivateHeroAbility { hero })
+            },
+            _ => EventType::PassPriority(PassPriority { hero })
+        };
+        Some(event)
+    }
+
+    // Plays one random game, returning the invariant broken and the event count
+    pub fn play(harness: &mut GameHarness, events: usize, rng: &mut StdRng) -> Result<(), (usize, String)> {
+        let heroes = harness.heroes();
+        check_priority(harness.world(), &heroes).map_err(|err| (0, err))?;
+        for sent in 1..=events {
+            let game_over = heroes
+                .iter()
+                .any(|hero| harness.world().get::<Health>(*hero).is_some_and(|v| v.0 == 0));
+            if game_over {
+                return Ok(());
+            }
+            let event = random_event(harness.world(), heroes, rng)
+                .ok_or((sent, String::from("Nobody has priority")))?;
+            harness.enqueue([event]).run();
+            check_priority(harness.world(), &heroes).map_err(|err| (sent, err))?;
+        }
+        Ok(())
+    }
+
+    // Plays random events until the given number of turns have ended, for benchmarks
+    // Stops early when a hero dies or nobody can act, returning the turns played
+    pub fn play_turns(harness: &mut GameHarness, turns: usize, rng: &mut StdRng) -> usize {
+        let heroes = harness.heroes();
+        let mut played = 0;
+        // Set each time a turn ends
+        let mut turn = harness.world().resource::<Priority>().turn;
+        while played < turns {
+            let game_over = heroes
+                .iter()
+                .any(|hero| harness.world().get::<Health>(*hero).is_some_and(|v| v.0 == 0));
+            let Some(event) = random_event(harness.world(), heroes, rng).filter(|_| !game_over) else {
+                break;
+            };
+            harness.enqueue([event]).run();
+            let next = harness.world().resource::<Priority>().turn;
+            if next != turn {
+                turn = next;
+                played += 1;
+            }
+        }
+        played
+    }
+
+    // Usage: fuzz [games] [events per game] [seed]
+    // Failing games are saved as replays for the timeline command
+    pub fn run(args: &[String]) -> Result<String, String> {
+        let number = |index: usize, default: u64| -> Result<u64, String> {
+            args.get(index)
+                .map(|v| v.parse::<u64>().map_err(|_| format!("\"{}\" is not a number", v)))
+                .unwrap_or(Ok(default))
+        };
+        let games = number(0, 100)?;
+        let events = number(1, 200)? as usize;
+        let seed = number(2, rand::random())?;
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut failures = Vec::new();
+        for game in 0..games {
+            // Either hero may go first
+            let rolls = if rng.gen_bool(0.5) { [6, 6, 1, 1] } else { [1, 1, 6, 6] };
+            let mut harness = GameHarness::new(&rolls);
+            harness.set_verbosity(logging::Verbosity::Quiet);
+            if let Err((sent, err)) = play(&mut harness, events, &mut rng) {
+                let path = format!("fuzz-{}-{}.jsonl", seed, game);
+                let saved = harness.save_replay(&path).map(|_| path).unwrap_or_else(|err| err);
+                failures.push(format!("Game {} broke after {} event(s): {} ({})", game, sent, err, saved));
+            }
+        }
+
+        failures.push(format!("{} of {} game(s) failed, seed {}", failures.len(), games, seed));
+        Ok(failures.join("\n"))
+    }
+}
+
+// Inserts game resources and spawns the starting entities
+// Returns the hero entities
+pub fn setup_world(world: &mut World) -> [Entity; 2] {
+    // Events
+    world.insert_resource(Events::<PlayCard>::default());
+    world.insert_resource(Events::<PassPriority>::default());
+    world.insert_resource(Events::<PitchCard>::default());
+    world.insert_resource(Events::<DeclareBlocks>::default());
+    world.insert_resource(Events::<OnBlock>::default());
+    world.insert_resource(Events::<ActivateHeroAbility>::default());
+    world.insert_resource(Events::<ActivateItem>::default());
+    world.insert_resource(Events::<DiscardCard>::default());
+    world.insert_resource(Events::<PayArcaneBarrier>::default());
+    world.insert_resource(Events::<ChooseSearch>::default());
+    world.insert_resource(Events::<ArrangeDeck>::default());
+    world.insert_resource(Events::<CancelPlay>::default());
+    world.insert_resource(Events::<Resolved>::default());
+    world.insert_resource(Events::<Hit>::default());
+    world.insert_resource(Events::<EnterPlay>::default());
+    world.insert_resource(Events::<LifeGained>::default());
+    world.insert_resource(Events::<LifeLost>::default());
+    world.insert_resource(Events::<EntityDestroyed>::default());
+    world.insert_resource(Events::<ChainClosed>::default());
+    world.insert_resource(Events::<HeroDefeated>::default());
+    world.insert_resource(Events::<PitchRecycled>::default());
+    world.insert_resource(Events::<ResourcesLost>::default());
+    world.insert_resource(Events::<Rejected>::default());
+
+    // Resources
+    world.insert_resource(AttackLayer::default());
+    world.insert_resource(ProposedEvent::default());
+    world.insert_resource(PendingPitch::default());
+
+    world.insert_resource(Priority::default());
+    world.insert_resource(Stack::default());
+    world.insert_resource(GameState::default());
+    world.insert_resource(CombatState::default());
+    world.insert_resource(CombatTransition::default());
+    // The game opens by entering the start phase
+    world.insert_resource(hooks::Moves(vec![hooks::Move::EnterPhase(GamePhases::StartPhase)]));
+    world.insert_resource(Chain::default());
+    world.insert_resource(Played::default());
+    world.insert_resource(PendingArcane::default());
+    world.insert_resource(PendingSearch::default());
+    world.insert_resource(PendingArrangement::default());
+    world.insert_resource(GameLog::default());
+    // Kept if the frontend already supplied its own time and randomness
+    world.get_resource_or_insert_with(GameClock::default);
+    world.get_resource_or_insert_with(Dice::default);
+    world.get_resource_or_insert_with(DeckChoices::default);
+    world.insert_resource(ResourcePolicy::default());
+    // Kept if the frontend already configured the game
+    let config = *world.get_resource_or_insert_with(GameConfig::default);
+    let age = config.format.hero_age();
+
+    // Spawn entities
+    let attack_card = card_registry::spawn(world, "BASIC001");
+    game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Attack card entity id {}", attack_card.index());
+
+    let pitch_card = card_registry::spawn(world, "BASIC002");
+    game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Pitch card entity id {}", pitch_card.index());
+
+    let hero1 = world.spawn(
+        <hero_systems::SilvanWayfarer as HeroCard>::hero("Player 1", age)
+    ).id();
+    game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Hero 1 entity id {}", hero1.index());
+
+    let hero2 = world.spawn(
+        <hero_systems::VexNightstalker as HeroCard>::hero("Player 2", age)
+    ).id();
+    game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Hero 2 entity id {}", hero2.index());
+    config.apply(world, hero1);
+    config.apply(world, hero2);
+
+    let toxicity_red = card_registry::spawn(world, "OUT165");
+    game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Toxicity entity id {}", toxicity_red.index());
+
+    let arcane_card = card_registry::spawn(world, "BASIC003");
+    game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Arcane card entity id {}", arcane_card.index());
+
+    // Each hero starts with a copy of every card in hand
+    let hero2_cards = vec![
+        card_registry::spawn(world, "BASIC001"),
+        card_registry::spawn(world, "BASIC002"),
+        card_registry::spawn(world, "OUT165"),
+        card_registry::spawn(world, "BASIC003"),
+    ];
+    let mut reactions = || vec![
+        card_registry::spawn(world, "BASIC004"),
+        card_registry::spawn(world, "BASIC005"),
+    ];
+    let hero1_reactions = reactions();
+    let hero2_reactions = reactions();
+    for (hero, cards) in [
+        (hero1, [vec![attack_card, pitch_card, toxicity_red, arcane_card], hero1_reactions].concat()),
+        (hero2, [hero2_cards, hero2_reactions].concat())
+    ] {
+        deal_starting_hand(world, hero, cards);
+    }
+
+    // Kept if the frontend already split the heroes into teams
+    world.insert_resource(SharedLife::default());
+    if let TeamPlay::TwoVsTwo(sharing) = *world.get_resource_or_insert_with(TeamPlay::default) {
+        // Spawned last so one on one games keep their entity ids
+        let hero3 = world.spawn(
+            <hero_systems::VexNightstalker as HeroCard>::hero("Player 3", age)
+        ).id();
+        game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Hero 3 entity id {}", hero3.index());
+
+        let hero4 = world.spawn(
+            <hero_systems::SilvanWayfarer as HeroCard>::hero("Player 4", age)
+        ).id();
+        game_log!(world.resource_mut::<GameLog>(), Zones, Normal, "Hero 4 entity id {}", hero4.index());
+        config.apply(world, hero3);
+        config.apply(world, hero4);
+
+        for hero in [hero3, hero4] {
+            let cards = ["BASIC001", "BASIC002", "OUT165", "BASIC003", "BASIC004", "BASIC005"]
+                .into_iter()
+                .map(|id| card_registry::spawn(world, id))
+                .collect();
+            deal_starting_hand(world, hero, cards);
+        }
+
+        // Turns go back and forth between the teams in spawn order
+        let teams = [(hero1, Team(0)), (hero2, Team(1)), (hero3, Team(0)), (hero4, Team(1))];
+        for (hero, team) in teams {
+            world.entity_mut(hero).insert(team);
+        }
+
+        // Shared life starts as the teammates' life added together
+        if sharing == LifeSharing::Shared {
+            for team in [Team(0), Team(1)] {
+                let members = teams
+                    .iter()
+                    .filter(|v| v.1 == team)
+                    .map(|v| v.0)
+                    .collect::<Vec<Entity>>();
+                let total = members
+                    .iter()
+                    .map(|v| world.get::<Health>(*v).map(|v| v.0).unwrap_or(0))
+                    .fold(0u16, u16::saturating_add);
+                for hero in members {
+                    world.get_mut::<Health>(hero).unwrap().0 = total;
+                }
+                world.resource_mut::<SharedLife>().0.insert(team.0, total);
+            }
+        }
+    }
+
+    let heroes = all_heroes(world);
+    world.insert_resource(EntityHandles::new(heroes));
+
+    [hero1, hero2]
+}
+
+fn deal_starting_hand(world: &mut World, hero: Entity, cards: Vec<Entity>) {
+    game_log!(world.resource_mut::<GameLog>(),
+        Zones, Normal,
+        "Hand of hero {}: {:?}",
+        hero.index(),
+        cards.iter().map(|v| v.index()).collect::<Vec<u32>>()
+    );
+    for card in &cards {
+        world.entity_mut(*card).insert((Owner(hero), Controller(hero)));
+    }
+    world.get_mut::<HandZone>(hero).unwrap().0 = cards;
+}
+
+// Every hero in the game in the order they were spawned, teammates included
+pub fn all_heroes(world: &mut World) -> Vec<Entity> {
+    let mut heroes = world
+        .query_filtered::<Entity, With<Hero>>()
+        .iter(world)
+        .collect::<Vec<Entity>>();
+    heroes.sort();
+    heroes
+}
+
+pub fn start_up_schedule() -> Schedule {
+    let mut start_up_schedule = Schedule::default();
+
+    // Add systems to start up schedule
+    start_up_schedule.add_systems((
+        start_up_systems::roll_for_first,
+        start_up_systems::shuffle_decks,
+        rules_text::check_printed_text,
+    ));
+
+    start_up_schedule
+}
+
+pub fn game_schedule() -> Schedule {
+    use run_conditions::*;
+
+    // Create a new Schedule, which defines an execution strategy for Systems
+    let mut schedule = Schedule::default();
+
+    // Add systems to game schedule
+    // Read Systems
+    // These poll their events: one rejected event leaves the rest of the tick's events for the next run
+    schedule.add_systems((
+        read_systems::read_card.in_set(ScheduleSets::Read),
+        read_systems::read_priority.in_set(ScheduleSets::Read),
+        // Pitches paid for an additional cost are read in the same run as the play
+        read_systems::read_pitch
+            .in_set(ScheduleSets::Read)
+            .after(read_systems::read_card),
+        read_systems::read_blocks.in_set(ScheduleSets::Read),
+        read_systems::read_activation.in_set(ScheduleSets::Read),
+        read_systems::read_item_activation.in_set(ScheduleSets::Read),
+        read_systems::read_discard.in_set(ScheduleSets::Read),
+        read_systems::read_barrier.in_set(ScheduleSets::Read),
+        read_systems::read_search.in_set(ScheduleSets::Read),
+        read_systems::read_arrangement.in_set(ScheduleSets::Read),
+        read_systems::read_cancel.in_set(ScheduleSets::Read),
+        read_systems::log_rejections
+            .after(ScheduleSets::Read)
+            .run_if(on_event::<Rejected>()),
+    ));
+    // Evaluate read systems
+    schedule.add_systems(
+        game_systems::evaluate_cost
+            .in_set(ScheduleSets::Process)
+            .run_if(play_proposed),
+    );
+    schedule.add_systems((
+        // Start phase triggers
+        state_change_systems::start_start_phase
+            .in_set(ScheduleSets::StartPhase)
+            .run_if(resource_changed::<GameState>),
+        state_change_systems::end_start_phase
+            .after(ScheduleSets::StartPhase)
+            .before(ScheduleSets::ActionPhase)
+            .run_if(in_phase(GamePhases::StartPhase)),
+
+        // Action phase triggers
+        state_change_systems::start_action_phase
+            .in_set(ScheduleSets::ActionPhase)
+            .run_if(resource_changed::<GameState>),
+
+        // Combat triggers
+        // Steps move on once everyone has passed, so the transition is only worked out when priority changes
+        // Each step system then waits for its own transition
+        combat_systems::trigger_layer_step
+            .after(ScheduleSets::ActionPhase)
+            .run_if(resource_changed::<AttackLayer>),
+        combat_systems::queue_transition
+            .after(combat_systems::trigger_layer_step)
+            .run_if(resource_changed::<Priority>),
+        combat_systems::trigger_attack_step
+            .after(combat_systems::queue_transition)
+            .run_if(transition_is(StepTransition::Passed(CombatSteps::LayerStep))),
+        combat_systems::trigger_defend_step
+            .after(combat_systems::queue_transition)
+            .run_if(transition_is(StepTransition::Passed(CombatSteps::AttackStep))),
+        combat_systems::finish_blocks
+            .after(combat_systems::queue_transition)
+            .run_if(transition_is(StepTransition::BlocksDeclared)),
+        combat_systems::trigger_reaction_step
+            .after(combat_systems::queue_transition)
+            .run_if(transition_is(StepTransition::Passed(CombatSteps::DefendStep))),
+        combat_systems::trigger_prevention_step
+            .after(combat_systems::queue_transition)
+            .run_if(transition_is(StepTransition::Passed(CombatSteps::ReactionStep))),
+        combat_systems::trigger_damage_step
+            .after(combat_systems::queue_transition)
+            .before(ScheduleSets::OnHit)
+            .run_if(transition_is(StepTransition::Passed(CombatSteps::PreventionStep))),
+        combat_systems::trigger_resolution_step
+            .after(ScheduleSets::ActionPhase)
+            .after(ScheduleSets::OnHit)
+            .run_if(in_step(CombatSteps::DamageStep)),
+        combat_systems::trigger_link_step
+            .after(combat_systems::queue_transition)
+            .run_if(transition_is(StepTransition::Passed(CombatSteps::ResolutionStep))),
+        combat_systems::trigger_close_step
+            .after(combat_systems::queue_transition)
+            .run_if(transition_is(StepTransition::Passed(CombatSteps::LinkStep))),
+        // Nobody gets priority in the close step, so the chain closes as soon as it is reached
+        combat_systems::close_combat_chain
+            .after(ScheduleSets::ActionPhase)
+            .after(combat_systems::trigger_close_step)
+            .run_if(in_step(CombatSteps::CloseStep)),
+
+        state_change_systems::end_action_phase
+            .after(ScheduleSets::ActionPhase)
+            .before(ScheduleSets::EndPhase)
+            .run_if(resource_changed::<Priority>),
+
+        // End phase triggers
+        state_change_systems::start_end_phase
+            .in_set(ScheduleSets::EndPhase)
+            .run_if(resource_changed::<GameState>),
+        state_change_systems::end_end_phase
+            .after(ScheduleSets::EndPhase)
+            .run_if(in_phase(GamePhases::EndPhase)),
+
+        // Misc
+        game_systems::resolve_stack.run_if(stack_has_cards),
+        arcane_systems::deal_arcane_damage
+            .after(game_systems::resolve_stack)
+            .run_if(on_event::<Resolved>()),
+    ));
+    schedule.add_systems((
+        combat_systems::resolve_reactions
+            .after(game_systems::resolve_stack)
+            .run_if(on_event::<Resolved>()),
+        effect_systems::resolve_effects
+            .after(game_systems::resolve_stack)
+            .run_if(on_event::<Resolved>()),
+        arcane_systems::resolve_arcane_damage
+            .after(ScheduleSets::Read)
+            .run_if(resource_changed::<PendingArcane>),
+        duration_systems::expire_effects.run_if(
+            resource_changed::<GameState>
+                .or_else(on_event::<ChainClosed>())
+                .or_else(resource_changed::<CombatState>)
+        ),
+        duration_systems::reset_usage_limits.run_if(resource_changed::<GameState>),
+        game_systems::log_priority_hold.run_if(resource_changed::<Priority>),
+        duration_systems::ready_items.in_set(hooks::OnEnter(GamePhases::ActionPhase)),
+        duration_systems::forget_chain_history.run_if(resource_changed::<GameState>),
+        team_systems::share_life
+            .after(permanent_systems::destroy_permanents)
+            .run_if(life_shared),
+        game_systems::report_lost_resources
+            .after(state_change_systems::end_end_phase)
+            .after(combat_systems::trigger_resolution_step)
+            .run_if(on_event::<ResourcesLost>()),
+        permanent_systems::destroy_permanents
+            .after(ScheduleSets::OnHit)
+            .after(ScheduleSets::EnterPlay)
+            .after(effect_systems::resolve_effects)
+            .after(arcane_systems::resolve_arcane_damage)
+            .run_if(any_changed::<Health>),
+        permanent_systems::defeat_heroes
+            .after(permanent_systems::destroy_permanents)
+            .after(team_systems::share_life)
+            .run_if(any_changed::<Health>),
+    ));
+
+    schedule.configure_sets(ScheduleSets::EnterPlay.after(effect_systems::resolve_effects));
+
+    // Scripted card effects
+    schedule.add_systems((
+        effect_script::run_on_play
+            .after(game_systems::resolve_stack)
+            .run_if(on_event::<Resolved>()),
+        effect_script::grant_next_attack.run_if(
+            resource_changed::<AttackLayer>.or_else(any_changed::<effect_script::PendingGrant>)
+        ),
+        effect_script::run_on_hit
+            .in_set(ScheduleSets::OnHit)
+            .run_if(on_event::<Hit>()),
+    ));
+
+    // Attachments settle before any combat math
+    schedule.add_systems((
+        attachment_systems::detach_from_departed.before(ScheduleSets::ActionPhase),
+        attachment_systems::recalculate_stats
+            .after(attachment_systems::detach_from_departed)
+            .before(ScheduleSets::ActionPhase),
+    ));
+
+    // Card systems can be scheduled on entering or leaving any phase or step
+    hooks::configure(&mut schedule);
+
+    <card_systems::ToxicityRed as Card>::add_systems(&mut schedule);
+    <hero_systems::SilvanWayfarer as HeroCard>::add_systems(&mut schedule);
+    <hero_systems::VexNightstalker as HeroCard>::add_systems(&mut schedule);
+
+    schedule
+}
+
+
+// Number of schedule runs allowed for the game to hand priority back to a player
+pub const MAX_SETTLE_RUNS: usize = 64;
+
+// Runs the rules until a player holds priority, as the game loop does between inputs
+// Gives up after MAX_SETTLE_RUNS runs, so a stuck game cannot hang the caller
+// Returns the number of runs
+pub fn settle(world: &mut World, schedule: &mut Schedule) -> usize {
+    let mut runs = 0;
+    while runs < MAX_SETTLE_RUNS && !world.resource::<Priority>().someone_has_priority() {
+        schedule.run(world);
+        runs += 1;
+    }
+    runs
+}
+
+// Everything about a game that is decided before the heroes are spawned
+// Anything left at its default gives a one on one game of the default format
+#[derive(Default)]
+pub struct GameSetup {
+    // Starting life, intellect, action points and format
+    pub config: GameConfig,
+    // How the heroes are split up, e.g. TeamPlay::TwoVsTwo
+    pub teams: TeamPlay,
+    pub policy: ResourcePolicy,
+    // Deck lists in hero order, checked against the format
+    // Heroes without one only have their starting hand
+    pub decks: Vec<deck_list::DeckList>,
+    // Time and randomness, for builds without system time or OS randomness, e.g. wasm32-unknown-unknown
+    pub clock: Option<Box<dyn clock::Clock>>,
+    pub random: Option<Box<dyn dice::RandomSource>>
+}
+
+// The rules engine without a terminal, for frontends that embed it
+// Player events go in through send and are acted on at the next tick
+// Saving, loading and the other game loop commands are left to the frontend
+pub struct GameEngine {
+    world: World,
+    schedule: Schedule,
+    heroes: Vec<Entity>,
+    rejections: bevy_ecs::event::ManualEventReader<Rejected>,
+    // The table and turn order as rolled at setup, where a replay starts from
+    table: replay::TableSetup,
+    turn_order: VecDeque<Entity>,
+    recorder: Option<replay::Recorder>
+}
+
+impl GameEngine {
+    // Sets up a one on one game and runs the start up rules, ready for the first player to act
+    pub fn new() -> Self {
+        Self::setup(GameSetup::default()).expect("A game without deck lists has nothing to reject")
+    }
+
+    // Sets up the game as described and runs the start up rules
+    // Fails if a deck list is not legal in the format
+    pub fn setup(setup: GameSetup) -> Result<Self, String> {
+        let mut world = World::new();
+        if let Some(clock) = setup.clock {
+            world.insert_resource(GameClock(clock));
+        }
+        if let Some(random) = setup.random {
+            world.insert_resource(Dice(random));
+        }
+        let mut table = replay::TableSetup {
+            config: setup.config,
+            teams: setup.teams,
+            policy: setup.policy,
+            decks: setup.decks,
+            deck_order: Vec::new()
+        };
+        let heroes = table.set(&mut world)?;
+
+        start_up_schedule().run(&mut world);
+        table.keep_deck_order(&world, &heroes);
+        let turn_order = world.resource::<Priority>().holding.clone();
+        let mut schedule = game_schedule();
+        schedule.run(&mut world);
+        let rejections = world.resource::<Events<Rejected>>().get_reader();
+        Ok(GameEngine { world, schedule, heroes, rejections, table, turn_order, recorder: None })
+    }
+
+    // Writes every event sent from here on to a replay, for auditing with the timeline command
+    // Replays start from the table as set up, so recording should start before anything is sent
+    pub fn record(&mut self, path: &str) -> Result<(), String> {
+        self.recorder = Some(replay::Recorder::create(path, &self.table, &self.turn_order)?);
+        Ok(())
+    }
+
+    // Every hero at the table in spawn order, including a team game's third and fourth player
+    pub fn heroes(&self) -> &[Entity] {
+        &self.heroes
+    }
+
+    // Hero whose turn it is
+    pub fn turn_player(&self) -> Entity {
+        *self.world.resource::<Priority>().turn_player()
+    }
+
+    // Returns false once the game has ended
+    // Recorded first, if recording, with any failure to write it logged
+    pub fn send(&mut self, event: EventType) -> bool {
+        if let Some(Err(err)) = self.recorder.as_mut().map(|v| v.record(&event)) {
+            game_log!(self.world.resource_mut::<GameLog>(), Actions, Normal, "{}", err);
+        }
+        event.send(&mut self.world)
+    }
+
+    // Runs the rules once over whatever has been sent
+    pub fn tick(&mut self) {
+        self.schedule.run(&mut self.world);
+    }
+
+    // Runs the rules until a player needs to act, returning who holds priority
+    // Passes for players whose stops skip this point
+    // Gives up after a bounded number of runs, so a stuck game cannot hang the frontend
+    pub fn step(&mut self) -> Option<Entity> {
+        for _ in 0..MAX_SETTLE_RUNS {
+            settle(&mut self.world, &mut self.schedule);
+            let Some(event) = auto_pass(&self.world) else {
+                break;
+            };
+            self.send(event);
+            self.tick();
+        }
+        let priority = self.world.resource::<Priority>();
+        priority.someone_has_priority().then(|| priority.priority_hero().copied()).flatten()
+    }
+
+    // The game as the viewer is allowed to see it
+    pub fn state(&mut self, viewer: state_view::Viewer) -> serde_json::Value {
+        state_view::snapshot(&mut self.world, viewer)
+    }
+
+    // Events the rules refused since the last call, with the hero who sent each one
+    pub fn rejections(&mut self) -> Vec<(Entity, validation_systems::Rejection)> {
+        let events = self.world.resource::<Events<Rejected>>();
+        self.rejections.read(events).map(|v| (v.hero, v.reason.clone())).collect()
+    }
+
+    pub fn hand_sizes(&mut self) -> Vec<(Entity, usize)> {
+        state_view::hand_sizes(&mut self.world)
+    }
+
+    pub fn visible_hand(&mut self, viewer: state_view::Viewer, hero: Entity) -> Vec<Entity> {
+        state_view::visible_hand(&mut self.world, viewer, hero)
+    }
+
+    // Lets the frontend arrange looked at cards, e.g. for a computer player
+    pub fn set_deck_arranger(&mut self, arranger: Box<dyn DeckArranger>) {
+        self.world.insert_resource(DeckChoices(arranger));
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+}
+
+impl Default for GameEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+
+    fn damage_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(GameLog::default());
+        world.insert_resource(GameClock(Box::new(clock::FrozenClock::default())));
+        world
+    }
+
+    fn spawn_hero(world: &mut World, life: u16) -> Entity {
+        world.spawn((CardName(String::from("Target")), Health(life))).id()
+    }
+
+    fn shield(world: &mut World, hero: Entity, amount: u16, order: u32) -> Entity {
+        world.spawn(DamageModifier::Prevent { hero, amount, order }).id()
+    }
+
+    fn deal(world: &mut World, target: Entity, amount: u16) -> (Entity, u16) {
+        world.run_system_once(move |
+            mut modifier_query: Query<(Entity, &mut DamageModifier)>,
+            mut health_query: Query<(&CardName, &mut Health)>,
+            mut commands: Commands,
+            mut log: ResMut<GameLog>,
+            clock: Res<GameClock>
+        | apply_damage(target, amount, &mut modifier_query, &mut health_query, &mut commands, &mut log, &clock))
+    }
+
+    fn life(world: &World, hero: Entity) -> u16 {
+        world.get::<Health>(hero).unwrap().0
+    }
+
+    #[test]
+    fn lose_stops_at_zero() {
+        let mut health = Health(3);
+        assert_eq!(health.lose(5), 3);
+        assert_eq!(health.0, 0);
+        assert_eq!(health.lose(1), 0);
+        assert_eq!(health.0, 0);
+    }
+
+    #[test]
+    fn overkill_damage_is_dealt_in_full() {
+        let mut world = damage_world();
+        let hero = spawn_hero(&mut world, 2);
+
+        assert_eq!(deal(&mut world, hero, 7), (hero, 7));
+        assert_eq!(life(&world, hero), 0);
+
+        // Damage to a hero already at zero does not wrap around
+        assert_eq!(deal(&mut world, hero, u16::MAX), (hero, u16::MAX));
+        assert_eq!(life(&world, hero), 0);
+    }
+
+    #[test]
+    fn shield_takes_its_share_of_overkill_damage() {
+        let mut world = damage_world();
+        let hero = spawn_hero(&mut world, 3);
+        let first = shield(&mut world, hero, 2, 0);
+        let second = shield(&mut world, hero, 1, 1);
+
+        assert_eq!(deal(&mut world, hero, 10), (hero, 7));
+        assert_eq!(life(&world, hero), 0);
+        // Both shields are used up
+        assert!(world.get_entity(first).is_none());
+        assert!(world.get_entity(second).is_none());
+    }
+
+    #[test]
+    fn partly_used_shield_keeps_the_rest() {
+        let mut world = damage_world();
+        let hero = spawn_hero(&mut world, 1);
+        let shield = shield(&mut world, hero, 5, 0);
+
+        assert_eq!(deal(&mut world, hero, 3), (hero, 0));
+        assert_eq!(life(&world, hero), 1);
+        assert!(matches!(world.get::<DamageModifier>(shield), Some(DamageModifier::Prevent { amount: 2, .. })));
+
+        // The rest of the shield only softens the overkill
+        assert_eq!(deal(&mut world, hero, 4), (hero, 2));
+        assert_eq!(life(&world, hero), 0);
+        assert!(world.get_entity(shield).is_none());
+    }
+
+    #[test]
+    fn increases_and_shields_apply_before_overkill() {
+        let mut world = damage_world();
+        let hero = spawn_hero(&mut world, 4);
+        world.spawn(DamageModifier::Increase { hero, amount: 3 });
+        shield(&mut world, hero, 2, 0);
+
+        assert_eq!(deal(&mut world, hero, 6), (hero, 7));
+        assert_eq!(life(&world, hero), 0);
+    }
+
+    #[test]
+    fn stacked_increases_saturate_instead_of_overflowing() {
+        let mut world = damage_world();
+        let hero = spawn_hero(&mut world, 5);
+        world.spawn(DamageModifier::Increase { hero, amount: u16::MAX });
+        world.spawn(DamageModifier::Increase { hero, amount: 10 });
+
+        assert_eq!(deal(&mut world, hero, 3), (hero, u16::MAX));
+        assert_eq!(life(&world, hero), 0);
+    }
+
+    #[test]
+    fn shields_soften_saturated_overkill() {
+        let mut world = damage_world();
+        let hero = spawn_hero(&mut world, 5);
+        world.spawn(DamageModifier::Increase { hero, amount: u16::MAX - 1 });
+        let shield = shield(&mut world, hero, 4, 0);
+
+        assert_eq!(deal(&mut world, hero, 2), (hero, u16::MAX - 4));
+        assert_eq!(life(&world, hero), 0);
+        assert!(world.get_entity(shield).is_none());
+    }
+
+    #[test]
+    fn rules_text_amounts_must_fit_the_effect() {
+        use rules_text::Clause;
+        assert_eq!(Clause::parse("Deal 2 arcane damage to target hero."), Ok(Clause::ArcaneDamage(2)));
+        assert_eq!(Clause::parse("Attached card has -1 attack."), Ok(Clause::AttackBonus(-1)));
+        assert_eq!(Clause::parse("Attached card has +2 defense."), Ok(Clause::DefenseBonus(2)));
+
+        // These used to wrap around into small amounts
+        assert!(Clause::parse("Deal -65535 arcane damage to target hero.").is_err());
+        assert!(Clause::parse("Deal 65537 arcane damage to target hero.").is_err());
+        assert!(Clause::parse("Gain -1 life.").is_err());
+        assert!(Clause::parse("Attached card has +40000 attack.").is_err());
+    }
+
+    // A link as it stands once the attack has resolved
+    fn resolved_link(attacker: Entity, attack: Entity, damage: u16, closed: bool) -> ChainLink {
+        ChainLink { hit: damage > 0, closed, damage, ..ChainLink::attack(Vec::new(), attacker, attack) }
+    }
+
+    // One chain closed earlier this turn with a hit, and an open chain with one link still going
+    fn chain_with_history(world: &mut World) -> (Chain, [Entity; 2]) {
+        let heroes = [world.spawn_empty().id(), world.spawn_empty().id()];
+        let ranger = world.spawn(CardClass::SingleClass(CardClassTypes::Ranger)).id();
+        let assassin = world.spawn(CardClass::SingleClass(CardClassTypes::Assassin)).id();
+        let dual_class = world.spawn(CardClass::DualClass((CardClassTypes::Ranger, CardClassTypes::Generic))).id();
+        let mut chain = Chain {
+            history: vec![resolved_link(heroes[0], ranger, 3, true)],
+            ..Chain::default()
+        };
+        chain.add_chain_link(resolved_link(heroes[0], assassin, 0, true));
+        chain.add_chain_link(resolved_link(heroes[1], dual_class, 0, false));
+        (chain, heroes)
+    }
+
+    #[test]
+    fn turn_queries_include_closed_chains() {
+        let mut world = World::new();
+        let (chain, [first, second]) = chain_with_history(&mut world);
+
+        assert_eq!(chain.links_this_turn().count(), 3);
+        assert_eq!(chain.closed_links_this_turn(), 2);
+        assert_eq!(chain.attacks_this_turn(first), 2);
+        assert_eq!(chain.attacks_this_turn(second), 1);
+        // Only the closed chain's attack hit
+        assert!(chain.hit_this_turn());
+        assert!(!chain.links.iter().any(ChainLink::hit));
+
+        let classes = world.run_system_once(move |class_query: Query<&CardClass>| chain.attack_classes(&class_query));
+        assert_eq!(classes, vec![CardClassTypes::Ranger, CardClassTypes::Assassin, CardClassTypes::Generic]);
+    }
+
+    #[test]
+    fn combat_damage_only_counts_the_current_chain() {
+        let mut world = World::new();
+        let (mut chain, [first, _]) = chain_with_history(&mut world);
+        assert_eq!(chain.combat_damage(), 0);
+
+        chain.links[1].damage = 4;
+        chain.add_chain_link(resolved_link(first, chain.links[0].attack, 2, false));
+        assert_eq!(chain.combat_damage(), 6);
+    }
+
+    #[test]
+    fn nothing_hit_on_a_new_turn() {
+        let chain = Chain::default();
+        assert!(!chain.hit_this_turn());
+        assert_eq!(chain.closed_links_this_turn(), 0);
+        assert_eq!(chain.combat_damage(), 0);
+    }
+
+    // Card text starts with the printed text, so the two can never disagree
+    #[test]
+    fn card_text_includes_printed_text() {
+        let mut world = World::new();
+        for entry in card_registry::REGISTRY {
+            let card = entry.spawn(&mut world);
+            let text = world.get::<CardText>(card).map(|v| v.0.as_str()).unwrap_or("");
+            if let Some(PrintedText(printed)) = world.get::<PrintedText>(card) {
+                assert!(text.starts_with(printed.as_str()), "{} text \"{}\" lacks \"{}\"", entry.id, text, printed);
+                assert!(!entry.text.contains(printed.as_str()), "{} repeats its printed text", entry.id);
+            }
+            assert_eq!(card_registry::text(entry.id).unwrap_or_default(), text, "{}", entry.id);
+        }
+    }
+
+    fn seeded_setup() -> GameSetup {
+        GameSetup {
+            clock: Some(Box::new(clock::FrozenClock::default())),
+            random: Some(Box::new(dice::SeededRandom::new(1))),
+            ..GameSetup::default()
+        }
+    }
+
+    // A team game can also change the starting life
+    #[test]
+    fn setup_combines_teams_and_config() {
+        let engine = GameEngine::setup(GameSetup {
+            config: GameConfig { life: Some(20), ..GameConfig::default() },
+            teams: TeamPlay::TwoVsTwo(LifeSharing::Separate),
+            ..seeded_setup()
+        }).unwrap();
+        assert_eq!(engine.heroes().len(), 4);
+        for hero in engine.heroes() {
+            assert_eq!(engine.world().get::<Health>(*hero).map(|v| v.0), Some(20));
+        }
+    }
+
+    #[test]
+    fn setup_rejects_illegal_decks() {
+        let err = GameEngine::setup(GameSetup {
+            decks: vec![deck_list::DeckList(vec![(CardId(String::from("NOPE001")), 1)])],
+            ..seeded_setup()
+        }).err().unwrap();
+        assert!(err.contains("Unknown card \"NOPE001\""), "{}", err);
+    }
+
+    fn replay_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("gen-{}-{}.jsonl", name, std::process::id())).display().to_string()
+    }
+
+    // The header sets the table up again, so a team game replays with all four heroes
+    #[test]
+    fn timeline_replays_a_team_game() {
+        let path = replay_path("team-game");
+        let mut engine = GameEngine::setup(GameSetup {
+            teams: TeamPlay::TwoVsTwo(LifeSharing::Separate),
+            ..seeded_setup()
+        }).unwrap();
+        engine.record(&path).unwrap();
+        for _ in 0..4 {
+            let hero = engine.step().expect("A hero should hold priority");
+            engine.send(EventType::PassPriority(PassPriority { hero }));
+            engine.tick();
+        }
+
+        let timeline = replay::timeline(&path);
+        std::fs::remove_file(&path).ok();
+        let timeline = timeline.unwrap();
+        for name in ["Player 1", "Player 2", "Player 3", "Player 4"] {
+            assert!(timeline.contains(&format!("{} sent pass", name)), "{}", timeline);
+        }
+    }
+
+    #[test]
+    fn timeline_rejects_heroes_not_at_the_table() {
+        let path = replay_path("unknown-hero");
+        std::fs::write(&path, "{\"version\":3,\"turn_order\":[2,99],\"setup\":{}}\n").unwrap();
+        let err = replay::timeline(&path);
+        std::fs::remove_file(&path).ok();
+        let err = err.unwrap_err();
+        assert!(err.contains("hero 99"), "{}", err);
+    }
+}
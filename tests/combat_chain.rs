@@ -0,0 +1,129 @@
+// A full combat chain driven one priority pass at a time, run with `cargo test --features harness`
+use bevy_ecs::entity::Entity;
+use gen::harness::GameHarness;
+use gen::validation_systems::Rejection;
+use gen::{CombatSteps, InChain, InGraveyard};
+
+use CombatSteps::*;
+
+// Vex goes first and attacks Silvan
+fn new_game() -> (GameHarness, Entity, Entity) {
+    let harness = GameHarness::new(&[6, 6, 1, 1]);
+    let [defender, attacker] = harness.heroes();
+    (harness, attacker, defender)
+}
+
+fn send(harness: &mut GameHarness, commands: &[String]) {
+    let commands = commands.iter().map(String::as_str).collect::<Vec<&str>>();
+    harness.enqueue_commands(&commands).expect("Commands should parse").run();
+}
+
+// Both heroes pass, the attacker first, moving the chain on a step
+fn pass_step(harness: &mut GameHarness, attacker: Entity, defender: Entity) {
+    send(harness, &[format!("{} pass", attacker.index()), format!("{} pass", defender.index())]);
+}
+
+fn in_chain(harness: &GameHarness, card: Entity) -> bool {
+    harness.world().get::<InChain>(card).is_some()
+}
+
+fn in_graveyard(harness: &GameHarness, card: Entity) -> bool {
+    harness.world().get::<InGraveyard>(card).is_some()
+}
+
+#[test]
+fn blocked_attack_from_layer_to_close() {
+    let (mut harness, attacker, defender) = new_game();
+    let attack = harness.hand(attacker)[0];
+    let resource = harness.hand(attacker)[1];
+    let block = harness.hand(defender)[0];
+
+    // Layer: the attack goes on the stack once paid for
+    send(&mut harness, &[
+        format!("{} play {} {}", attacker.index(), attack.index(), defender.index()),
+        format!("{} pitch {}", attacker.index(), resource.index())
+    ]);
+    harness
+        .assert_steps(&[LayerStep])
+        .assert_priority(attacker)
+        .assert_hand_size(attacker, 4)
+        .assert_resources(attacker, 1);
+    assert!(!in_chain(&harness, attack));
+
+    // Attack: the attack becomes the first chain link
+    pass_step(&mut harness, attacker, defender);
+    harness.assert_steps(&[LayerStep, AttackStep]).assert_priority(attacker);
+    assert!(in_chain(&harness, attack));
+
+    // Defend: only the defender acts, blocking from hand
+    pass_step(&mut harness, attacker, defender);
+    harness.assert_steps(&[LayerStep, AttackStep, DefendStep]).assert_priority(defender);
+    send(&mut harness, &[format!("{} block {}", defender.index(), block.index())]);
+    harness.assert_priority(attacker).assert_hand_size(defender, 5);
+    assert!(!harness.hand(defender).contains(&block));
+    assert!(in_chain(&harness, block));
+
+    // Reaction and prevention: nothing played, life untouched
+    pass_step(&mut harness, attacker, defender);
+    harness.assert_steps(&[LayerStep, AttackStep, DefendStep, ReactionStep]).assert_priority(attacker);
+    pass_step(&mut harness, attacker, defender);
+    harness
+        .assert_steps(&[LayerStep, AttackStep, DefendStep, ReactionStep, PreventionStep])
+        .assert_priority(attacker)
+        .assert_life(defender, 40);
+
+    // Damage and resolution: what gets past the block is dealt to the defender
+    pass_step(&mut harness, attacker, defender);
+    harness
+        .assert_steps(&[LayerStep, AttackStep, DefendStep, ReactionStep, PreventionStep, DamageStep, ResolutionStep])
+        .assert_priority(attacker)
+        .assert_life(defender, 39)
+        .assert_life(attacker, 40);
+
+    // Link
+    pass_step(&mut harness, attacker, defender);
+    harness
+        .assert_steps(&[
+            LayerStep, AttackStep, DefendStep, ReactionStep, PreventionStep, DamageStep, ResolutionStep, LinkStep
+        ])
+        .assert_priority(attacker);
+    assert!(in_chain(&harness, attack));
+
+    // Close: the chain's cards go to their owners' graveyards and the turn player gets priority back
+    pass_step(&mut harness, attacker, defender);
+    harness
+        .assert_steps(&[
+            LayerStep, AttackStep, DefendStep, ReactionStep, PreventionStep, DamageStep, ResolutionStep, LinkStep,
+            CloseStep
+        ])
+        .assert_priority(attacker);
+    assert_eq!(harness.graveyard(attacker), vec![attack]);
+    assert_eq!(harness.graveyard(defender), vec![block]);
+    for card in [attack, block] {
+        assert!(!in_chain(&harness, card));
+        assert!(in_graveyard(&harness, card));
+    }
+    assert!(harness.rejected().is_empty(), "{:?}", harness.rejected());
+}
+
+#[test]
+fn blocks_outside_the_defend_step_are_rejected() {
+    let (mut harness, attacker, defender) = new_game();
+    let attack = harness.hand(attacker)[0];
+    let resource = harness.hand(attacker)[1];
+    let block = harness.hand(defender)[0];
+
+    send(&mut harness, &[
+        format!("{} play {} {}", attacker.index(), attack.index(), defender.index()),
+        format!("{} pitch {}", attacker.index(), resource.index())
+    ]);
+    pass_step(&mut harness, attacker, defender);
+    harness.assert_steps(&[LayerStep, AttackStep]);
+
+    send(&mut harness, &[format!("{} block {}", defender.index(), block.index())]);
+    harness
+        .assert_rejected(defender, Rejection::NotDefending)
+        .assert_priority(attacker)
+        .assert_hand_size(defender, 6);
+    assert!(!in_chain(&harness, block));
+}
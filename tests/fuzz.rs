@@ -0,0 +1,40 @@
+// The fuzz command's priority invariants over fixed seeds, run with `cargo test --features harness`
+// Use `gen fuzz` for longer runs with fresh seeds
+use gen::fuzz;
+use gen::harness::GameHarness;
+use gen::logging::Verbosity;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const GAMES: u64 = 16;
+const EVENTS: usize = 300;
+
+// Plays a seeded random game for each seed, saving a replay of any that break an invariant
+fn check_games(rolls: [u32; 4], seeds: std::ops::Range<u64>) {
+    for seed in seeds {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut harness = GameHarness::new(&rolls);
+        harness.set_verbosity(Verbosity::Quiet);
+        if let Err((sent, err)) = fuzz::play(&mut harness, EVENTS, &mut rng) {
+            let path = format!("fuzz-test-{}.jsonl", seed);
+            let saved = harness.save_replay(&path).map(|_| path).unwrap_or_else(|err| err);
+            panic!("Seed {} broke after {} event(s): {} ({})", seed, sent, err, saved);
+        }
+    }
+}
+
+#[test]
+fn priority_invariants_hold_when_the_first_hero_starts() {
+    check_games([1, 1, 6, 6], 0..GAMES);
+}
+
+#[test]
+fn priority_invariants_hold_when_the_second_hero_starts() {
+    check_games([6, 6, 1, 1], GAMES..GAMES * 2);
+}
+
+#[test]
+fn fuzz_command_reports_no_failures() {
+    let report = fuzz::run(&[String::from("4"), String::from("100"), String::from("42")]).unwrap();
+    assert_eq!(report.lines().last(), Some("0 of 4 game(s) failed, seed 42"), "{}", report);
+}